@@ -0,0 +1,79 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use graph::Graph;
+
+/// An exported symbol with its version and size, displayed as
+/// `symbol@VERSION (N bytes)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Export<'a> {
+    pub symbol: &'a str,
+    pub version: Option<&'a str>,
+    /// The size of the data objects and functions, 0 when unknown.
+    pub size: u64,
+}
+
+impl<'a> fmt::Display for Export<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.symbol)?;
+        if let Some(version) = self.version {
+            write!(f, "@{}", version)?;
+        }
+        if self.size > 0 {
+            write!(f, " ({} bytes)", self.size)?;
+        }
+        Ok(())
+    }
+}
+
+/// A difference between the exports of two versions of a library.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AbiChange<'a> {
+    Removed(Export<'a>),
+    /// The old and the new definition, of another version or size.
+    Changed(Export<'a>, Export<'a>),
+    Added(Export<'a>),
+}
+
+/// The exports removed, changed and added from the `old` graph to the `new`
+/// one, the removed and changed ones by symbol then the added ones.
+pub fn abi_changes<'a>(old: &'a Graph, new: &'a Graph) -> Vec<AbiChange<'a>> {
+    let (old_exports, new_exports) = (exports(old), exports(new));
+    let mut changes = vec![];
+    for (symbol, export) in &old_exports {
+        match new_exports.get(symbol) {
+            None => changes.push(AbiChange::Removed(*export)),
+            Some(new_export) if new_export != export => changes.push(AbiChange::Changed(*export, *new_export)),
+            Some(_) => (),
+        }
+    }
+    for (_, export) in new_exports.iter().filter(|(s, _)| !old_exports.contains_key(*s)) {
+        changes.push(AbiChange::Added(*export));
+    }
+    changes
+}
+
+/// The consumers of `library` importing one of the `removed` symbols from
+/// it, as sorted label and symbol pairs.
+pub fn broken_imports<'a>(graph: &'a Graph, library: u32, removed: &[&str]) -> Vec<(&'a str, &'a str)> {
+    let mut broken: Vec<(&str, &str)> = graph.edges()
+        .filter(|((_, n2), _)| *n2 == library)
+        .flat_map(|((n1, _), p)| p.symbols().map(move |s| (n1, *s)))
+        .filter_map(|(n, s)| Some((graph.label(n)?, graph.symbol(s)?)))
+        .filter(|(_, s)| removed.contains(s))
+        .collect();
+    broken.sort();
+    broken
+}
+
+// the exported symbols of every node with their version and size
+fn exports(graph: &Graph) -> BTreeMap<&str, Export<'_>> {
+    graph.nodes()
+        .flat_map(|(_, p)| p.symbols().map(move |s| (*s, p)))
+        .filter_map(|(s, p)| {
+            let version = p.definition(s).and_then(|d| d.version).and_then(|v| graph.symbol(v));
+            let symbol = graph.symbol(s)?;
+            Some((symbol, Export { symbol, version, size: p.size(s) }))
+        })
+        .collect()
+}
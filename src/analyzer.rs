@@ -0,0 +1,273 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use graph::Graph;
+
+/// How serious a [`Finding`] is, `analyze --check` fails on warnings and errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// A problem or a remark reported by an [`Analyzer`].
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub severity: Severity,
+    /// The nodes the finding is about, empty for the whole graph.
+    pub nodes: Vec<u32>,
+    pub message: String,
+}
+
+/// A check run over the finished graph.
+///
+/// Implement it to ship custom checks, eg. naming conventions or forbidden
+/// dependencies, and register them into [`Analyzers`]:
+///
+/// ```
+/// # use symbols_graph::{Analyzer, Analyzers, Finding, Graph, Severity};
+/// struct NoTestLibraries;
+///
+/// impl Analyzer for NoTestLibraries {
+///     fn name(&self) -> &str {
+///         "test libraries"
+///     }
+///
+///     fn analyze(&self, graph: &Graph) -> Vec<Finding> {
+///         graph.nodes()
+///             .filter(|(n, _)| graph.label(*n).is_some_and(|l| l.contains("test")))
+///             .map(|(n, _)| Finding { severity: Severity::Warning, nodes: vec![n], message: format!("{:?}", graph.label(n)) })
+///             .collect()
+///     }
+/// }
+///
+/// let mut analyzers = Analyzers::default();
+/// analyzers.register(Box::new(NoTestLibraries));
+/// for (name, findings) in analyzers.run(&Graph::new("deps")) {
+///     println!("{}: {}", name, findings.len());
+/// }
+/// ```
+pub trait Analyzer {
+    /// A short name, used to title the findings.
+    fn name(&self) -> &str;
+    fn analyze(&self, graph: &Graph) -> Vec<Finding>;
+}
+
+/// The analyzers run in registration order, the builtin ones by default.
+pub struct Analyzers {
+    analyzers: Vec<Box<dyn Analyzer>>,
+}
+
+impl Analyzers {
+    /// No analyzer at all, not even the builtin ones.
+    pub fn empty() -> Self {
+        Self { analyzers: vec![] }
+    }
+
+    pub fn register(&mut self, analyzer: Box<dyn Analyzer>) {
+        self.analyzers.push(analyzer);
+    }
+
+    /// The findings of each analyzer, by name.
+    pub fn run(&self, graph: &Graph) -> Vec<(&str, Vec<Finding>)> {
+        self.analyzers.iter().map(|a| (a.name(), a.analyze(graph))).collect()
+    }
+}
+
+impl Default for Analyzers {
+    fn default() -> Self {
+        let mut analyzers = Self::empty();
+        analyzers.register(Box::new(UnresolvedImports));
+        analyzers.register(Box::new(DependencyCycles));
+        analyzers
+    }
+}
+
+/// The imported symbols no input exports, one warning per symbol.
+pub struct UnresolvedImports;
+
+impl Analyzer for UnresolvedImports {
+    fn name(&self) -> &str {
+        "unresolved imports"
+    }
+
+    fn analyze(&self, graph: &Graph) -> Vec<Finding> {
+        let mut unresolved = graph.unresolved();
+        unresolved.sort();
+        unresolved.into_iter()
+            .map(|(symbol, libs)| Finding {
+                severity: Severity::Warning,
+                nodes: graph.nodes()
+                    .map(|(n, _)| n)
+                    .filter(|n| graph.label(*n).is_some_and(|l| libs.contains(&l)))
+                    .collect(),
+                message: format!("{} <- {}", symbol, libs.join(", ")),
+            })
+            .collect()
+    }
+}
+
+/// The imports of banned symbols, eg. `gets` or `strcpy`, one error per
+/// importer and symbol with its provider.
+pub struct BannedSymbols {
+    symbols: HashSet<String>,
+}
+
+impl BannedSymbols {
+    pub fn new<I: IntoIterator<Item = String>>(symbols: I) -> Self {
+        Self { symbols: symbols.into_iter().collect() }
+    }
+}
+
+impl Analyzer for BannedSymbols {
+    fn name(&self) -> &str {
+        "banned symbols"
+    }
+
+    fn analyze(&self, graph: &Graph) -> Vec<Finding> {
+        let label = |n: u32| graph.label(n).unwrap_or_default();
+        let mut findings: Vec<Finding> = graph.edges()
+            .flat_map(|((n1, n2), p)| p.imports().map(move |s| (n1, n2, s)))
+            .filter_map(|(n1, n2, symbol)| {
+                if !self.symbols.contains(symbol) {
+                    return None;
+                }
+                Some(Finding {
+                    severity: Severity::Error,
+                    nodes: vec![n1, n2],
+                    message: format!("{} imports {} from {}", label(n1), symbol, label(n2)),
+                })
+            })
+            .collect();
+
+        // banned symbols are often provided by the libraries left out, eg. libc
+        for (symbol, libs) in graph.unresolved().into_iter().filter(|(s, _)| self.symbols.contains(*s)) {
+            for lib in libs {
+                findings.push(Finding {
+                    severity: Severity::Error,
+                    nodes: graph.nodes().map(|(n, _)| n).filter(|n| graph.label(*n) == Some(lib)).collect(),
+                    message: format!("{} imports {}, unresolved", lib, symbol),
+                });
+            }
+        }
+        findings.sort_by(|a, b| a.message.cmp(&b.message));
+        findings
+    }
+}
+
+/// The exports of the libraries missing from their allowlist, one error per
+/// added symbol, and the allowed symbols no longer exported as infos.
+///
+/// The allowlist lists the symbols of each library under its label:
+///
+/// ```text
+/// [libfoo]
+/// foo_init
+/// foo_free  # comments start with #
+/// ```
+///
+/// The libraries without a section are not checked.
+pub struct ExportAllowlist {
+    allowed: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl ExportAllowlist {
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let mut allowed = BTreeMap::new();
+        let mut library: Option<String> = None;
+        for (number, line) in content.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                allowed.entry(name.trim().to_string()).or_insert_with(BTreeSet::new);
+                library = Some(name.trim().to_string());
+                continue;
+            }
+            match &library {
+                Some(library) => allowed.entry(library.clone()).or_insert_with(BTreeSet::new).insert(line.to_string()),
+                None => return Err(format!("line {}: {} is not under a [library] section", number + 1, line)),
+            };
+        }
+        Ok(Self { allowed })
+    }
+}
+
+impl Analyzer for ExportAllowlist {
+    fn name(&self) -> &str {
+        "export allowlist"
+    }
+
+    fn analyze(&self, graph: &Graph) -> Vec<Finding> {
+        let mut findings = vec![];
+        let mut nodes: Vec<(u32, &str)> = graph.nodes().filter_map(|(n, _)| Some((n, graph.label(n)?))).collect();
+        nodes.sort_by_key(|(_, label)| *label);
+        for (node, label) in nodes {
+            let allowed = match self.allowed.get(label) {
+                Some(allowed) => allowed,
+                None => continue,
+            };
+            // as named in the symbol tables, like the allowlist
+            let exported: BTreeSet<&str> = graph.nodes()
+                .filter(|(n, _)| *n == node)
+                .flat_map(|(_, p)| p.raw_exports())
+                .collect();
+
+            for symbol in exported.iter().filter(|s| !allowed.contains(**s)) {
+                findings.push(Finding { severity: Severity::Error, nodes: vec![node], message: format!("{} +{}", label, symbol) });
+            }
+            for symbol in allowed.iter().filter(|s| !exported.contains(s.as_str())) {
+                findings.push(Finding { severity: Severity::Info, nodes: vec![node], message: format!("{} -{}", label, symbol) });
+            }
+        }
+        findings
+    }
+}
+
+/// The imports from a library missing from the DT_NEEDED entries of the
+/// importer, found only as another library needs it, one warning per edge, as
+/// told by [`Graph::underlinked`].
+pub struct Underlinking;
+
+impl Analyzer for Underlinking {
+    fn name(&self) -> &str {
+        "underlinking"
+    }
+
+    fn analyze(&self, graph: &Graph) -> Vec<Finding> {
+        let label = |n: u32| graph.label(n).unwrap_or_default();
+        let mut findings: Vec<Finding> = graph.nodes()
+            .flat_map(|(importer, _)| graph.underlinked(importer).into_iter().map(move |exporter| (importer, exporter)))
+            .map(|(importer, exporter)| {
+                let total = graph.edges().find(|(e, _)| *e == (importer, exporter)).map_or(0, |(_, p)| p.total);
+                Finding {
+                    severity: Severity::Warning,
+                    nodes: vec![importer, exporter],
+                    message: format!("{} imports {} symbol{} from {} without needing it", label(importer), total,
+                                     if total > 1 { "s" } else { "" }, label(exporter)),
+                }
+            })
+            .collect();
+        findings.sort_by(|a, b| a.message.cmp(&b.message));
+        findings
+    }
+}
+
+/// The dependency cycles, one warning per cycle.
+pub struct DependencyCycles;
+
+impl Analyzer for DependencyCycles {
+    fn name(&self) -> &str {
+        "dependency cycles"
+    }
+
+    fn analyze(&self, graph: &Graph) -> Vec<Finding> {
+        graph.cycles().into_iter()
+            .map(|cycle| {
+                let labels: Vec<&str> = cycle.iter().filter_map(|n| graph.label(*n)).collect();
+                Finding { severity: Severity::Warning, message: labels.join(" <-> "), nodes: cycle }
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::fmt::Display;
+use std::path::Path;
+
+use graph::Graph;
+
+/// Renders a [`Graph`] as `bazel query --output graph` does, to diff the
+/// declared dependencies of the Bazel targets against the imported symbols.
+///
+/// The nodes are Bazel labels: the targets of the [`BazelWriter::targets`]
+/// mapping, else `//package:name` for the libraries under `bazel-bin` or
+/// `bazel-out/*/bin`, else the node labels. The edges go from the dependents to
+/// their dependencies, whatever the [`Graph::edge_direction`]:
+///
+/// ```text
+/// digraph mygraph {
+///   node [shape=box];
+/// "//app:main"
+/// "//app:main" -> "//lib:foo"
+/// "//lib:foo"
+/// }
+/// ```
+pub struct BazelWriter<'a> {
+    graph: &'a Graph,
+    targets: Option<&'a HashMap<String, String>>,
+}
+
+impl<'a> BazelWriter<'a> {
+    pub fn new(graph: &'a Graph) -> Self {
+        Self { graph, targets: None }
+    }
+
+    /// The Bazel labels of the libraries, by node label, soname or file name.
+    pub fn targets(mut self, targets: &'a HashMap<String, String>) -> Self {
+        self.targets = Some(targets);
+        self
+    }
+
+    fn label(&self, node: u32) -> Option<String> {
+        let graph = self.graph;
+        let path = graph.path(node)?;
+        let file_name = Path::new(path).file_name().and_then(|f| f.to_str());
+        let soname = graph.nodes.get(&node).and_then(|p| p.attributes.get("soname")).map(String::as_str);
+        let mapped = self.targets.and_then(|targets| {
+            [graph.label(node), soname, file_name].iter().flatten().find_map(|name| targets.get(*name))
+        });
+        Some(match mapped {
+            Some(target) => target.clone(),
+            None => bazel_label(path).unwrap_or_else(|| graph.label(node).unwrap_or(path).to_string()),
+        })
+    }
+}
+
+impl<'a> Display for BazelWriter<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let labels: HashMap<u32, String> = self.graph.nodes.keys().filter_map(|n| Some((*n, self.label(*n)?))).collect();
+        let mut dependencies: BTreeSet<(&str, Option<&str>)> = labels.values().map(|l| (l.as_str(), None)).collect();
+        for (importer, exporter) in self.graph.edges.keys() {
+            if let (Some(from), Some(to)) = (labels.get(importer), labels.get(exporter)) {
+                if from != to {
+                    dependencies.insert((from, Some(to)));
+                }
+            }
+        }
+
+        writeln!(f, "digraph mygraph {{")?;
+        writeln!(f, "  node [shape=box];")?;
+        for (from, to) in dependencies {
+            match to {
+                Some(to) => writeln!(f, "\"{}\" -> \"{}\"", from, to)?,
+                None => writeln!(f, "\"{}\"", from)?,
+            }
+        }
+        writeln!(f, "}}")
+    }
+}
+
+// the label of a library built by Bazel from its path under the output tree,
+// //package:name for the package/libname.so of a cc_library
+fn bazel_label(path: &str) -> Option<String> {
+    let relative = match path.find("bazel-bin/") {
+        Some(index) => &path[index + "bazel-bin/".len()..],
+        None => {
+            let index = path.find("bazel-out/")?;
+            let configuration = &path[index + "bazel-out/".len()..];
+            configuration.split_once('/')?.1.strip_prefix("bin/")?
+        }
+    };
+    let (package, file_name) = relative.rsplit_once('/').unwrap_or(("", relative));
+    let name = file_name.strip_prefix("lib").filter(|n| n.contains(".so") || n.ends_with(".a") || n.ends_with(".dylib"));
+    let name = match name {
+        Some(name) => name.split(".so").next().unwrap_or(name).trim_end_matches(".a").trim_end_matches(".dylib"),
+        None => file_name.trim_end_matches(".dll").trim_end_matches(".exe"),
+    };
+    Some(format!("//{}:{}", package, name))
+}
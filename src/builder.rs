@@ -0,0 +1,95 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use error::Result;
+use graph::Graph;
+use parse::{self, ParsedFile};
+use saved;
+
+/// Builds a [`Graph`] from symbol lists known without the object files, eg.
+/// read from a custom format, along with parsed binaries.
+///
+/// The nodes are added in order and their imports resolved once, by
+/// [`GraphBuilder::finish`], with the settings of the graph:
+///
+/// ```
+/// # use symbols_graph::{DotWriter, GraphBuilder};
+/// let mut builder = GraphBuilder::new("deps");
+/// builder.add_symbols("libfoo.so", ["foo"], ["bar"])
+///     .add_symbols("libbar.so", ["bar"], Vec::<String>::new());
+/// let graph = builder.finish();
+/// assert_eq!(graph.edges().count(), 1);
+/// print!("{}", DotWriter::new(&graph));
+/// ```
+pub struct GraphBuilder {
+    graph: Graph,
+    files: Vec<ParsedFile>,
+}
+
+impl GraphBuilder {
+    /// Add the nodes to a new graph named `name`.
+    pub fn new(name: &str) -> Self {
+        Self::with_graph(Graph::new(name))
+    }
+
+    /// Add the nodes to `graph`, parsed with its filters and resolved with
+    /// its settings.
+    pub fn with_graph(graph: Graph) -> Self {
+        Self { graph, files: vec![] }
+    }
+
+    /// The graph the nodes are added to, eg. to set its filters.
+    pub fn graph_mut(&mut self) -> &mut Graph {
+        &mut self.graph
+    }
+
+    /// Parse a binary, or a graph written by [`Graph::save`], as
+    /// [`Graph::parse_file`] does.
+    pub fn add_file(&mut self, filename: &str) -> Result<&mut Self> {
+        if saved::is_saved(filename) {
+            self.files.extend(saved::read(filename)?);
+        } else {
+            let graph = &self.graph;
+            self.files.extend(parse::parse(filename, &graph.filters, graph.cache.as_deref(), graph.mmap, &graph.sources)?);
+        }
+        Ok(self)
+    }
+
+    /// Add a node named `name`, its path and label, exporting and importing
+    /// the symbols.
+    ///
+    /// The symbols are added as given, the filters only leave out the nodes.
+    pub fn add_symbols<E, I>(&mut self, name: &str, exports: E, imports: I) -> &mut Self
+        where E: IntoIterator, E::Item: Into<String>, I: IntoIterator, I::Item: Into<String>
+    {
+        let label = match self.graph.filters.node_label(name) {
+            Some(label) => label,
+            None => return self,
+        };
+        let exports: Vec<String> = exports.into_iter().map(Into::into).collect();
+        let imports: Vec<String> = imports.into_iter().map(Into::into).collect();
+        self.files.push(ParsedFile {
+            path: name.to_string(),
+            label,
+            export_infos: vec![None; exports.len()],
+            export_names: vec![None; exports.len()],
+            exports,
+            import_weak: vec![false; imports.len()],
+            imports,
+            ordinals: vec![],
+            forwards: vec![],
+            attributes: BTreeMap::new(),
+            warnings: vec![],
+            dropped: vec![],
+            open_time: Duration::ZERO,
+            parse_time: Duration::ZERO,
+        });
+        self
+    }
+
+    /// Resolve the imports of the added nodes and return the graph.
+    pub fn finish(mut self) -> Graph {
+        self.graph.add_parsed(self.files);
+        self.graph
+    }
+}
@@ -0,0 +1,126 @@
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+const NINJA_LOG: &str = "# ninja log v";
+const NINJA_DEPS: &[u8] = b"# ninjadeps\n";
+
+// the shared libraries and executables built as listed by the .ninja_log, the
+// .ninja_deps or the make -p database at path, none when it is not one
+pub(crate) fn outputs(path: &str) -> Option<Vec<String>> {
+    let mut header = [0; 512];
+    let length = fs::File::open(path).and_then(|mut f| f.read(&mut header)).ok()?;
+    let header = &header[..length];
+    let base = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+
+    if header.starts_with(NINJA_LOG.as_bytes()) {
+        Some(built(ninja_log(&fs::read_to_string(path).ok()?), base))
+    } else if header.starts_with(NINJA_DEPS) {
+        Some(built(ninja_deps(&fs::read(path).ok()?), base))
+    } else if String::from_utf8_lossy(header).lines().take(8).any(|l| l.starts_with("# GNU Make") || l.starts_with("# Make data base")) {
+        let content = fs::read_to_string(path).ok()?;
+        let (targets, directory) = make_database(&content);
+        // relative to where make ran, else to the current directory
+        Some(built(targets, Path::new(directory.unwrap_or(""))))
+    } else {
+        None
+    }
+}
+
+// the existing linked binaries among the paths relative to base, sorted
+fn built(paths: Vec<&str>, base: &Path) -> Vec<String> {
+    let mut outputs: Vec<String> = paths.into_iter()
+        .map(|p| base.join(p))
+        .filter(|p| is_linked(p))
+        .map(|p| p.to_string_lossy().into_owned())
+        .collect();
+    outputs.sort();
+    outputs.dedup();
+    outputs
+}
+
+// the outputs of the ninja log v5 entries: start, end, mtime, output, hash
+fn ninja_log(content: &str) -> Vec<&str> {
+    content.lines()
+        .filter(|l| !l.starts_with('#'))
+        .filter_map(|l| l.split('\t').nth(3))
+        .collect()
+}
+
+// the paths of the deps log records: a size whose high bit tells the deps
+// records apart, then the path padded to 4 bytes and its checksum
+fn ninja_deps(content: &[u8]) -> Vec<&str> {
+    let mut paths = vec![];
+    // the magic and the version
+    let mut rest = match content.get(NINJA_DEPS.len() + 4..) {
+        Some(rest) => rest,
+        None => return paths,
+    };
+    while rest.len() >= 4 {
+        let header = u32::from_le_bytes([rest[0], rest[1], rest[2], rest[3]]);
+        let size = (header & 0x7fff_ffff) as usize;
+        let record = match rest.get(4..4 + size) {
+            Some(record) => record,
+            None => break,
+        };
+        if header & 0x8000_0000 == 0 && size >= 4 {
+            let path = &record[..size - 4];
+            let end = path.iter().position(|b| *b == 0).unwrap_or(path.len());
+            if let Ok(path) = std::str::from_utf8(&path[..end]) {
+                paths.push(path);
+            }
+        }
+        rest = &rest[4 + size..];
+    }
+    paths
+}
+
+// the targets of the make -p database and its CURDIR, the special targets and
+// the files make only considered left out
+fn make_database(content: &str) -> (Vec<&str>, Option<&str>) {
+    let mut targets = vec![];
+    let mut directory = None;
+    let mut not_target = false;
+    for line in content.lines() {
+        if line.starts_with("# Not a target") {
+            not_target = true;
+            continue;
+        }
+        if let Some(value) = line.strip_prefix("CURDIR := ") {
+            directory = Some(value);
+        }
+        if line.starts_with(|c: char| c == '#' || c.is_whitespace()) {
+            continue;
+        }
+        let target = match line.split_once(':') {
+            Some((target, rest)) if !rest.starts_with('=') && !target.contains('=') => target,
+            _ => continue,
+        };
+        if !std::mem::replace(&mut not_target, false) && !target.starts_with('.') && !target.contains('%') {
+            targets.push(target);
+        }
+    }
+    (targets, directory)
+}
+
+// whether path is a shared library or an executable, not an object file nor
+// an archive
+fn is_linked(path: &Path) -> bool {
+    let mut magic = [0; 20];
+    match fs::File::open(path).and_then(|mut f| f.read_exact(&mut magic)) {
+        Ok(()) => {}
+        Err(_) => return false,
+    }
+    let extension = path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase);
+    match magic {
+        // ET_EXEC or ET_DYN, in the byte order of EI_DATA
+        [0x7f, b'E', b'L', b'F', ..] => {
+            let kind = if magic[5] == 2 { magic[17] } else { magic[16] };
+            kind == 2 || kind == 3
+        }
+        // the Mach-O executables and dylibs, the filetype after the cpu types
+        [0xcf, 0xfa, 0xed, 0xfe, ..] | [0xce, 0xfa, 0xed, 0xfe, ..] => magic[12] == 2 || magic[12] == 6,
+        [b'M', b'Z', ..] => matches!(extension.as_deref(), Some("dll") | Some("exe")),
+        _ => false,
+    }
+}
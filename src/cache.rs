@@ -0,0 +1,235 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::process;
+use std::time::UNIX_EPOCH;
+
+use object::SymbolKind;
+
+use error::{Error, Result};
+use filters::{SpecialSymbol, SymbolInfo, Visibility};
+use parse::{extract, map, FileSymbols, RawSymbol};
+
+// bumped when the entries layout changes, older entries are then ignored
+const HEADER: &str = "symbols-graph cache 12";
+
+// the key of a cache entry, the size and mtime avoid hashing unchanged files
+struct Stamp {
+    path: String,
+    size: u64,
+    mtime: String,
+    hash: u64,
+}
+
+// the unfiltered symbols of filename, from the cache directory if unchanged
+//
+// the entries are rewritten when the file changed, failing to write them only
+// makes the next run slower and is returned as a warning
+pub(crate) fn load_or_read(cache: &Path, filename: &str, mmap: bool) -> Result<(FileSymbols, Option<Error>)> {
+    let metadata = match fs::metadata(filename) {
+        Ok(metadata) => metadata,
+        Err(error) => return Err(Error::Io { path: filename.to_string(), error }),
+    };
+    let mtime = match metadata.modified().map(|m| m.duration_since(UNIX_EPOCH)) {
+        Ok(Ok(duration)) => format!("{}.{:09}", duration.as_secs(), duration.subsec_nanos()),
+        _ => String::new(),
+    };
+    let entry = cache.join(format!("{:016x}", fnv1a(filename.as_bytes())));
+    let cached = fs::read_to_string(&entry).ok().and_then(|content| read_entry(&content));
+
+    let cached = match cached {
+        Some((stamp, symbols)) if stamp.path == filename && stamp.size == metadata.len() && !mtime.is_empty() && stamp.mtime == mtime => {
+            debug!("{} unchanged, using the cached symbols", filename);
+            return Ok((symbols, None));
+        }
+        cached => cached,
+    };
+
+    let memory = map(filename, mmap)?;
+    let stamp = Stamp { path: filename.to_string(), size: metadata.len(), mtime, hash: fnv1a(&memory) };
+
+    // touched but identical, only the stamp is refreshed
+    let symbols = match cached {
+        Some((cached, symbols)) if cached.path == stamp.path && cached.hash == stamp.hash => symbols,
+        _ => extract(filename, &memory, false)?,
+    };
+    let warning = write_entry(cache, &entry, &stamp, &symbols)
+        .err()
+        .map(|error| Error::WriteFailed { path: entry.to_string_lossy().into_owned(), error });
+    Ok((symbols, warning))
+}
+
+// the 64-bit FNV-1a hash, enough to detect a changed file or to spread keys
+pub(crate) fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3))
+}
+
+// the entry layout is a header then one `key value` or symbol per line:
+//
+//   path <hex path>, size <bytes>, mtime <secs.nanos>, hash <hex>,
+//   format pe|coff|other, soname <hex name> if any, attribute <hex name> <hex value>,
+//   E|I <kind> <visibility> <weak> <special>|- <size> <hex section>|- <hex address> <hex version>|- <hex name>,
+//   or E|I - <hex name> without symbol table information, O <ordinal> <hex name>,
+//   F <hex export> <hex target>
+fn write_entry(cache: &Path, entry: &Path, stamp: &Stamp, symbols: &FileSymbols) -> ::std::io::Result<()> {
+    let mut content = format!("{}\n", HEADER);
+    content += &format!("path {}\nsize {}\nmtime {}\nhash {:016x}\n", hex(stamp.path.as_bytes()), stamp.size, stamp.mtime, stamp.hash);
+    content += match (symbols.pe, symbols.coff) {
+        (true, _) => "format pe\n",
+        (_, true) => "format coff\n",
+        _ => "format other\n",
+    };
+    if let Some(soname) = &symbols.soname {
+        content += &format!("soname {}\n", hex(soname.as_bytes()));
+    }
+    for (name, value) in &symbols.attributes {
+        content += &format!("attribute {} {}\n", hex(name.as_bytes()), hex(value.as_bytes()));
+    }
+    for (tag, symbols) in [("E", &symbols.exports), ("I", &symbols.imports)] {
+        for symbol in symbols.iter() {
+            match &symbol.info {
+                Some(info) => {
+                    let section = info.section.as_ref().map_or(String::from("-"), |s| hex(s.as_bytes()));
+                    let version = info.version.as_ref().map_or(String::from("-"), |v| hex(v.as_bytes()));
+                    let special = info.special.map_or("-", SpecialSymbol::name);
+                    content += &format!("{} {} {} {} {} {} {} {:x} {} {}\n", tag, kind_name(info.kind), visibility_name(info.visibility), info.weak,
+                                        special, info.size, section, info.address, version, hex(&symbol.name));
+                }
+                None => content += &format!("{} - {}\n", tag, hex(&symbol.name)),
+            }
+        }
+    }
+    for (ordinal, name) in &symbols.ordinals {
+        content += &format!("O {} {}\n", ordinal, hex(name));
+    }
+    for (export, target) in &symbols.forwards {
+        content += &format!("F {} {}\n", hex(export), hex(target));
+    }
+
+    // written aside then renamed so that concurrent runs never read a partial entry
+    fs::create_dir_all(cache)?;
+    let temporary = entry.with_extension(format!("{}.tmp", process::id()));
+    fs::write(&temporary, content)?;
+    fs::rename(&temporary, entry)
+}
+
+// None if the entry is unreadable or from another version
+fn read_entry(content: &str) -> Option<(Stamp, FileSymbols)> {
+    let mut lines = content.lines();
+    if lines.next() != Some(HEADER) {
+        return None;
+    }
+
+    let mut stamp = Stamp { path: String::new(), size: 0, mtime: String::new(), hash: 0 };
+    let mut symbols = FileSymbols { pe: false, coff: false, soname: None, attributes: BTreeMap::new(), exports: vec![], imports: vec![], ordinals: vec![], forwards: vec![] };
+    for line in lines {
+        let mut fields = line.split(' ');
+        match (fields.next()?, fields.next()?) {
+            ("path", path) => stamp.path = String::from_utf8(unhex(path)?).ok()?,
+            ("size", size) => stamp.size = size.parse().ok()?,
+            ("mtime", mtime) => stamp.mtime = mtime.to_string(),
+            ("hash", hash) => stamp.hash = u64::from_str_radix(hash, 16).ok()?,
+            ("format", format) => {
+                symbols.pe = format == "pe";
+                symbols.coff = format == "coff";
+            }
+            ("soname", soname) => symbols.soname = Some(String::from_utf8(unhex(soname)?).ok()?),
+            ("attribute", name) => {
+                let value = String::from_utf8(unhex(fields.next()?)?).ok()?;
+                symbols.attributes.insert(String::from_utf8(unhex(name)?).ok()?, value);
+            }
+            ("F", export) => symbols.forwards.push((unhex(export)?, unhex(fields.next()?)?)),
+            ("O", ordinal) => symbols.ordinals.push((ordinal.parse().ok()?, unhex(fields.next()?)?)),
+            (tag @ "E", kind) | (tag @ "I", kind) => {
+                let info = match kind {
+                    "-" => None,
+                    kind => Some(SymbolInfo {
+                        kind: kind_from_name(kind)?,
+                        visibility: visibility_from_name(fields.next()?)?,
+                        weak: fields.next()?.parse().ok()?,
+                        special: match fields.next()? {
+                            "-" => None,
+                            special => Some(SpecialSymbol::from_name(special)?),
+                        },
+                        size: fields.next()?.parse().ok()?,
+                        section: match fields.next()? {
+                            "-" => None,
+                            section => Some(String::from_utf8(unhex(section)?).ok()?),
+                        },
+                        address: u64::from_str_radix(fields.next()?, 16).ok()?,
+                        version: match fields.next()? {
+                            "-" => None,
+                            version => Some(String::from_utf8(unhex(version)?).ok()?),
+                        },
+                    }),
+                };
+                let symbol = RawSymbol { name: unhex(fields.next()?)?, info };
+                if tag == "E" {
+                    symbols.exports.push(symbol);
+                } else {
+                    symbols.imports.push(symbol);
+                }
+            }
+            _ => return None,
+        }
+    }
+    Some((stamp, symbols))
+}
+
+// symbol names may contain spaces or be invalid UTF-8
+pub(crate) fn hex(data: &[u8]) -> String {
+    data.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub(crate) fn unhex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len()).step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i+2], 16).ok())
+        .collect()
+}
+
+pub(crate) fn kind_name(kind: SymbolKind) -> &'static str {
+    match kind {
+        SymbolKind::Text => "text",
+        SymbolKind::Data => "data",
+        SymbolKind::Section => "section",
+        SymbolKind::File => "file",
+        SymbolKind::Label => "label",
+        SymbolKind::Tls => "tls",
+        _ => "unknown",
+    }
+}
+
+pub(crate) fn kind_from_name(name: &str) -> Option<SymbolKind> {
+    match name {
+        "text" => Some(SymbolKind::Text),
+        "data" => Some(SymbolKind::Data),
+        "section" => Some(SymbolKind::Section),
+        "file" => Some(SymbolKind::File),
+        "label" => Some(SymbolKind::Label),
+        "tls" => Some(SymbolKind::Tls),
+        "unknown" => Some(SymbolKind::Unknown),
+        _ => None,
+    }
+}
+
+fn visibility_name(visibility: Visibility) -> &'static str {
+    match visibility {
+        Visibility::Internal => "internal",
+        Visibility::Hidden => "hidden",
+        Visibility::Protected => "protected",
+        Visibility::Default => "default",
+    }
+}
+
+fn visibility_from_name(name: &str) -> Option<Visibility> {
+    match name {
+        "internal" => Some(Visibility::Internal),
+        "hidden" => Some(Visibility::Hidden),
+        "protected" => Some(Visibility::Protected),
+        "default" => Some(Visibility::Default),
+        _ => None,
+    }
+}
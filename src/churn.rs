@@ -0,0 +1,222 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io;
+use std::io::Write;
+use std::iter::Peekable;
+use std::str::Chars;
+
+// a JSON document, as written by the graph command, the booleans are not read
+enum Value {
+    Null,
+    Bool,
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn array(&self) -> &[Value] {
+        match self {
+            Value::Array(values) => values,
+            _ => &[],
+        }
+    }
+
+    fn str(&self) -> Option<&str> {
+        match self {
+            Value::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn number(&self) -> Option<f64> {
+        match self {
+            Value::Number(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+// the libraries of a snapshot by label, with their counts and dependencies
+struct Snapshot {
+    name: String,
+    libraries: BTreeMap<String, Library>,
+}
+
+#[derive(Default)]
+struct Library {
+    cluster: Option<String>,
+    exports: usize,
+    imports: usize,
+    dependencies: BTreeSet<String>,
+}
+
+// read a JSON graph written by `graph -o FILE.json`
+fn snapshot(name: &str, content: &str) -> Result<Snapshot, String> {
+    let mut chars = content.chars().peekable();
+    let document = parse_value(&mut chars)?;
+
+    let mut labels = HashMap::new();
+    let mut libraries = BTreeMap::new();
+    for node in document.get("nodes").map(Value::array).unwrap_or_default() {
+        let (id, label) = match (node.get("id").and_then(Value::number), node.get("label").and_then(Value::str)) {
+            (Some(id), Some(label)) => (id as u64, label.to_string()),
+            _ => return Err(String::from("a node has no id or label")),
+        };
+        let library = Library {
+            cluster: node.get("cluster").and_then(Value::str).map(String::from),
+            exports: node.get("exports").map_or(0, |e| e.array().len()),
+            ..Library::default()
+        };
+        labels.insert(id, label.clone());
+        libraries.insert(label, library);
+    }
+    // the importers are the edges ends when they provide
+    let (importer, exporter) = match document.get("edge_direction").and_then(Value::str) {
+        Some("provides") => ("to", "from"),
+        _ => ("from", "to"),
+    };
+    for edge in document.get("edges").map(Value::array).unwrap_or_default() {
+        let label = |key| edge.get(key).and_then(Value::number).and_then(|id| labels.get(&(id as u64)));
+        let (from, to) = match (label(importer), label(exporter)) {
+            (Some(from), Some(to)) => (from.clone(), to.clone()),
+            _ => return Err(String::from("an edge links unknown nodes")),
+        };
+        if let Some(library) = libraries.get_mut(&from) {
+            library.imports += edge.get("symbols").map_or(0, |s| s.array().len());
+            library.dependencies.insert(to);
+        }
+    }
+    Ok(Snapshot { name: name.to_string(), libraries })
+}
+
+// one row per library and snapshot for plotting, with the dependencies
+// crossing clusters added since the previous snapshot, the libraries out of
+// any cluster are their own component
+pub(crate) fn churn(names: &[&String], contents: &[String], writer: &mut dyn Write) -> io::Result<Result<(), String>> {
+    let mut snapshots = vec![];
+    for (name, content) in names.iter().zip(contents) {
+        match snapshot(name, content) {
+            Ok(snapshot) => snapshots.push(snapshot),
+            Err(error) => return Ok(Err(format!("Unable to read {} : {}", name, error))),
+        }
+    }
+
+    writeln!(writer, "snapshot\tlibrary\texports\timports\tdependencies\tnew cross-component dependencies")?;
+    for (i, snapshot) in snapshots.iter().enumerate() {
+        let previous = if i > 0 { snapshots.get(i - 1) } else { None };
+        for (label, library) in &snapshot.libraries {
+            let component = |l: &str| snapshot.libraries.get(l).and_then(|l| l.cluster.clone()).unwrap_or_else(|| l.to_string());
+            let added: Vec<&str> = library.dependencies.iter()
+                .filter(|d| previous.is_some_and(|p| !p.libraries.get(label).is_some_and(|l| l.dependencies.contains(*d))))
+                .filter(|d| component(d) != component(label))
+                .map(String::as_str)
+                .collect();
+            writeln!(writer, "{}\t{}\t{}\t{}\t{}\t{}", snapshot.name, label, library.exports, library.imports, library.dependencies.len(), added.join(","))?;
+        }
+    }
+    Ok(Ok(()))
+}
+
+fn parse_value(chars: &mut Peekable<Chars>) -> Result<Value, String> {
+    skip_whitespace(chars);
+    match chars.peek() {
+        Some('{') => {
+            chars.next();
+            let mut fields = vec![];
+            loop {
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some('}') if fields.is_empty() => break,
+                    Some('"') => (),
+                    _ => return Err(String::from("expected a key")),
+                }
+                let key = parse_string(chars)?;
+                skip_whitespace(chars);
+                if chars.next() != Some(':') {
+                    return Err(format!("expected : after {}", key));
+                }
+                fields.push((key, parse_value(chars)?));
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => (),
+                    Some('}') => break,
+                    _ => return Err(String::from("expected , or }")),
+                }
+            }
+            Ok(Value::Object(fields))
+        }
+        Some('[') => {
+            chars.next();
+            let mut values = vec![];
+            skip_whitespace(chars);
+            if chars.peek() == Some(&']') {
+                chars.next();
+                return Ok(Value::Array(values));
+            }
+            loop {
+                values.push(parse_value(chars)?);
+                skip_whitespace(chars);
+                match chars.next() {
+                    Some(',') => (),
+                    Some(']') => break,
+                    _ => return Err(String::from("expected , or ]")),
+                }
+            }
+            Ok(Value::Array(values))
+        }
+        Some('"') => {
+            chars.next();
+            parse_string(chars).map(Value::String)
+        }
+        Some(_) => {
+            let mut word = String::new();
+            while let Some(c) = chars.peek().filter(|c| c.is_ascii_alphanumeric() || "+-.".contains(**c)) {
+                word.push(*c);
+                chars.next();
+            }
+            match word.as_str() {
+                "null" => Ok(Value::Null),
+                "true" | "false" => Ok(Value::Bool),
+                number => number.parse().map(Value::Number).map_err(|_| format!("unexpected {}", number)),
+            }
+        }
+        None => Err(String::from("unexpected end")),
+    }
+}
+
+// after the opening quote
+fn parse_string(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    let mut value = String::new();
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(value),
+            Some('\\') => match chars.next() {
+                Some('n') => value.push('\n'),
+                Some('t') => value.push('\t'),
+                Some('u') => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    let c = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32);
+                    value.push(c.ok_or_else(|| format!("invalid escape \\u{}", hex))?);
+                }
+                Some(c) => value.push(c),
+                None => return Err(String::from("unterminated string")),
+            },
+            Some(c) => value.push(c),
+            None => return Err(String::from("unterminated string")),
+        }
+    }
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while chars.peek().is_some_and(|c| c.is_whitespace()) {
+        chars.next();
+    }
+}
@@ -0,0 +1,800 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::fmt::Display;
+
+use object::SymbolKind;
+use regex::Regex;
+
+use graph::{Edge, EdgeProperties, Graph, Node, NodeProperties};
+use writer::GraphWriter;
+use layout;
+use layout::{Layout, Point, ARROW};
+
+/// Renders a [`Graph`] in the Graphviz DOT format.
+///
+/// A `Graph` displays itself with the default options, the writer adds the
+/// rendering options; as a [`GraphWriter`] it also renders through
+/// [`Graph::write`]:
+///
+/// ```no_run
+/// # use symbols_graph::{DotWriter, Graph};
+/// # fn main() -> symbols_graph::Result<()> {
+/// let mut graph = Graph::new("deps");
+/// graph.parse_file("libfoo.so")?;
+/// print!("{}", DotWriter::new(&graph).max_edge_symbols(Some(3)));
+/// # Ok(())
+/// # }
+/// ```
+pub struct DotWriter<'a> {
+    graph: &'a Graph,
+    max_edge_symbols: Option<usize>,
+    highlights: &'a [Highlight],
+    label_attributes: &'a [String],
+    weight_by: Option<EdgeWeight>,
+    symbol_kinds: bool,
+    detailed_labels: bool,
+    metadata: &'a [(String, String)],
+    title: Option<&'a str>,
+    attributes: &'a [(String, String)],
+    font_size: Option<f64>,
+    resolution_colors: bool,
+    xdot: bool,
+    state: DotState,
+}
+
+// what the nodes and edges are written with, set by the header
+#[derive(Default)]
+struct DotState {
+    ids: HashMap<u32, u64>,
+    layout: Option<Layout>,
+    heaviest: f64,
+    // the cluster of the nodes written last, open until another starts
+    cluster: Option<String>,
+}
+
+// the edges of the DT_NEEDED libraries no symbol is imported from
+const DECLARED_EDGE: &str = ", color=\"#7f7f7f\", style=dotted";
+
+// the exported symbols listed by the detailed labels
+const DETAILED_SYMBOLS: usize = 5;
+
+// the font size of the labels without --font-size, in points
+const FONT_SIZE: f64 = 14.0;
+
+/// What the edge thickness reflects, see [`DotWriter::weight_by`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeWeight {
+    /// The number of imported symbols.
+    Count,
+    /// The bytes of imported code and data.
+    Size,
+}
+
+impl<'a> DotWriter<'a> {
+    /// Write `graph` with the default options.
+    pub fn new(graph: &'a Graph) -> Self {
+        Self {
+            graph,
+            max_edge_symbols: None,
+            highlights: &[],
+            label_attributes: &[],
+            weight_by: None,
+            symbol_kinds: false,
+            detailed_labels: false,
+            metadata: &[],
+            title: None,
+            attributes: &[],
+            font_size: None,
+            resolution_colors: false,
+            xdot: false,
+            state: DotState::default(),
+        }
+    }
+
+    /// Lay the graph out and write it as xdot: with the positions and the
+    /// drawing operations of every node and edge, for the viewers without
+    /// Graphviz. The ranks go from the top down along the edges.
+    pub fn xdot(mut self, xdot: bool) -> Self {
+        self.xdot = xdot;
+        self
+    }
+
+    /// Color the edges by how they were resolved: black within the inputs,
+    /// blue to the external libraries of a sysroot, dotted grey for the
+    /// DT_NEEDED libraries no symbol is imported from, and red to an
+    /// `unresolved` node for the imports no library exports.
+    pub fn resolution_colors(mut self, colors: bool) -> Self {
+        self.resolution_colors = colors;
+        self
+    }
+
+    /// Set these attributes of the graph, eg. `dpi`, `size` or `ratio` to
+    /// render large graphs.
+    pub fn graph_attributes(mut self, attributes: &'a [(String, String)]) -> Self {
+        self.attributes = attributes;
+        self
+    }
+
+    /// The font size of the node labels, in points.
+    pub fn font_size(mut self, size: Option<f64>) -> Self {
+        self.font_size = size;
+        self
+    }
+
+    /// Label the graph with `title`, under the diagram.
+    pub fn title(mut self, title: Option<&'a str>) -> Self {
+        self.title = title;
+        self
+    }
+
+    /// Start the graph with a `// name: value` comment per entry, eg. the
+    /// version and the options that made it.
+    pub fn metadata(mut self, metadata: &'a [(String, String)]) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Render a single edge per library pair, labeled with at most `max` symbols.
+    pub fn max_edge_symbols(mut self, max: Option<usize>) -> Self {
+        self.max_edge_symbols = max;
+        self
+    }
+
+    /// Color the nodes and the symbol edges matching the highlights.
+    pub fn highlights(mut self, highlights: &'a [Highlight]) -> Self {
+        self.highlights = highlights;
+        self
+    }
+
+    /// Add the values of these node attributes to the node labels, one per line.
+    pub fn label_attributes(mut self, names: &'a [String]) -> Self {
+        self.label_attributes = names;
+        self
+    }
+
+    /// Draw the edges thicker as they weigh more, relative to the heaviest.
+    pub fn weight_by(mut self, weight: Option<EdgeWeight>) -> Self {
+        self.weight_by = weight;
+        self
+    }
+
+    /// Suffix the symbols with `()` for the functions and `[data]` for the
+    /// data, as told by the exporter symbol table.
+    pub fn symbol_kinds(mut self, symbol_kinds: bool) -> Self {
+        self.symbol_kinds = symbol_kinds;
+        self
+    }
+
+    /// Render the nodes as tables with their kind, their exports count and
+    /// their largest exports, rather than as their name.
+    pub fn detailed_labels(mut self, detailed: bool) -> Self {
+        self.detailed_labels = detailed;
+        self
+    }
+
+    // the symbol name, with its kind if requested
+    fn symbol_label(&self, exporter: Option<&NodeProperties>, symbol: u32) -> Option<String> {
+        let name = self.graph.strings.resolve(symbol)?;
+        if !self.symbol_kinds {
+            return Some(name.to_string());
+        }
+        Some(match exporter.map_or(SymbolKind::Unknown, |e| e.kind(symbol)) {
+            SymbolKind::Text => format!("{}()", name),
+            SymbolKind::Data | SymbolKind::Tls => format!("{} [data]", name),
+            _ => name.to_string(),
+        })
+    }
+
+    // the label attribute of a node, its name followed by the requested attributes
+    fn node_label(&self, node: u32, label: &str) -> String {
+        let properties = self.graph.nodes.get(&node);
+        if self.detailed_labels {
+            return self.detailed_label(properties, label);
+        }
+
+        let mut lines = vec![escape(label)];
+        if let Some(properties) = properties {
+            for name in self.label_attributes {
+                if let Some(value) = properties.attributes.get(name) {
+                    lines.push(escape(value));
+                }
+            }
+        }
+        format!("label=\"{}\"", lines.join("\\n"))
+    }
+
+    // an HTML-like table: the name, the kind, the exports count then the largest exports
+    fn detailed_label(&self, properties: Option<&NodeProperties>, label: &str) -> String {
+        let rows: Vec<String> = self.detailed_rows(properties, label).iter().enumerate()
+            .map(|(i, (text, symbol))| match (i, symbol) {
+                (0, _) => format!("<tr><td><b>{}</b></td></tr>", html_escape(text)),
+                (_, true) => format!("<tr><td align=\"left\">{}</td></tr>", html_escape(text)),
+                (_, false) => format!("<tr><td>{}</td></tr>", html_escape(text)),
+            })
+            .collect();
+        format!("label=<<table border=\"0\" cellborder=\"1\" cellspacing=\"0\">{}</table>>, shape=plain", rows.concat())
+    }
+
+    // the rows of the detailed labels, and whether each is a symbol
+    fn detailed_rows(&self, properties: Option<&NodeProperties>, label: &str) -> Vec<(String, bool)> {
+        let mut rows = vec![(label.to_string(), false)];
+        if let Some(properties) = properties {
+            let kind: Vec<&str> = ["kind", "arch"].iter().filter_map(|a| properties.attributes.get(*a)).map(String::as_str).collect();
+            if !kind.is_empty() {
+                rows.push((kind.join(" "), false));
+            }
+            rows.push((format!("{} exports", properties.symbols.len()), false));
+
+            let mut largest: Vec<u32> = properties.symbols().cloned().collect();
+            largest.sort_by_key(|s| Reverse(properties.size(*s)));
+            for symbol in largest.into_iter().take(DETAILED_SYMBOLS) {
+                if let Some(name) = self.symbol_label(Some(properties), symbol) {
+                    rows.push((name, true));
+                }
+            }
+            for name in self.label_attributes {
+                if let Some(value) = properties.attributes.get(name) {
+                    rows.push((value.clone(), false));
+                }
+            }
+        }
+        rows
+    }
+
+    // the lines of text of a node label, as drawn
+    fn label_lines(&self, node: u32, label: &str) -> Vec<String> {
+        let properties = self.graph.nodes.get(&node);
+        if self.detailed_labels {
+            return self.detailed_rows(properties, label).into_iter().map(|(text, _)| text).collect();
+        }
+        let mut lines = vec![label.to_string()];
+        if let Some(properties) = properties {
+            lines.extend(self.label_attributes.iter().filter_map(|name| properties.attributes.get(name)).cloned());
+        }
+        lines
+    }
+
+    fn highlight_attributes(&self, label: &str) -> String {
+        highlight_attributes(self.highlights, label)
+    }
+
+    // the color of an edge with resolution_colors, after its exporter or
+    // importer
+    fn resolution_color(&self, importer: u32, exporter: u32) -> &'static str {
+        let attribute = |node: u32, name: &str| self.graph.nodes.get(&node).and_then(|p| p.attributes.get(name));
+        if !self.resolution_colors {
+            ""
+        } else if attribute(importer, "degraded").is_some_and(|d| d == "needed") {
+            DECLARED_EDGE
+        } else if attribute(exporter, "external").is_some() {
+            ", color=\"#1f77b4\""
+        } else {
+            ""
+        }
+    }
+
+    // the DT_NEEDED libraries of the inputs without an edge to them, in the
+    // output direction, and the unresolved imports count of each importer
+    fn unlinked(&self, ids: &HashMap<u32, u64>) -> (BTreeSet<(u64, u64)>, BTreeMap<u64, usize>) {
+        let graph = self.graph;
+        let sonames = graph.sonames();
+        let mut declared = BTreeSet::new();
+        for (node, properties) in &graph.nodes {
+            let needed = properties.attributes.get("needed").into_iter().flat_map(|n| n.split(' '));
+            for exporter in needed.filter_map(|n| sonames.get(n)) {
+                if exporter != node && !graph.edges.contains_key(&(*node, *exporter)) {
+                    declared.insert(graph.edge_direction.orient(ids[node], ids[exporter]));
+                }
+            }
+        }
+
+        let mut unresolved: BTreeMap<u64, usize> = BTreeMap::new();
+        for libs in graph.undefined.values() {
+            for lib in libs.iter().collect::<BTreeSet<_>>() {
+                *unresolved.entry(ids[lib]).or_default() += 1;
+            }
+        }
+        (declared, unresolved)
+    }
+
+    // the edges of the DT_NEEDED libraries of the inputs without symbols, and
+    // the edges to the unresolved imports
+    fn write_unlinked(&self, f: &mut dyn fmt::Write, ids: &HashMap<u32, u64>, layout: Option<&Layout>) -> fmt::Result {
+        let (declared, unresolved) = self.unlinked(ids);
+        for (from, to) in declared {
+            let (from, to) = (format!("n{}", from), format!("n{}", to));
+            let style = DECLARED_EDGE.trim_start_matches(", ");
+            let drawing = layout.map(|l| self.xdot_edge(l, &from, &to, &[], 0, style)).unwrap_or_default();
+            writeln!(f, "    {} -> {} [{}{}]", from, to, style, drawing)?;
+        }
+
+        if !unresolved.is_empty() {
+            let style = "shape=box, style=dashed, color=\"#d62728\", fontcolor=\"#d62728\"";
+            let drawing = layout.map(|l| self.xdot_node(l, "unresolved", &[String::from("unresolved")], style)).unwrap_or_default();
+            writeln!(f, "    unresolved [label=\"unresolved\", {}{}]", style, drawing)?;
+        }
+        for (importer, count) in unresolved {
+            let text = format!("{} symbol{}", count, if count > 1 { "s" } else { "" });
+            let style = "color=\"#d62728\", fontcolor=\"#d62728\"";
+            let importer = format!("n{}", importer);
+            let (from, to) = self.graph.edge_direction.orient(importer.as_str(), "unresolved");
+            let drawing = layout.map(|l| self.xdot_edge(l, from, to, std::slice::from_ref(&text), 0, style)).unwrap_or_default();
+            writeln!(f, "    {} -> {} [label=\"{}\", {}{}]", from, to, text, style, drawing)?;
+        }
+        Ok(())
+    }
+
+    // the size of a node drawn with these lines, in points
+    fn node_size(&self, lines: &[String], boxed: bool) -> (f64, f64) {
+        let (width, height) = text_size(lines, self.font_size.unwrap_or(FONT_SIZE));
+        if boxed {
+            ((width + 16.0).max(54.0), (height + 8.0).max(36.0))
+        } else {
+            // the ellipse around the text
+            ((width * 1.42 + 8.0).max(54.0), (height * 1.42 + 4.0).max(36.0))
+        }
+    }
+
+    // the positions of the nodes and the routes of the edges
+    fn layout(&self, ids: &HashMap<u32, u64>) -> Layout {
+        let graph = self.graph;
+        let mut nodes: Vec<(u64, u32, &str)> = graph.nodes.keys().filter_map(|n| Some((ids[n], *n, graph.label(*n)?))).collect();
+        nodes.sort();
+        let mut sizes: Vec<(String, f64, f64)> = nodes.iter()
+            .map(|(id, node, label)| {
+                let (width, height) = self.node_size(&self.label_lines(*node, label), self.detailed_labels);
+                (format!("n{}", id), width, height)
+            })
+            .collect();
+
+        let mut edges: Vec<(String, String)> = graph.edges.keys()
+            .map(|(n1, n2)| {
+                let (from, to) = graph.edge_direction.orient(ids[n1], ids[n2]);
+                (format!("n{}", from), format!("n{}", to))
+            })
+            .collect();
+        if self.resolution_colors {
+            let (declared, unresolved) = self.unlinked(ids);
+            edges.extend(declared.into_iter().map(|(from, to)| (format!("n{}", from), format!("n{}", to))));
+            if !unresolved.is_empty() {
+                let (width, height) = self.node_size(&[String::from("unresolved")], true);
+                sizes.push((String::from("unresolved"), width, height));
+            }
+            for importer in unresolved.keys() {
+                let (from, to) = graph.edge_direction.orient(format!("n{}", importer), String::from("unresolved"));
+                edges.push((from, to));
+            }
+        }
+        edges.sort();
+
+        let groups: Vec<Vec<String>> = graph.clusters.iter()
+            .map(|c| {
+                let mut members: Vec<u64> = c.nodes.keys().map(|n| ids[n]).collect();
+                members.sort();
+                members.iter().map(|id| format!("n{}", id)).collect()
+            })
+            .collect();
+        let mut layout = layout::layout(&sizes, &edges, &groups);
+        // room for the title below the drawing
+        if self.title.is_some() {
+            layout.raise(2.0 * self.font_size.unwrap_or(FONT_SIZE));
+        }
+        layout
+    }
+
+    // the xdot attributes of a node, drawn as styled by its attributes
+    fn xdot_node(&self, layout: &Layout, key: &str, lines: &[String], style: &str) -> String {
+        let node = match layout.nodes.get(key) {
+            Some(node) => node,
+            None => return String::new(),
+        };
+        let Point { x, y } = node.center;
+        let (rx, ry) = (node.width / 2.0, node.height / 2.0);
+        let boxed = matches!(attribute(style, "shape"), Some("box") | Some("plain"));
+        let pen = attribute(style, "color").unwrap_or("black");
+
+        let mut draw = String::new();
+        if attribute(style, "style") == Some("dashed") {
+            draw += "S 6 -dashed ";
+        }
+        draw += &format!("c {} -{} ", pen.len(), pen);
+        let fill = attribute(style, "fillcolor").filter(|_| attribute(style, "style") == Some("filled"));
+        if let Some(fill) = fill {
+            draw += &format!("C {} -{} ", fill.len(), fill);
+        }
+        let shape = match (boxed, fill.is_some()) {
+            (false, filled) => format!("{} {} {} {} {}", if filled { "E" } else { "e" }, number(x), number(y), number(rx), number(ry)),
+            (true, filled) => format!("{} 4 {} {} {} {} {} {} {} {}", if filled { "P" } else { "p" },
+                                      number(x - rx), number(y - ry), number(x + rx), number(y - ry),
+                                      number(x + rx), number(y + ry), number(x - rx), number(y + ry)),
+        };
+        draw += &shape;
+
+        let font = self.font_size.unwrap_or(FONT_SIZE);
+        let text = text_operations(lines, font, attribute(style, "fontcolor").unwrap_or("black"), node.center);
+        format!(", pos=\"{},{}\", width=\"{}\", height=\"{}\", _draw_=\"{}\", _ldraw_=\"{}\"",
+                number(x), number(y), number(node.width / 72.0), number(node.height / 72.0), escape(&draw), escape(&text))
+    }
+
+    // the xdot attributes of an edge, its nth label stacked under the first
+    fn xdot_edge(&self, layout: &Layout, from: &str, to: &str, lines: &[String], nth: usize, style: &str) -> String {
+        let (points, tip) = match layout.edges.get(&(from.to_string(), to.to_string())) {
+            Some(route) => route,
+            None => return String::new(),
+        };
+        let pen = attribute(style, "color").unwrap_or("black");
+        let mut draw = String::new();
+        if let Some(dash) = attribute(style, "style") {
+            draw += &format!("S {} -{} ", dash.len(), dash);
+        }
+        if let Some(width) = attribute(style, "penwidth") {
+            let width = format!("setlinewidth({})", width);
+            draw += &format!("S {} -{} ", width.len(), width);
+        }
+        draw += &format!("c {} -{} B {}", pen.len(), pen, points.len());
+        let mut pos = format!("e,{},{}", number(tip.x), number(tip.y));
+        for point in points {
+            draw += &format!(" {} {}", number(point.x), number(point.y));
+            pos += &format!(" {},{}", number(point.x), number(point.y));
+        }
+
+        // the arrowhead from the end of the spline to the tip
+        let end = points[points.len() - 1];
+        let (dx, dy) = (tip.x - end.x, tip.y - end.y);
+        let length = (dx * dx + dy * dy).sqrt().max(1.0);
+        let (nx, ny) = (-dy / length * ARROW * 0.35, dx / length * ARROW * 0.35);
+        let head = format!("S 5 -solid c {} -{} C {} -{} P 3 {} {} {} {} {} {}", pen.len(), pen, pen.len(), pen,
+                           number(end.x + nx), number(end.y + ny), number(tip.x), number(tip.y), number(end.x - nx), number(end.y - ny));
+
+        let mut attributes = format!(", pos=\"{}\", _draw_=\"{}\", _hdraw_=\"{}\"", pos, escape(&draw), escape(&head));
+        if !lines.is_empty() {
+            let font = self.font_size.unwrap_or(FONT_SIZE);
+            let (width, height) = text_size(lines, font);
+            let middle = points[points.len() / 2];
+            let center = Point { x: middle.x + width / 2.0 + 4.0, y: middle.y - nth as f64 * height };
+            let text = text_operations(lines, font, attribute(style, "fontcolor").unwrap_or("black"), center);
+            attributes += &format!(", lp=\"{},{}\", _ldraw_=\"{}\"", number(center.x), number(center.y), escape(&text));
+        }
+        attributes
+    }
+
+    // the graph statements of the drawing: its bounding box, the title and
+    // the frame of each cluster
+    fn xdot_graph(&self, layout: &Layout) -> String {
+        let mut statements = format!("    bb=\"0,0,{},{}\"\n    xdotversion=\"1.7\"\n", number(layout.width), number(layout.height));
+        if let Some(title) = self.title {
+            let font = self.font_size.unwrap_or(FONT_SIZE);
+            let text = text_operations(&[title.to_string()], font, "black", Point { x: layout.width / 2.0, y: font });
+            statements += &format!("    _ldraw_=\"{}\"\n", escape(&text));
+        }
+        statements
+    }
+
+    // the bounding box and the frame of a cluster around its members
+    fn xdot_cluster(&self, layout: &Layout, members: &[String], label: &str) -> String {
+        let font = self.font_size.unwrap_or(FONT_SIZE);
+        let (mut left, mut bottom, mut right, mut top) = (f64::INFINITY, f64::INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY);
+        for node in members.iter().filter_map(|m| layout.nodes.get(m)) {
+            left = left.min(node.center.x - node.width / 2.0 - 8.0);
+            right = right.max(node.center.x + node.width / 2.0 + 8.0);
+            bottom = bottom.min(node.center.y - node.height / 2.0 - 8.0);
+            top = top.max(node.center.y + node.height / 2.0 + 8.0 + 1.2 * font);
+        }
+        if !left.is_finite() {
+            return String::new();
+        }
+        let frame = format!("c 7 -#000000 p 4 {} {} {} {} {} {} {} {}", number(left), number(bottom), number(right), number(bottom),
+                            number(right), number(top), number(left), number(top));
+        let text = text_operations(&[label.to_string()], font, "black", Point { x: (left + right) / 2.0, y: top - 0.7 * font });
+        format!("        bb=\"{},{},{},{}\"\n        _draw_=\"{}\"\n        _ldraw_=\"{}\"\n",
+                number(left), number(bottom), number(right), number(top), escape(&frame), escape(&text))
+    }
+
+    fn weight(&self, p: &EdgeProperties) -> f64 {
+        match self.weight_by {
+            Some(EdgeWeight::Count) => p.total as f64,
+            Some(EdgeWeight::Size) => p.bytes as f64,
+            None => 0.0,
+        }
+    }
+
+    fn close_cluster(&mut self, f: &mut dyn fmt::Write) -> fmt::Result {
+        match self.state.cluster.take() {
+            Some(_) => writeln!(f, "    }}"),
+            None => Ok(()),
+        }
+    }
+
+    fn node_fill(&self, node: u32) -> String {
+        self.graph.nodes.get(&node).map(|p| fill_attributes(&p.attributes)).unwrap_or_default()
+    }
+
+    // the xdot attributes of a graph node, none without a layout
+    fn node_drawing(&self, layout: Option<&Layout>, ids: &HashMap<u32, u64>, node: u32, label: &str) -> String {
+        let layout = match layout {
+            Some(layout) => layout,
+            None => return String::new(),
+        };
+        let mut style = format!("{}{}", self.node_fill(node), self.highlight_attributes(label));
+        if self.detailed_labels {
+            style += ", shape=plain";
+        }
+        self.xdot_node(layout, &format!("n{}", ids[&node]), &self.label_lines(node, label), &style)
+    }
+}
+
+impl<'a> GraphWriter for DotWriter<'a> {
+    fn write_header(&mut self, graph: &Graph, f: &mut dyn fmt::Write) -> fmt::Result {
+        let ids = graph.node_ids();
+        self.state.layout = if self.xdot { Some(self.layout(&ids)) } else { None };
+        // the heaviest edge is 5 points wide
+        self.state.heaviest = graph.edges.values().map(|p| self.weight(p)).fold(0.0, f64::max);
+        self.state.ids = ids;
+        self.state.cluster = None;
+
+        for (name, value) in self.metadata {
+            writeln!(f, "// {}: {}", name, value.replace('\n', " "))?;
+        }
+        writeln!(f, "digraph {} {{", graph_id(&graph.name))?;
+        if let Some(title) = self.title {
+            write!(f, "{}", title_attributes(title))?;
+        }
+        write!(f, "{}", layout_attributes(self.attributes, self.font_size))?;
+        if let Some(layout) = &self.state.layout {
+            write!(f, "{}", self.xdot_graph(layout))?;
+        }
+        Ok(())
+    }
+
+    fn write_node(&mut self, node: Node, cluster: Option<&str>, f: &mut dyn fmt::Write) -> fmt::Result {
+        let graph = self.graph;
+        if cluster != self.state.cluster.as_deref() {
+            self.close_cluster(f)?;
+            if let Some(label) = cluster {
+                writeln!(f, "    subgraph \"cluster_{}\" {{", escape(label))?;
+                writeln!(f, "        label=\"{}\"", escape(label))?;
+                let members = graph.clusters.iter().find(|c| graph.strings.resolve(c.name) == Some(label));
+                if let (Some(layout), Some(members)) = (&self.state.layout, members) {
+                    let mut members: Vec<u64> = members.nodes.keys().map(|n| self.state.ids[n]).collect();
+                    members.sort();
+                    let members: Vec<String> = members.iter().map(|id| format!("n{}", id)).collect();
+                    write!(f, "{}", self.xdot_cluster(layout, &members, label))?;
+                }
+            }
+            self.state.cluster = cluster.map(String::from);
+        }
+
+        let (idx, ids, layout) = (node.id(), &self.state.ids, self.state.layout.as_ref());
+        let indent = if cluster.is_some() { "        " } else { "    " };
+        match graph.label(idx) {
+            Some(label) => writeln!(f, "{}n{} [{}{}{}{}]", indent, ids[&idx], self.node_label(idx, label), self.node_fill(idx), self.highlight_attributes(label),
+                                    self.node_drawing(layout, ids, idx, label)),
+            None if cluster.is_some() => writeln!(f, "{}n{}", indent, ids[&idx]),
+            None => Ok(()),
+        }
+    }
+
+    fn write_edge(&mut self, edge: Edge, f: &mut dyn fmt::Write) -> fmt::Result {
+        self.close_cluster(f)?;
+        let graph = self.graph;
+        let ((n1, n2), p) = (edge.id(), &*edge);
+        let (ids, layout) = (&self.state.ids, self.state.layout.as_ref());
+        let (from, to) = graph.edge_direction.orient(ids[&n1], ids[&n2]);
+        let (from_key, to_key) = (format!("n{}", from), format!("n{}", to));
+        let drawing = |lines: &[String], nth: usize, style: &str| {
+            layout.map(|l| self.xdot_edge(l, &from_key, &to_key, lines, nth, style)).unwrap_or_default()
+        };
+        let penwidth = if self.state.heaviest > 0.0 {
+            format!(", penwidth={:.1}", 1.0 + 4.0 * self.weight(p) / self.state.heaviest)
+        } else {
+            String::new()
+        };
+
+        // the optional dependencies are dashed
+        let style = dashed(p.is_weak());
+        let color = self.resolution_color(n1, n2);
+
+        if p.symbols.is_empty() && self.max_edge_symbols.is_none() {
+            let mut attributes = format!("{}{}{}", color, style, penwidth);
+            attributes += &drawing(&[], 0, &attributes);
+            match attributes.strip_prefix(", ") {
+                Some(attributes) => writeln!(f, "    n{} -> n{} [{}]", from, to, attributes)?,
+                None => writeln!(f, "    n{} -> n{}", from, to)?,
+            }
+        } else if let Some(max) = self.max_edge_symbols {
+            // a single edge listing the first symbols
+            let exporter = graph.nodes.get(&n2);
+            let mut labels: Vec<String> = graph.sorted_symbols(p).into_iter()
+                .take(max)
+                .filter_map(|(_, s)| self.symbol_label(exporter, s))
+                .collect();
+            let more = match labels.len() {
+                0 if p.total == 1 => String::from("1 symbol"),
+                0 => format!("{} symbols", p.total),
+                shown => format!("(+{} more)", p.total - shown),
+            };
+            let highlight = graph.sorted_symbols(p).into_iter()
+                .map(|(s, _)| self.highlight_attributes(s))
+                .find(|a| !a.is_empty())
+                .unwrap_or_default();
+            if p.total > labels.len() {
+                labels.push(more);
+            }
+            let attributes = format!("{}{}{}{}", color, highlight, style, penwidth);
+            let label = labels.iter().map(|l| escape(l)).collect::<Vec<_>>().join("\\n");
+            writeln!(f, "    n{} -> n{} [label=\"{}\"{}{}{}]", from, to, label, tooltip(p.bytes), attributes, drawing(&labels, 0, &attributes))?;
+        } else {
+            let exporter = graph.nodes.get(&n2);
+            for (nth, (name, symbol)) in graph.sorted_symbols(p).into_iter().enumerate() {
+                if let Some(label) = self.symbol_label(exporter, symbol) {
+                    let size = exporter.map_or(0, |e| e.size(symbol));
+                    let attributes = format!("{}{}{}{}", color, self.highlight_attributes(name), dashed(p.is_weak_symbol(symbol)), penwidth);
+                    let xdot = drawing(std::slice::from_ref(&label), nth, &attributes);
+                    writeln!(f, "    n{} -> n{} [label=\"{}\"{}{}{}]", from, to, escape(&label), tooltip(size), attributes, xdot)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self, _: &Graph, f: &mut dyn fmt::Write) -> fmt::Result {
+        self.close_cluster(f)?;
+        if self.resolution_colors {
+            self.write_unlinked(f, &self.state.ids, self.state.layout.as_ref())?;
+        }
+        writeln!(f, "}}")
+    }
+}
+
+impl<'a> Display for DotWriter<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut writer = DotWriter { state: DotState::default(), ..*self };
+        self.graph.write(&mut writer, f)
+    }
+}
+
+impl Display for Graph {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        DotWriter::new(self).fmt(f)
+    }
+}
+
+/// A `regex[=color]` coloring the matching nodes and symbols, red by default.
+#[derive(Debug, Clone)]
+pub struct Highlight {
+    regex: Regex,
+    color: String,
+}
+
+impl Highlight {
+    /// Parse a `regex[=color]`, the error tells what is invalid.
+    pub fn parse(highlight: &str) -> Result<Self, String> {
+        // a trailing =name or =#rrggbb is the color, the regex may contain = too
+        let (regex, color) = match highlight.rfind('=') {
+            Some(index) if is_color(&highlight[index+1..]) => (&highlight[..index], &highlight[index+1..]),
+            _ => (highlight, "red"),
+        };
+
+        match Regex::new(regex) {
+            Ok(regex) => Ok(Highlight { regex, color: color.to_string() }),
+            Err(error) => Err(format!("invalid highlight {} : {}", highlight, error)),
+        }
+    }
+}
+
+// the style of the weak references, resolved only if available
+pub(crate) fn dashed(weak: bool) -> &'static str {
+    if weak {
+        ", style=dashed"
+    } else {
+        ""
+    }
+}
+
+// the DOT attributes coloring a highlighted node or symbol
+pub(crate) fn highlight_attributes(highlights: &[Highlight], label: &str) -> String {
+    match highlights.iter().find(|h| h.regex.is_match(label)) {
+        Some(h) => format!(", color=\"{}\", fontcolor=\"{}\"", h.color, h.color),
+        None => String::new(),
+    }
+}
+
+// the DOT attributes filling the external nodes resolved from a sysroot in
+// grey, and the nodes missing hardenings, eg. with an executable stack
+pub(crate) fn fill_attributes(attributes: &BTreeMap<String, String>) -> String {
+    if attributes.contains_key("external") {
+        return String::from(", style=filled, fillcolor=\"#d9d9d9\", fontcolor=\"#595959\", tooltip=\"external\"");
+    }
+    match attributes.get("hardening") {
+        Some(issues) => format!(", style=filled, fillcolor=\"#f4cccc\", tooltip=\"missing hardening: {}\"", issues),
+        None => String::new(),
+    }
+}
+
+// the tooltip of an edge importing `size` bytes, none when unknown
+pub(crate) fn tooltip(size: u64) -> String {
+    match size {
+        0 => String::new(),
+        size => format!(", tooltip=\"{} bytes\"", size),
+    }
+}
+
+// the name of a graph, quoted unless a plain DOT identifier or empty
+pub(crate) fn graph_id(name: &str) -> String {
+    let plain = name.chars().enumerate().all(|(i, c)| c == '_' || c.is_ascii_alphabetic() || i > 0 && c.is_ascii_digit());
+    if plain {
+        name.to_string()
+    } else {
+        format!("\"{}\"", escape(name))
+    }
+}
+
+// the statements of a graph title, rendered at the bottom
+pub(crate) fn title_attributes(title: &str) -> String {
+    format!("    label=\"{}\"\n    labelloc=\"b\"\n", escape(title))
+}
+
+// the statements of the graph attributes and of the node font size
+pub(crate) fn layout_attributes(attributes: &[(String, String)], font_size: Option<f64>) -> String {
+    let mut statements: String = attributes.iter()
+        .map(|(name, value)| format!("    {}=\"{}\"\n", name, escape(value)))
+        .collect();
+    if let Some(size) = font_size {
+        statements += &format!("    node [fontsize={}]\n", size);
+    }
+    statements
+}
+
+// the value of an attribute in the `, name="value"` lists of the statements
+fn attribute<'s>(attributes: &'s str, name: &str) -> Option<&'s str> {
+    attributes.split(", ")
+        .filter_map(|a| a.trim_start_matches(", ").strip_prefix(name)?.strip_prefix('='))
+        .map(|v| v.trim_matches('"'))
+        .next()
+}
+
+// a coordinate of the xdot drawings
+fn number(value: f64) -> String {
+    format!("{:.2}", value)
+}
+
+// the approximate size of lines of text, in points
+fn text_size(lines: &[String], font_size: f64) -> (f64, f64) {
+    let longest = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+    (0.6 * font_size * longest as f64, 1.2 * font_size * lines.len() as f64)
+}
+
+// the xdot operations writing lines of text centered on center
+fn text_operations(lines: &[String], font_size: f64, color: &str, center: Point) -> String {
+    let mut operations = format!("F {} 11 -Times-Roman c {} -{}", font_size, color.len(), color);
+    let line_height = 1.2 * font_size;
+    for (i, line) in lines.iter().enumerate() {
+        let baseline = center.y + line_height * ((lines.len() - 1) as f64 / 2.0 - i as f64) - 0.3 * font_size;
+        let (width, _) = text_size(std::slice::from_ref(line), font_size);
+        operations += &format!(" T {} {} 0 {} {} -{}", number(center.x), number(baseline), number(width), line.len(), line);
+    }
+    operations
+}
+
+// a value quoted inside a DOT string
+pub(crate) fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+// a value inside an HTML-like label
+fn html_escape(value: &str) -> String {
+    value.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn is_color(v: &str) -> bool {
+    match v.strip_prefix('#') {
+        Some(hex) => !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit()),
+        None => !v.is_empty() && v.chars().all(|c| c.is_ascii_alphanumeric()),
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::{HashMap, HashSet};
+
+use object::read::elf::{Dyn, ElfFile, FileHeader, ProgramHeader};
+use object::SymbolIndex;
+
+// the string values of the dynamic entries tagged `tag`, eg. DT_SONAME or DT_NEEDED
+pub(crate) fn dynamic_strings(object_file: &object::File, tag: u32) -> Vec<String> {
+    match object_file {
+        object::File::Elf32(elf) => elf_dynamic_strings(elf, tag),
+        object::File::Elf64(elf) => elf_dynamic_strings(elf, tag),
+        _ => vec![],
+    }
+}
+
+// the names of the versions the file defines in its SHT_GNU_verdef, by index
+// of the dynamic symbols bound to them, eg. GLIBC_2.2.5 in libc; the symbols
+// unversioned or bound to the version of another file, eg. the data copy
+// relocated from libc into an executable, are left out
+pub(crate) fn symbol_versions(object_file: &object::File) -> HashMap<usize, String> {
+    match object_file {
+        object::File::Elf32(elf) => elf_symbol_versions(elf),
+        object::File::Elf64(elf) => elf_symbol_versions(elf),
+        _ => HashMap::new(),
+    }
+}
+
+fn elf_symbol_versions<Elf: FileHeader<Endian = object::Endianness>>(elf: &ElfFile<Elf>) -> HashMap<usize, String> {
+    let endian = elf.endian();
+    let versions = match elf.elf_section_table().versions(endian, elf.data()) {
+        Ok(Some(versions)) => versions,
+        _ => return HashMap::new(),
+    };
+    // the indices of the definitions, the others are the SHT_GNU_verneed ones
+    let mut defined = HashSet::new();
+    if let Ok(Some((mut verdefs, _))) = elf.elf_section_table().gnu_verdef(endian, elf.data()) {
+        while let Ok(Some((verdef, _))) = verdefs.next() {
+            defined.insert(verdef.vd_ndx.get(endian) & object::elf::VERSYM_VERSION);
+        }
+    }
+
+    (0..elf.elf_dynamic_symbol_table().len())
+        .filter_map(|index| {
+            let version_index = versions.version_index(endian, SymbolIndex(index));
+            if !defined.contains(&version_index.index()) {
+                return None;
+            }
+            let version = versions.version(version_index).ok()??;
+            Some((index, String::from_utf8_lossy(version.name()).into_owned()))
+        })
+        .collect()
+}
+
+// the missing hardenings of an executable or a shared library: no-relro,
+// execstack or textrel, nothing for the relocatable objects
+pub(crate) fn hardening_issues(object_file: &object::File) -> Vec<&'static str> {
+    match object_file {
+        object::File::Elf32(elf) => elf_hardening_issues(elf),
+        object::File::Elf64(elf) => elf_hardening_issues(elf),
+        _ => vec![],
+    }
+}
+
+fn elf_hardening_issues<Elf: FileHeader<Endian = object::Endianness>>(elf: &ElfFile<Elf>) -> Vec<&'static str> {
+    let endian = elf.endian();
+    let segments = elf.elf_program_headers();
+    if segments.is_empty() {
+        return vec![];
+    }
+
+    let relro = segments.iter().any(|s| s.p_type(endian) == object::elf::PT_GNU_RELRO);
+    // the stack is executable without PT_GNU_STACK
+    let execstack = match segments.iter().find(|s| s.p_type(endian) == object::elf::PT_GNU_STACK) {
+        Some(stack) => stack.p_flags(endian) & object::elf::PF_X != 0,
+        None => true,
+    };
+    let dynamic = match elf.elf_section_table().dynamic(endian, elf.data()) {
+        Ok(Some((dynamic, _))) => dynamic,
+        _ => &[],
+    };
+    let textrel = dynamic.iter().any(|d| match d.tag32(endian) {
+        Some(object::elf::DT_TEXTREL) => true,
+        Some(object::elf::DT_FLAGS) => d.val32(endian).is_some_and(|flags| flags & object::elf::DF_TEXTREL != 0),
+        _ => false,
+    });
+
+    let mut issues = vec![];
+    if !relro {
+        issues.push("no-relro");
+    }
+    if execstack {
+        issues.push("execstack");
+    }
+    if textrel {
+        issues.push("textrel");
+    }
+    issues
+}
+
+// the program interpreter of PT_INTERP, eg. /lib64/ld-linux-x86-64.so.2, set for
+// the executables, the position independent ones included
+pub(crate) fn interpreter(object_file: &object::File) -> Option<String> {
+    match object_file {
+        object::File::Elf32(elf) => elf_interpreter(elf),
+        object::File::Elf64(elf) => elf_interpreter(elf),
+        _ => None,
+    }
+}
+
+fn elf_interpreter<Elf: FileHeader<Endian = object::Endianness>>(elf: &ElfFile<Elf>) -> Option<String> {
+    let endian = elf.endian();
+    let segment = elf.elf_program_headers().iter().find(|s| s.p_type(endian) == object::elf::PT_INTERP)?;
+    let path = segment.data(endian, elf.data()).ok()?;
+    Some(String::from_utf8_lossy(path).trim_end_matches('\0').to_string())
+}
+
+fn elf_dynamic_strings<Elf: FileHeader<Endian = object::Endianness>>(elf: &ElfFile<Elf>, tag: u32) -> Vec<String> {
+    let endian = elf.endian();
+    let sections = elf.elf_section_table();
+
+    let (dynamic, link) = match sections.dynamic(endian, elf.data()) {
+        Ok(Some(dynamic)) => dynamic,
+        _ => return vec![],
+    };
+    let strings = match sections.strings(endian, elf.data(), link) {
+        Ok(strings) => strings,
+        Err(_) => return vec![],
+    };
+
+    dynamic.iter()
+        .filter(|d| d.tag32(endian) == Some(tag))
+        .filter_map(|d| d.string(endian, strings).ok())
+        .map(|s| String::from_utf8_lossy(s).into_owned())
+        .collect()
+}
@@ -0,0 +1,88 @@
+use std::io;
+
+use object;
+
+/// The failures while reading the input binaries.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// The file cannot be opened or read.
+    #[error("Unable to open {path} : {error}")]
+    Io { path: String, #[source] error: io::Error },
+    /// The file cannot be written, eg. an output, or a cache entry then only a
+    /// warning.
+    #[error("Unable to write {path} : {error}")]
+    WriteFailed { path: String, #[source] error: io::Error },
+    /// The file is not an object file object can parse.
+    #[error("Unable to parse {path} : {error}")]
+    Parse { path: String, #[source] error: object::Error },
+    /// A symbol name is not valid UTF-8, the symbol is skipped.
+    #[error("Invalid UTF-8 symbol in {path} : {}", String::from_utf8_lossy(.symbol))]
+    NonUtf8Symbol { path: String, symbol: Vec<u8> },
+    /// A library exports versioned and unversioned symbols, `symbols` are the
+    /// fewer ones, `versioned` tells which.
+    #[error("{} in the {} {path} : {}", if *.versioned { "Versioned exports" } else { "Unversioned exports" },
+            if *.versioned { "mostly unversioned" } else { "versioned" }, .symbols.join(", "))]
+    MixedVersioning { path: String, symbols: Vec<String>, versioned: bool },
+    /// The file has no dynamic symbols, `degraded` tells what it is linked by
+    /// instead.
+    #[error("No {} in {path} : {}", if *.degraded == Degraded::Symtab { "dynamic symbols" } else { "symbols" }, .degraded.describe())]
+    Degraded { path: String, degraded: Degraded },
+    /// The saved graph is malformed or from another version at `line`.
+    #[error("Unable to load the saved graph {path} : malformed line {line}")]
+    Saved { path: String, line: usize },
+    /// The providers index is malformed or from another version at `line`.
+    #[error("Unable to load the providers index {path} : malformed line {line}")]
+    Index { path: String, line: usize },
+    /// A [`SymbolSource`](crate::SymbolSource) cannot read the file.
+    #[error("Unable to read {path} : {message}")]
+    Source { path: String, message: String },
+}
+
+impl Error {
+    /// The file the error is about.
+    pub fn path(&self) -> &str {
+        match self {
+            Error::Io { path, .. } | Error::WriteFailed { path, .. } | Error::Parse { path, .. } => path,
+            Error::NonUtf8Symbol { path, .. } | Error::MixedVersioning { path, .. } | Error::Saved { path, .. } => path,
+            Error::Degraded { path, .. } | Error::Index { path, .. } | Error::Source { path, .. } => path,
+        }
+    }
+}
+
+/// A `Result` with the crate [`Error`].
+pub type Result<T> = ::std::result::Result<T, Error>;
+
+/// What a file without dynamic symbols is linked by instead, as in its
+/// `degraded` attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Degraded {
+    /// Its symbol table.
+    Symtab,
+    /// Its DT_NEEDED libraries only.
+    Needed,
+    /// Nothing, the node is left unlinked.
+    Empty,
+}
+
+impl Degraded {
+    /// Its name, as in the `degraded` attribute.
+    pub fn name(self) -> &'static str {
+        match self {
+            Degraded::Symtab => "symtab",
+            Degraded::Needed => "needed",
+            Degraded::Empty => "empty",
+        }
+    }
+
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        [Degraded::Symtab, Degraded::Needed, Degraded::Empty].iter().cloned().find(|d| d.name() == name)
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Degraded::Symtab => "using its symbol table",
+            Degraded::Needed => "only linked to its needed libraries",
+            Degraded::Empty => "left unlinked",
+        }
+    }
+}
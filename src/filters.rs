@@ -0,0 +1,490 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use object::{Object, ObjectSection, ObjectSymbol, SymbolFlags, SymbolKind};
+use regex::Regex;
+
+use elf::symbol_versions;
+
+/// Options applied while parsing binaries.
+#[derive(Debug, Clone, Default)]
+pub struct Filters {
+    /// Skip the well-known runtime libraries (libc, libstdc++, kernel32, ...).
+    pub ignore_system: bool,
+    /// With `ignore_system`, also skip the libraries inside these directories,
+    /// eg. the library path of a cross-compilation sysroot.
+    pub system_dirs: Vec<PathBuf>,
+    /// Keep the `_` prefixed symbols reserved to the compiler and runtime.
+    pub keep_reserved: bool,
+    /// The assembler markers always dropped, eg. the ARM mapping symbols
+    /// `$a`, `$t`, `$d` and `$x`, also matched with a `.` suffix as in `$d.1`.
+    pub markers: Vec<String>,
+    /// Match modules and symbols regardless of the case, always enabled for PE inputs.
+    pub ignore_case: bool,
+    /// `Text` to keep only the functions, `Data` to keep only the data.
+    pub only: Option<SymbolKind>,
+    /// Drop the symbols less visible than this.
+    pub min_visibility: Option<Visibility>,
+    /// Drop the weak definitions and the weak undefined imports.
+    pub no_weak: bool,
+    /// The special ELF definitions exported like the others, the other ones
+    /// are dropped.
+    pub special_exports: Vec<SpecialSymbol>,
+    /// Resolve the imports of the forwarded PE exports to the DLL they are
+    /// forwarded to, rather than through the forwarding DLL.
+    pub follow_forwarders: bool,
+    /// Keep the symbols of the Go runtime and standard internal packages.
+    pub go_runtime: bool,
+    /// Name the symbols of the Go binaries after their package path, eg.
+    /// `net/http` for `net/http.(*Client).Do`.
+    pub go_packages: bool,
+    /// Read only the needed libraries of the files rather than their symbols,
+    /// the nodes are linked to them by `DT_NEEDED` edges; the cache is not
+    /// used.
+    pub needed_only: bool,
+    /// Record the SHA-256 digest of each file as its `sha256` node attribute.
+    pub sha256: bool,
+    /// Label the nodes with their path relative to these prefixes.
+    pub strip_prefixes: Vec<String>,
+    /// File path or basename to node label.
+    pub aliases: HashMap<String, String>,
+}
+
+impl Filters {
+    // symbols without information from the symbol tables are always kept
+    pub(crate) fn accept(&self, info: Option<&SymbolInfo>) -> bool {
+        let info = match info {
+            Some(info) => info,
+            None => return true,
+        };
+
+        if self.no_weak && info.weak {
+            return false;
+        }
+
+        if let Some(min_visibility) = self.min_visibility {
+            if info.visibility < min_visibility {
+                return false;
+            }
+        }
+
+        match (self.only, info.kind) {
+            (_, SymbolKind::Unknown) => true,
+            (Some(SymbolKind::Text), kind) => kind == SymbolKind::Text,
+            (Some(SymbolKind::Data), kind) => kind == SymbolKind::Data || kind == SymbolKind::Tls,
+            _ => true,
+        }
+    }
+
+    /// Why [`Graph::parse_file`](::Graph::parse_file) would leave `path` out, without reading it.
+    ///
+    /// System libraries are recognized by their file name, the soname needs parsing.
+    pub fn skip_reason(&self, path: &str) -> Option<&'static str> {
+        if self.ignore_system && self.is_system(path, basename(path)) {
+            Some("system library")
+        } else if self.node_label(path).is_none() {
+            Some("no valid node name")
+        } else {
+            None
+        }
+    }
+
+    // a well-known soname or a library of the system directories
+    pub(crate) fn is_system(&self, path: &str, soname: &str) -> bool {
+        is_system_library(soname) || self.system_dirs.iter().any(|d| Path::new(path).starts_with(d))
+    }
+
+    // the alias, the basename, or the path relative to a stripped prefix
+    pub(crate) fn node_label(&self, path: &str) -> Option<String> {
+        if let Some(alias) = self.aliases.get(path).or_else(|| self.aliases.get(basename(path))) {
+            return Some(alias.clone());
+        }
+
+        let label = self.mangle_as_valid_dot_name(path)?;
+        if self.strip_prefixes.is_empty() {
+            return Some(label);
+        }
+
+        let path = match self.strip_prefixes.iter().find(|p| path.starts_with(p.as_str())) {
+            Some(prefix) => &path[prefix.len()..],
+            None => path,
+        };
+        match path.rfind('/') {
+            Some(index) => Some(format!("{}/{}", &path[..index], label)),
+            None => Some(label),
+        }
+    }
+
+    // why an export is left out for its binding or section, None if it is kept
+    pub(crate) fn special_reason(&self, info: Option<&SymbolInfo>) -> Option<&'static str> {
+        match info.and_then(|i| i.special) {
+            Some(special) if !self.special_exports.contains(&special) => Some(special.name()),
+            _ => None,
+        }
+    }
+
+    // why a name is left out of the graph, None if it is kept
+    pub(crate) fn dropped_reason(&self, v: &str) -> Option<&'static str> {
+        // blacklisted symbols
+        match v {
+            "_GLOBAL_OFFSET_TABLE_" => return Some("blacklisted"),
+            "" => return Some("empty"),
+            _ => (),
+        };
+
+        // .LC0 and .LC1 are used for constants
+        if v.starts_with(".LC") {
+            return Some("local constant");
+        }
+        // the ARM mapping symbols mark code and data, eg. $d or $x.42
+        let marker = |m: &String| v.strip_prefix(m.as_str()).is_some_and(|rest| rest.is_empty() || rest.starts_with('.'));
+        if self.markers.iter().any(marker) {
+            return Some("assembler marker");
+        }
+        // _ prefixed symbols are compiler reserved
+        if v.starts_with('_') && !self.keep_reserved {
+            return Some("reserved");
+        }
+        None
+    }
+
+    // the Go symbols are package paths with a name, the runtime symbols and
+    // the compiler generated ones are left out
+    pub(crate) fn go_dropped_reason(&self, v: &str) -> Option<&'static str> {
+        if let Some(reason) = self.dropped_reason(v) {
+            return Some(reason);
+        }
+        let package = go_package(v);
+        let runtime = ["type:", "go:", "go."].iter().any(|p| v.starts_with(p))
+            || package == "runtime" || package.starts_with("runtime/") || package.starts_with("internal/");
+        if runtime && !self.go_runtime {
+            return Some("go runtime");
+        }
+        None
+    }
+
+    // the Go symbols keep their package path, only the characters breaking
+    // the DOT labels are replaced
+    pub(crate) fn go_name(&self, v: &str) -> String {
+        let name = if self.go_packages { go_package(v) } else { v };
+        name.chars().map(|c| if c == '"' || c == '\\' || c.is_control() { '_' } else { c }).collect()
+    }
+
+    pub(crate) fn mangle_as_valid_dot_name(&self, v: &str) -> Option<String> {
+        if self.dropped_reason(v).is_some() {
+            return None;
+        }
+
+        // escape file names: return basename
+        let dot = if v.ends_with(".o") {
+            v.len() - 2
+        } else {
+            v.len()
+        };
+        let slash = match v.rfind('/') {
+            Some(index) => index+1,
+            None => 0,
+        };
+
+        // filter invalid dot symbols
+        Some(v[slash..dot].chars()
+            // dot use dash as a edge symbol, translate it
+            .map(|c: char| if c == '-' { '_' } else { c })
+            // dot use dot as a edge symbol, translate it
+            .map(|c: char| if c == '.' { '_' } else { c })
+            .collect::<String>())
+    }
+}
+
+/// A symbol name left out of the graph by the filters.
+#[derive(Debug, Clone)]
+pub struct DroppedSymbol {
+    pub path: String,
+    pub symbol: String,
+    /// `reserved`, `local constant`, `blacklisted`, `empty`, or the
+    /// [`SpecialSymbol::name`] of a definition not in the special exports.
+    pub reason: &'static str,
+}
+
+// the properties of a symbol from the object file symbol tables
+#[derive(Debug, Clone)]
+pub(crate) struct SymbolInfo {
+    pub(crate) kind: SymbolKind,
+    pub(crate) visibility: Visibility,
+    pub(crate) weak: bool,
+    // the st_size of ELF symbols, 0 when unknown
+    pub(crate) size: u64,
+    // the defining section, eg. .text, None for the undefined symbols
+    pub(crate) section: Option<String>,
+    pub(crate) address: u64,
+    // the ELF version the file defines the symbol in, eg. GLIBC_2.2.5 in libc,
+    // None when unversioned or bound to a version of another file
+    pub(crate) version: Option<String>,
+    pub(crate) special: Option<SpecialSymbol>,
+}
+
+/// The ELF definitions the dynamic linker does not bind like the others.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpecialSymbol {
+    /// `STB_GNU_UNIQUE`, a single definition per process whichever library
+    /// is loaded first, eg. the C++ template static members.
+    Unique,
+    /// `SHN_COMMON`, a tentative definition allocated by the linker.
+    Common,
+    /// `SHN_ABS`, a value not relocated, eg. the version names of libc.
+    Absolute,
+}
+
+impl SpecialSymbol {
+    /// `unique`, `common` or `absolute`.
+    pub fn name(self) -> &'static str {
+        match self {
+            SpecialSymbol::Unique => "unique",
+            SpecialSymbol::Common => "common",
+            SpecialSymbol::Absolute => "absolute",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "unique" => Some(SpecialSymbol::Unique),
+            "common" => Some(SpecialSymbol::Common),
+            "absolute" => Some(SpecialSymbol::Absolute),
+            _ => None,
+        }
+    }
+
+    fn of<'data, S: ObjectSymbol<'data>>(sym: &S) -> Option<Self> {
+        match (sym.section(), sym.flags()) {
+            (object::SymbolSection::Common, _) => Some(SpecialSymbol::Common),
+            (object::SymbolSection::Absolute, _) => Some(SpecialSymbol::Absolute),
+            (_, SymbolFlags::Elf { st_info, .. }) if st_info >> 4 == object::elf::STB_GNU_UNIQUE => Some(SpecialSymbol::Unique),
+            _ => None,
+        }
+    }
+}
+
+/// ELF symbol visibility, ordered from the least to the most exposed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Visibility {
+    Internal,
+    Hidden,
+    Protected,
+    Default,
+}
+
+impl Visibility {
+    fn from_flags<Section, Symbol>(flags: SymbolFlags<Section, Symbol>) -> Self {
+        match flags {
+            SymbolFlags::Elf { st_other, .. } => match st_other & 0x3 {
+                object::elf::STV_INTERNAL => Visibility::Internal,
+                object::elf::STV_HIDDEN => Visibility::Hidden,
+                object::elf::STV_PROTECTED => Visibility::Protected,
+                _ => Visibility::Default,
+            },
+            // other formats have no visibility, everything exported is public
+            _ => Visibility::Default,
+        }
+    }
+}
+
+// index the symbol tables by name, the dynamic one takes precedence
+pub(crate) fn symbol_infos<'data>(object_file: &object::File<'data>) -> HashMap<&'data [u8], SymbolInfo> {
+    let versions = symbol_versions(object_file);
+    let dynamic = object_file.dynamic_symbols().map(|s| (s.index().0, s));
+    let mut infos = HashMap::new();
+    // only the dynamic symbols are versioned
+    for (index, sym) in dynamic.chain(object_file.symbols().map(|s| (usize::MAX, s))) {
+        if let Ok(name) = sym.name_bytes() {
+            infos.entry(name).or_insert(SymbolInfo {
+                kind: sym.kind(),
+                visibility: Visibility::from_flags(sym.flags()),
+                weak: sym.is_weak(),
+                size: sym.size(),
+                section: section_name(object_file, sym.section()),
+                address: sym.address(),
+                version: versions.get(&index).cloned(),
+                special: SpecialSymbol::of(&sym),
+            });
+        }
+    }
+    infos
+}
+
+fn section_name(object_file: &object::File, section: object::SymbolSection) -> Option<String> {
+    match section {
+        object::SymbolSection::Section(index) => {
+            let section = object_file.section_by_index(index).ok()?;
+            section.name().ok().map(String::from)
+        }
+        object::SymbolSection::Absolute => Some(String::from("*ABS*")),
+        object::SymbolSection::Common => Some(String::from("*COM*")),
+        _ => None,
+    }
+}
+
+// the well-known runtime libraries, by soname
+pub(crate) fn is_system_library(soname: &str) -> bool {
+    let soname = soname.to_lowercase();
+
+    // unix sonames are versioned after the extension, libc.so.6
+    let stem = match soname.find(".so") {
+        Some(index) => &soname[..index],
+        None => soname.trim_end_matches(".dll"),
+    };
+
+    match stem {
+        "libc" | "libm" | "libdl" | "libpthread" | "librt" | "libstdc++" | "libgcc_s" => true,
+        "kernel32" | "ntdll" | "msvcrt" | "ucrtbase" | "libgcc_s_seh-1" | "libgcc_s_dw2-1" | "libstdc++-6" | "libwinpthread-1" => true,
+        _ => stem.starts_with("ld-linux")
+            || stem.starts_with("api-ms-win-")
+            || stem.starts_with("vcruntime")
+            || stem.starts_with("msvcp"),
+    }
+}
+
+/// Parse the `name -> alias` lines of an alias file, `#` starts a comment.
+pub fn parse_aliases(content: &str) -> Result<HashMap<String, String>, String> {
+    let mut aliases = HashMap::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = match line.find('#') {
+            Some(index) => &line[..index],
+            None => line,
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        match line.find("->") {
+            Some(index) => {
+                let name = line[..index].trim();
+                let alias = line[index+2..].trim();
+                if name.is_empty() || alias.is_empty() {
+                    return Err(format!("line {} : empty name or alias", number + 1));
+                }
+                aliases.insert(name.to_string(), alias.to_string());
+            }
+            None => return Err(format!("line {} : expected name -> alias", number + 1)),
+        }
+    }
+    Ok(aliases)
+}
+
+// the package path of a Go symbol, eg. net/http for net/http.(*Client).Do or
+// main for main.main, the type arguments may be package paths too
+fn go_package(symbol: &str) -> &str {
+    let end = symbol.find('[').unwrap_or(symbol.len());
+    let slash = symbol[..end].rfind('/').map_or(0, |i| i + 1);
+    match symbol[slash..end].find('.') {
+        Some(dot) => &symbol[..slash + dot],
+        None => &symbol[..end],
+    }
+}
+
+// whether name matches the glob pattern, * and ? match any characters and
+// any character
+pub(crate) fn glob_match(pattern: &str, name: &str) -> bool {
+    let (pattern, name): (Vec<char>, Vec<char>) = (pattern.chars().collect(), name.chars().collect());
+    // the last * and where it resumed in name, to backtrack
+    let (mut p, mut n, mut star) = (0, 0, None);
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+pub(crate) fn basename(path: &str) -> &str {
+    match path.rfind('/') {
+        Some(index) => &path[index+1..],
+        None => path,
+    }
+}
+
+/// A sed-like `s/regex/replacement/` rule naming the group of a node, captures are expanded.
+///
+/// The rule matches the node label, or the node attribute NAME when prefixed by `@NAME:`.
+#[derive(Debug, Clone)]
+pub struct GroupRule {
+    attribute: Option<String>,
+    regex: Regex,
+    replacement: String,
+}
+
+impl GroupRule {
+    /// Parse a `[@NAME:]s/regex/replacement/` rule, the error tells what is invalid.
+    pub fn parse(rule: &str) -> Result<Self, String> {
+        let (attribute, substitution) = match rule.strip_prefix('@').and_then(|r| r.find(':').map(|i| (r, i))) {
+            Some((r, index)) => (Some(r[..index].to_string()), &r[index+1..]),
+            None => (None, rule),
+        };
+
+        let mut chars = substitution.chars();
+        if chars.next() != Some('s') {
+            return Err(format!("invalid group rule {} : expected s/regex/replacement/", rule));
+        }
+        let separator = match chars.next() {
+            Some(c) => c,
+            None => return Err(format!("invalid group rule {} : missing separator", rule)),
+        };
+
+        // split on unescaped separators
+        let mut parts = vec![String::new()];
+        while let Some(c) = chars.next() {
+            if c == '\\' {
+                match chars.next() {
+                    Some(n) if n == separator => parts.last_mut().unwrap().push(n),
+                    Some(n) => {
+                        parts.last_mut().unwrap().push(c);
+                        parts.last_mut().unwrap().push(n);
+                    }
+                    None => parts.last_mut().unwrap().push(c),
+                }
+            } else if c == separator {
+                parts.push(String::new());
+            } else {
+                parts.last_mut().unwrap().push(c);
+            }
+        }
+
+        if parts.len() != 3 || !parts[2].is_empty() {
+            return Err(format!("invalid group rule {} : expected s/regex/replacement/", rule));
+        }
+
+        let regex = match Regex::new(&parts[0]) {
+            Ok(regex) => regex,
+            Err(error) => return Err(format!("invalid group rule {} : {}", rule, error)),
+        };
+        Ok(GroupRule { attribute, regex, replacement: parts.remove(1) })
+    }
+
+    // the group name of a matching label or attribute
+    pub(crate) fn apply(&self, label: &str, attributes: &BTreeMap<String, String>) -> Option<String> {
+        let value = match &self.attribute {
+            Some(name) => attributes.get(name)?,
+            None => label,
+        };
+        if self.regex.is_match(value) {
+            Some(self.regex.replace(value, self.replacement.as_str()).into_owned())
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,1841 @@
+use std::collections::btree_map;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::io::Write;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use object::SymbolKind;
+use sha2::{Digest, Sha256};
+
+use error::{Error, Result};
+use filters::{basename, glob_match, DroppedSymbol, Filters, GroupRule, SymbolInfo, Visibility};
+use index;
+use parse;
+use parse::{ParsedFile, PeNames};
+use saved;
+use source::SymbolSource;
+use strings::Strings;
+use writer::GraphWriter;
+
+/// The libraries (nodes) and the symbols they import from each other (edges).
+///
+/// Nodes, edges and symbols are identified by the 32 bits index of their interned
+/// name, use [`Graph::label`] and [`Graph::symbol`] to get it back; the
+/// [`Node`] and [`Edge`] of [`Graph::nodes`] and [`Graph::edges`] also tell
+/// their names.
+pub struct Graph {
+    pub(crate) name: String,
+
+    pub(crate) nodes: HashMap<u32, NodeProperties>,
+    pub(crate) edges: HashMap<(u32, u32), EdgeProperties>,
+
+    pub(crate) clusters: Vec<SubGraph>,
+    pub(crate) strings: Strings,
+    /// Options applied by [`Graph::parse_file`].
+    pub filters: Filters,
+    /// Directory keeping the symbols of the parsed files, unchanged files are not parsed again.
+    pub cache: Option<PathBuf>,
+    /// Map the files in memory rather than reading them, when the filesystem
+    /// allows it (true by default).
+    pub mmap: bool,
+    /// Readers of the inputs other than object files, asked in order before
+    /// parsing them as one.
+    pub sources: Vec<Arc<dyn SymbolSource>>,
+    /// Keep the symbols carried by each edge, or only their number when they
+    /// are not rendered (true by default).
+    pub edge_symbols: bool,
+    /// Which of the libraries exporting a symbol its importers depend on
+    /// ([`Resolution::First`] by default).
+    pub resolution: Resolution,
+    /// Which way the edges point in the outputs ([`EdgeDirection::Uses`] by
+    /// default), [`Graph::edges`] are always `(importer, exporter)`.
+    pub edge_direction: EdgeDirection,
+
+    // rendered node names, nodes are identified by their full path
+    labels: HashMap<u32, String>,
+    // recoverable errors, the faulty symbols were skipped
+    warnings: Vec<Error>,
+    // the names left out by the filters
+    dropped: Vec<DroppedSymbol>,
+    stats: Stats,
+    // the (importer, symbol) weak references, the edges made only of them are optional
+    weak_imports: HashSet<(u32, u32)>,
+    
+    // temporary map undefined symbol ->  lib
+    pub(crate) undefined: HashMap<u32, Vec<u32>>,
+    // temporary map defined symbol -> libs, in the parsing order
+    defined: HashMap<u32, Vec<u32>>,
+    // resolved symbol -> importers, for the later providers of every symbol
+    imported: HashMap<u32, Vec<u32>>,
+    // every resolved (importer, exporter, symbol), bound again in load order
+    // as the libraries are added
+    candidates: Vec<(u32, u32, u32)>,
+    // the PE exports by ordinal and forwarded, for the later importers
+    pe_names: PeNames,
+    // plugin -> the host loading it at runtime
+    loaders: HashMap<u32, u32>,
+}
+
+/// Which way the edges point in the outputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeDirection {
+    /// From the importer to the library it uses.
+    Uses,
+    /// From the exporter to the libraries it provides symbols to.
+    Provides,
+}
+
+impl EdgeDirection {
+    /// The ends of an edge in the output order.
+    pub fn orient<T>(self, importer: T, exporter: T) -> (T, T) {
+        match self {
+            EdgeDirection::Uses => (importer, exporter),
+            EdgeDirection::Provides => (exporter, importer),
+        }
+    }
+
+    /// Its name, as in the JSON graph.
+    pub fn name(self) -> &'static str {
+        match self {
+            EdgeDirection::Uses => "uses",
+            EdgeDirection::Provides => "provides",
+        }
+    }
+}
+
+/// Which providers an import is resolved to, when several libraries export
+/// the same symbol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    /// An edge to every library exporting the symbol.
+    All,
+    /// An edge to the first library exporting it, in the parsing order.
+    First,
+    /// An edge to the library the dynamic linker would bind: the first
+    /// exporting the symbol in the breadth-first order of the `needed`
+    /// libraries of the importer, else the first parsed.
+    ElfOrder,
+}
+
+/// How the connections of a node are counted by [`Graph::most_connected`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Degree {
+    /// The nodes depending on it.
+    In,
+    /// The nodes it depends on.
+    Out,
+    /// Both.
+    Total,
+}
+
+impl Graph {
+    /// An empty graph named `name`, with the default filters and settings.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: String::from(name),
+            nodes: HashMap::new(),
+            edges: HashMap::new(),
+
+            clusters: Vec::new(),
+            strings: Strings::new(),
+            filters: Filters::default(),
+            cache: None,
+            mmap: true,
+            sources: Vec::new(),
+            edge_symbols: true,
+            resolution: Resolution::First,
+            edge_direction: EdgeDirection::Uses,
+
+            labels: HashMap::new(),
+            warnings: Vec::new(),
+            dropped: Vec::new(),
+            stats: Stats::default(),
+            weak_imports: HashSet::new(),
+            
+            undefined: HashMap::new(),
+            defined: HashMap::new(),
+            imported: HashMap::new(),
+            candidates: Vec::new(),
+            pe_names: PeNames::default(),
+            loaders: HashMap::new(),
+        }
+    }
+
+    /// Parse a binary file using object, adding its exports and imports to the graph.
+    ///
+    /// A graph written by [`Graph::save`], named `.sgz`, adds all its nodes.
+    /// On error the graph is left unchanged.
+    pub fn parse_file(&mut self, filename: &str) -> Result<()> {
+        if saved::is_saved(filename) {
+            self.add_parsed(saved::read(filename)?);
+        } else if let Some(mut parsed) = parse::parse(filename, &self.filters, self.cache.as_deref(), self.mmap, &self.sources)? {
+            parse::name_pe_imports(std::slice::from_mut(&mut parsed), &self.filters, &mut self.pe_names);
+            self.insert_parsed(parsed);
+        }
+        Ok(())
+    }
+
+    /// Add the nodes of a graph written by [`Graph::save`], with `files`
+    /// parsed again in place of their saved nodes.
+    ///
+    /// The saved nodes keep their order, the files that were not saved are
+    /// added after them and every import is resolved again. Returns the errors
+    /// of the files that could not be parsed, they are left out of the graph.
+    pub fn update<F>(&mut self, saved: &str, files: &[String], jobs: usize, on_file: F) -> Result<Vec<Error>>
+        where F: Fn(&str) + Sync
+    {
+        let saved_files = saved::read(saved)?;
+        // the same file, whatever the directory it was named from
+        let canonical = |path: &str| fs::canonicalize(path).unwrap_or_else(|_| PathBuf::from(path));
+        let changed: HashSet<PathBuf> = files.iter().map(|f| canonical(f)).collect();
+
+        let mut errors = vec![];
+        let mut parsed: HashMap<PathBuf, ParsedFile> = HashMap::new();
+        for result in parse::parse_all(files, jobs, &self.filters, self.cache.as_deref(), self.mmap, &self.sources, on_file) {
+            match result {
+                Ok(files) => parsed.extend(files.into_iter().map(|f| (canonical(&f.path), f))),
+                Err(error) => errors.push(error),
+            }
+        }
+
+        let mut updated = Vec::with_capacity(saved_files.len() + parsed.len());
+        for file in saved_files {
+            let path = canonical(&file.path);
+            if !changed.contains(&path) {
+                updated.push(file);
+            } else if let Some(file) = parsed.remove(&path) {
+                updated.push(file);
+            }
+        }
+        // the new files, in the given order
+        for file in files {
+            updated.extend(parsed.remove(&canonical(file)));
+        }
+
+        let started = Instant::now();
+        parse::name_pe_imports(&mut updated, &self.filters, &mut self.pe_names);
+        let interned: Vec<InternedFile> = updated.into_iter().map(|f| self.intern(f)).collect();
+        self.resolve(&interned, jobs);
+        self.stats.resolve += started.elapsed();
+        Ok(errors)
+    }
+
+    /// Parse the files on `jobs` threads then add them, in order, to the graph.
+    ///
+    /// `on_file` is called from the worker threads as each file is started.
+    /// Returns the errors of the files left out of the graph.
+    pub fn parse_files<F>(&mut self, files: &[String], jobs: usize, on_file: F) -> Vec<Error>
+        where F: Fn(&str) + Sync
+    {
+        let results = parse::parse_all(files, jobs, &self.filters, self.cache.as_deref(), self.mmap, &self.sources, on_file);
+
+        // at most the paths and every symbol name, interned at once
+        let strings: usize = results.iter()
+            .filter_map(|r| r.as_ref().ok())
+            .flatten()
+            .map(|p| 1 + p.exports.len() + p.imports.len())
+            .sum();
+        self.strings.reserve(strings);
+
+        let started = Instant::now();
+        let parsed_count = results.iter().filter_map(|r| r.as_ref().ok()).map(Vec::len).sum();
+        self.nodes.reserve(parsed_count);
+        self.labels.reserve(parsed_count);
+
+        let mut errors = vec![];
+        let mut parsed_files = Vec::with_capacity(parsed_count);
+        for result in results {
+            match result {
+                Ok(parsed) => parsed_files.extend(parsed),
+                Err(error) => errors.push(error),
+            }
+        }
+        parse::name_pe_imports(&mut parsed_files, &self.filters, &mut self.pe_names);
+        let interned: Vec<InternedFile> = parsed_files.into_iter().map(|p| self.intern(p)).collect();
+        self.resolve(&interned, jobs);
+        self.stats.resolve += started.elapsed();
+        errors
+    }
+
+    /// Attribute the imports no input exports to the libraries of a sysroot,
+    /// once all the inputs are parsed.
+    ///
+    /// Only the libraries exporting an unresolved symbol are added, without
+    /// their own imports and with the `external` attribute; the first of
+    /// `files` exporting a symbol provides it. Returns the errors of the files
+    /// that could not be parsed.
+    pub fn resolve_against(&mut self, files: &[String], jobs: usize) -> Vec<Error> {
+        // the system libraries are what is looked for
+        let filters = Filters { ignore_system: false, ..self.filters.clone() };
+        let mut errors = vec![];
+        for chunk in files.chunks(jobs.max(1) * 4) {
+            let chunk: Vec<String> = chunk.iter()
+                .filter(|f| self.strings.get(f.as_str()).is_none_or(|f| !self.nodes.contains_key(&f)))
+                .cloned()
+                .collect();
+            let mut providers = vec![];
+            for result in parse::parse_all(&chunk, jobs, &filters, self.cache.as_deref(), self.mmap, &self.sources, |_| ()) {
+                match result {
+                    Ok(parsed) => {
+                        for parsed in parsed {
+                            let provides = parsed.exports.iter()
+                                .filter_map(|s| self.strings.get(s.as_str()))
+                                .any(|s| self.undefined.contains_key(&s));
+                            if provides {
+                                providers.push(parsed);
+                            }
+                        }
+                    }
+                    Err(error) => errors.push(error),
+                }
+            }
+
+            let started = Instant::now();
+            let mut interned = vec![];
+            for mut parsed in providers {
+                parsed.imports.clear();
+                parsed.import_weak.clear();
+                parsed.attributes.insert(String::from("external"), String::from("true"));
+                interned.push(self.intern(parsed));
+            }
+            self.resolve(&interned, jobs);
+            self.stats.resolve += started.elapsed();
+        }
+        errors
+    }
+
+    /// Parse the libraries needed by the nodes, looked up next to their
+    /// importer then in `dirs` with their multiarch subdirectories, eg.
+    /// `/usr/lib/x86_64-linux-gnu`, and the libraries they need in turn, up to
+    /// `max_depth` levels.
+    ///
+    /// The libraries matching one of the `stop_at` globs, on their path when
+    /// the glob holds a `/` else on their soname, are parsed but not
+    /// followed. Returns the errors of the libraries that could not be parsed.
+    pub fn follow_dependencies(&mut self, dirs: &[PathBuf], stop_at: &[String], max_depth: usize, jobs: usize) -> Vec<Error> {
+        // with the multiarch directories, eg. /usr/lib/x86_64-linux-gnu
+        let mut search = vec![];
+        for dir in dirs {
+            let mut subdirs: Vec<PathBuf> = fs::read_dir(dir).into_iter()
+                .flatten()
+                .filter_map(|e| e.ok().map(|e| e.path()))
+                .filter(|p| p.is_dir() && p.file_name().is_some_and(|n| n.to_string_lossy().contains("-linux-")))
+                .collect();
+            subdirs.sort();
+            search.push(dir.clone());
+            search.extend(subdirs);
+        }
+
+        // the sonames already in the graph or looked for
+        let mut known: HashSet<String> = HashSet::new();
+        let mut importers: Vec<u32> = vec![];
+        for (node, properties) in self.nodes() {
+            known.extend(properties.attributes().get("soname").cloned());
+            known.extend(self.path(node).and_then(|p| Path::new(p).file_name()).map(|n| n.to_string_lossy().into_owned()));
+            importers.push(node);
+        }
+        // the first importer looking for a soname finds it, in the parsing order
+        importers.sort_unstable();
+
+        let mut errors = vec![];
+        let mut depth = 0;
+        while depth < max_depth && !importers.is_empty() {
+            depth += 1;
+            let mut files = vec![];
+            let mut stopped = HashSet::new();
+            for node in importers {
+                let properties = match self.nodes.get(&node) {
+                    Some(properties) => properties,
+                    None => continue,
+                };
+                let origin = self.path(node).and_then(|p| Path::new(p).parent()).map(Path::to_path_buf);
+                let needed = properties.attributes.get("needed").map(|n| n.split(' ').map(String::from).collect()).unwrap_or_else(Vec::new);
+                for soname in needed.into_iter().filter(|s| !s.is_empty()) {
+                    if !known.insert(soname.clone()) {
+                        continue;
+                    }
+                    let found = origin.iter().chain(&search).map(|d| d.join(&soname)).find(|p| p.is_file());
+                    match found.and_then(|p| p.to_str().map(String::from)) {
+                        Some(path) => {
+                            if stop_at.iter().any(|g| glob_match(g, if g.contains('/') { &path } else { &soname })) {
+                                stopped.insert(path.clone());
+                            }
+                            files.push(path);
+                        }
+                        None => debug!("Needed {} not found", soname),
+                    }
+                }
+            }
+            if files.is_empty() {
+                break;
+            }
+            info!("Following {} needed libraries, depth {}", files.len(), depth);
+            errors.extend(self.parse_files(&files, jobs, |_| ()));
+            let followed: HashSet<&String> = files.iter().filter(|f| !stopped.contains(*f)).collect();
+            importers = self.nodes()
+                .map(|(n, _)| n)
+                .filter(|n| self.path(*n).is_some_and(|p| followed.contains(&p.to_string())))
+                .collect();
+            importers.sort_unstable();
+        }
+        errors
+    }
+
+    /// Attribute the imports no input exports to the libraries of an index
+    /// written by [`Graph::save_index`], as [`Graph::resolve_against`]
+    /// without parsing them.
+    ///
+    /// The first library of the index exporting a symbol provides it, the
+    /// libraries get the exports they provide only, with their sizes.
+    pub fn resolve_against_index(&mut self, path: &str) -> Result<()> {
+        let index = index::read(path)?;
+        let mut providers: BTreeMap<usize, Vec<(String, u64)>> = BTreeMap::new();
+        for (symbol, libraries) in &index.providers {
+            if !self.strings.get(symbol.as_str()).is_some_and(|s| self.undefined.contains_key(&s)) {
+                continue;
+            }
+            // the first library of the index exporting it provides it
+            if let Some((library, size)) = libraries.first() {
+                providers.entry(*library).or_default().push((symbol.clone(), *size));
+            }
+        }
+
+        let started = Instant::now();
+        let mut interned = vec![];
+        for (library, symbols) in providers {
+            let (library_path, label, attributes) = &index.libraries[library];
+            if self.strings.get(library_path.as_str()).is_some_and(|l| self.nodes.contains_key(&l)) {
+                continue;
+            }
+            let mut file = ParsedFile {
+                path: library_path.clone(),
+                label: label.clone(),
+                exports: vec![],
+                export_infos: vec![],
+                export_names: vec![],
+                imports: vec![],
+                import_weak: vec![],
+                ordinals: vec![],
+                forwards: vec![],
+                attributes: attributes.clone(),
+                warnings: vec![],
+                dropped: vec![],
+                open_time: Duration::ZERO,
+                parse_time: Duration::ZERO,
+            };
+            file.attributes.insert(String::from("external"), String::from("true"));
+            for (symbol, size) in symbols {
+                file.exports.push(symbol);
+                file.export_names.push(None);
+                file.export_infos.push(Some(SymbolInfo {
+                    kind: SymbolKind::Unknown,
+                    visibility: Visibility::Default,
+                    weak: false,
+                    size,
+                    section: None,
+                    address: 0,
+                    version: None,
+                    special: None,
+                }));
+            }
+            interned.push(self.intern(file));
+        }
+        self.resolve(&interned, 1);
+        self.stats.resolve += started.elapsed();
+        Ok(())
+    }
+
+    /// Write the symbols exported by each node, gzip compressed, as an index
+    /// of their providers for [`Graph::resolve_against_index`].
+    pub fn save_index<W: Write>(&self, writer: W) -> io::Result<()> {
+        index::write(&self.parsed_files(), writer)
+    }
+
+    /// Write the nodes with their attributes, exports and imports, gzip
+    /// compressed, to be passed again as a `.sgz` input and merged with other
+    /// files without parsing them.
+    ///
+    /// The nodes are written as parsed, call it before grouping them. The
+    /// resolved imports are only known with [`Graph::edge_symbols`].
+    pub fn save<W: Write>(&self, writer: W) -> io::Result<()> {
+        saved::write(&self.parsed_files(), writer)
+    }
+
+    // the nodes as the parsed files they were made of, in the parsing order
+    pub(crate) fn parsed_files(&self) -> Vec<ParsedFile> {
+        let mut imports: HashMap<u32, BTreeSet<u32>> = HashMap::new();
+        for ((importer, _), properties) in &self.edges {
+            imports.entry(*importer).or_default().extend(properties.symbols.keys());
+        }
+        for (symbol, libs) in &self.undefined {
+            for lib in libs {
+                imports.entry(*lib).or_default().insert(*symbol);
+            }
+        }
+
+        let mut seen = HashSet::new();
+        let resolve = |id: u32| self.strings.resolve(id).map(String::from);
+        let mut files = vec![];
+        for (node, _) in self.stats.files.iter().filter(|(n, _)| seen.insert(*n)) {
+            let (properties, path, label) = match (self.nodes.get(node), resolve(*node), self.label(*node)) {
+                (Some(properties), Some(path), Some(label)) => (properties, path, label.to_string()),
+                _ => continue,
+            };
+            let (ordinals, forwards) = (self.pe_names.ordinals_of(&path, &self.filters), self.pe_names.forwards_of(&path, &self.filters));
+            let mut file = ParsedFile {
+                path,
+                label,
+                exports: vec![],
+                export_infos: vec![],
+                export_names: vec![],
+                imports: vec![],
+                import_weak: vec![],
+                ordinals,
+                forwards,
+                attributes: properties.attributes.clone(),
+                warnings: vec![],
+                dropped: vec![],
+                open_time: Duration::ZERO,
+                parse_time: Duration::ZERO,
+            };
+            for (symbol, count) in &properties.symbols {
+                let name = match resolve(*symbol) {
+                    Some(name) => name,
+                    None => continue,
+                };
+                let info = properties.definitions.get(symbol).map(|d| SymbolInfo {
+                    kind: d.kind,
+                    visibility: Visibility::Default,
+                    weak: false,
+                    size: d.size,
+                    section: d.section.and_then(resolve),
+                    address: d.address,
+                    version: d.version.and_then(resolve),
+                    special: None,
+                });
+                let raw = properties.names.get(symbol).and_then(|n| resolve(*n));
+                for _ in 0..*count {
+                    file.exports.push(name.clone());
+                    file.export_infos.push(info.clone());
+                    file.export_names.push(raw.clone());
+                }
+            }
+            for symbol in imports.get(node).into_iter().flatten() {
+                if let Some(name) = resolve(*symbol) {
+                    file.imports.push(name);
+                    file.import_weak.push(self.weak_imports.contains(&(*node, *symbol)));
+                }
+            }
+            files.push(file);
+        }
+        files
+    }
+
+    // add the parsed files in order, their imports resolved at once
+    pub(crate) fn add_parsed(&mut self, mut files: Vec<ParsedFile>) {
+        parse::name_pe_imports(&mut files, &self.filters, &mut self.pe_names);
+        let started = Instant::now();
+        let interned: Vec<InternedFile> = files.into_iter().map(|f| self.intern(f)).collect();
+        self.resolve(&interned, 1);
+        self.stats.resolve += started.elapsed();
+    }
+
+    fn insert_parsed(&mut self, parsed: ParsedFile) {
+        let started = Instant::now();
+        let interned = self.intern(parsed);
+        self.resolve(&[interned], 1);
+        self.stats.resolve += started.elapsed();
+    }
+
+    /// The recoverable errors met while parsing, eg. skipped symbols.
+    pub fn warnings(&self) -> &[Error] {
+        &self.warnings
+    }
+
+    /// The symbol names left out by the filters, eg. the compiler reserved ones.
+    pub fn dropped(&self) -> &[DroppedSymbol] {
+        &self.dropped
+    }
+
+    /// The time spent building the graph.
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    // add the node of a parsed file, its imports are resolved later
+    fn intern(&mut self, parsed: ParsedFile) -> InternedFile {
+        let filename = self.strings.get_or_intern(parsed.path);
+        self.labels.insert(filename, parsed.label);
+        self.warnings.extend(parsed.warnings);
+        self.dropped.extend(parsed.dropped);
+        self.stats.open += parsed.open_time;
+        self.stats.parse += parsed.parse_time;
+        self.stats.files.push((filename, parsed.open_time + parsed.parse_time));
+
+        let exports: Vec<u32> = parsed.exports.iter().map(|s| self.strings.get_or_intern(s.as_str())).collect();
+        let imports: Vec<u32> = parsed.imports.iter().map(|s| self.strings.get_or_intern(s.as_str())).collect();
+        for (symbol, _) in imports.iter().zip(&parsed.import_weak).filter(|(_, weak)| **weak) {
+            self.weak_imports.insert((filename, *symbol));
+        }
+
+        // render in the label
+        let mut properties = NodeProperties { attributes: parsed.attributes, ..NodeProperties::default() };
+        for ((symbol, info), raw) in exports.iter().zip(parsed.export_infos).zip(parsed.export_names) {
+            properties.insert(*symbol);
+            if let Some(raw) = raw {
+                properties.names.insert(*symbol, self.strings.get_or_intern(raw));
+            }
+            // the first definition, as streamed
+            if let (Some(info), 1) = (info, properties.count(*symbol)) {
+                let section = info.section.map(|s| self.strings.get_or_intern(s));
+                let version = info.version.map(|v| self.strings.get_or_intern(v));
+                properties.definitions.insert(*symbol, Definition { kind: info.kind, size: info.size, section, address: info.address, version });
+            }
+        }
+        self.nodes.insert(filename, properties);
+        (filename, exports, imports)
+    }
+
+    // link the imports of the files to the exports of the files before or
+    // after them
+    //
+    // each symbol is resolved on its own, the symbols are split into one shard
+    // per job resolved concurrently
+    fn resolve(&mut self, files: &[InternedFile], jobs: usize) {
+        // the imports no file exports cannot be resolved, they are rejected
+        // before looking the maps up
+        let mut exported = SymbolSet::default();
+        for symbol in self.defined.keys().chain(files.iter().flat_map(|(_, exports, _)| exports)) {
+            exported.insert(*symbol);
+        }
+        let exported = &exported;
+
+        // sized from the symbol counts, spread evenly over the shards
+        let shards = jobs.max(1);
+        let exports: usize = files.iter().map(|(_, exports, _)| exports.len()).sum();
+        let imports: usize = files.iter().map(|(_, _, imports)| imports.len()).sum();
+        let missing = files.iter()
+            .flat_map(|(_, _, imports)| imports)
+            .filter(|s| !exported.contains(**s))
+            .count();
+        // the load order needs every provider to choose from
+        let all = self.resolution != Resolution::First;
+        let mut resolvers: Vec<Resolver> = (0..shards).map(|_| Resolver { all, ..Resolver::default() }).collect();
+        if shards == 1 {
+            resolvers[0].defined = std::mem::take(&mut self.defined);
+            resolvers[0].undefined = std::mem::take(&mut self.undefined);
+            resolvers[0].imported = std::mem::take(&mut self.imported);
+        } else {
+            for (symbol, libs) in self.defined.drain() {
+                resolvers[symbol as usize % shards].defined.insert(symbol, libs);
+            }
+            for (symbol, libs) in self.undefined.drain() {
+                resolvers[symbol as usize % shards].undefined.insert(symbol, libs);
+            }
+            for (symbol, libs) in self.imported.drain() {
+                resolvers[symbol as usize % shards].imported.insert(symbol, libs);
+            }
+        }
+        for resolver in resolvers.iter_mut() {
+            resolver.defined.reserve(exports / shards);
+            resolver.undefined.reserve(missing / shards);
+            resolver.edges.reserve((imports - missing) / shards);
+        }
+
+        if shards == 1 {
+            resolvers[0].insert_all(files, exported, 0, 1);
+        } else {
+            thread::scope(|scope| {
+                for (shard, resolver) in resolvers.iter_mut().enumerate() {
+                    scope.spawn(move || resolver.insert_all(files, exported, shard, shards));
+                }
+            });
+        }
+
+        self.defined.reserve(resolvers.iter().map(|r| r.defined.len()).sum());
+        self.undefined.reserve(resolvers.iter().map(|r| r.undefined.len()).sum());
+        for resolver in resolvers {
+            self.defined.extend(resolver.defined);
+            self.undefined.extend(resolver.undefined);
+            self.imported.extend(resolver.imported);
+            if self.resolution == Resolution::ElfOrder {
+                self.candidates.extend(resolver.edges);
+                continue;
+            }
+            for (importer, exporter, symbol) in resolver.edges {
+                self.insert_edge(importer, exporter, symbol);
+            }
+        }
+        if self.resolution == Resolution::ElfOrder {
+            self.bind_in_load_order();
+        }
+        self.link_needed();
+    }
+
+    /// Record that `host` loads the `plugins` at runtime, eg. with dlopen:
+    /// the host depends on each of them through a `loaded at runtime`
+    /// symbol and they get its label as their `plugin` attribute.
+    ///
+    /// The imports of the plugins are resolved to the exports of the host
+    /// as to any other node; with [`Resolution::ElfOrder`] the host and its
+    /// needed libraries come first in their load order, as for dlopen.
+    pub fn attach_plugins(&mut self, host: u32, plugins: &[u32]) {
+        let label = self.label(host).unwrap_or_default().to_string();
+        for plugin in plugins.iter().filter(|p| **p != host) {
+            self.set_attribute(*plugin, "plugin", &label);
+            self.loaders.insert(*plugin, host);
+        }
+        if self.resolution == Resolution::ElfOrder {
+            self.bind_in_load_order();
+            self.link_needed();
+        } else {
+            let symbol = self.strings.get_or_intern("loaded at runtime");
+            for plugin in plugins.iter().filter(|p| **p != host) {
+                self.insert_edge(host, *plugin, symbol);
+            }
+        }
+    }
+
+    // the files without symbols, or all of them with needed_only, depend on
+    // their DT_NEEDED libraries through a DT_NEEDED symbol
+    fn link_needed(&mut self) {
+        let all = self.filters.needed_only;
+        let degraded: Vec<u32> = self.nodes.iter()
+            .filter(|(_, p)| all || p.attributes.get("degraded").is_some_and(|d| d == "needed"))
+            .map(|(n, _)| *n)
+            .collect();
+        if degraded.is_empty() {
+            return;
+        }
+        let symbol = self.strings.get_or_intern("DT_NEEDED");
+        let sonames = self.sonames();
+        let mut edges = vec![];
+        for importer in degraded {
+            let needed = self.nodes[&importer].attributes.get("needed").into_iter().flat_map(|n| n.split(' '));
+            edges.extend(needed.filter_map(|n| sonames.get(n)).map(|exporter| (importer, *exporter)));
+        }
+        for (importer, exporter) in edges {
+            self.insert_edge(importer, exporter, symbol);
+        }
+    }
+
+    // the libraries by soname, or file name without one
+    pub(crate) fn sonames(&self) -> HashMap<&str, u32> {
+        let mut sonames = HashMap::new();
+        for (node, properties) in &self.nodes {
+            let name = match (properties.attributes.get("soname"), self.strings.resolve(*node)) {
+                (Some(soname), _) => soname.as_str(),
+                (None, Some(path)) => basename(path),
+                (None, None) => continue,
+            };
+            sonames.entry(name).or_insert(*node);
+        }
+        sonames
+    }
+
+    fn insert_edge(&mut self, importer: u32, exporter: u32, symbol: u32) {
+        let size = self.nodes.get(&exporter).map_or(0, |p| p.size(symbol));
+        let weak = self.weak_imports.contains(&(importer, symbol));
+        self.edges.entry((importer, exporter)).or_default().insert(symbol, self.edge_symbols, size, weak);
+    }
+
+    // rebuild the edges from the candidates, a newly added library may be
+    // found before the provider of an import in its load order
+    fn bind_in_load_order(&mut self) {
+        let sonames = self.sonames();
+        let parsed: HashMap<u32, usize> = self.stats.files.iter().enumerate().map(|(i, (node, _))| (*node, i)).collect();
+
+        let mut orders: HashMap<u32, HashMap<u32, usize>> = HashMap::new();
+        let mut chosen: HashMap<(u32, u32), u32> = HashMap::new();
+        for (importer, exporter, symbol) in &self.candidates {
+            let order = orders.entry(*importer).or_insert_with(|| {
+                // breadth-first from the importer, as ld.so searches
+                let mut order = HashMap::new();
+                // a plugin sees the global scope of its host first
+                let mut queue = VecDeque::new();
+                queue.extend(self.loaders.get(importer));
+                queue.push_back(*importer);
+                while let Some(node) = queue.pop_front() {
+                    if order.contains_key(&node) {
+                        continue;
+                    }
+                    order.insert(node, order.len());
+                    let needed = self.nodes.get(&node).and_then(|p| p.attributes.get("needed"));
+                    queue.extend(needed.into_iter().flat_map(|n| n.split(' ')).filter_map(|n| sonames.get(n)));
+                }
+                order
+            });
+            let rank = |node: u32| (order.get(&node).cloned().unwrap_or(usize::MAX), parsed.get(&node).cloned().unwrap_or(usize::MAX));
+            chosen.entry((*importer, *symbol))
+                .and_modify(|c| if rank(*exporter) < rank(*c) { *c = *exporter })
+                .or_insert(*exporter);
+        }
+
+        self.edges.clear();
+        for (importer, exporter, symbol) in self.candidates.clone() {
+            if chosen.get(&(importer, symbol)) == Some(&exporter) {
+                self.insert_edge(importer, exporter, symbol);
+            }
+        }
+        if !self.loaders.is_empty() {
+            let symbol = self.strings.get_or_intern("loaded at runtime");
+            let mut loaders: Vec<(u32, u32)> = self.loaders.iter().map(|(plugin, host)| (*host, *plugin)).collect();
+            loaders.sort();
+            for (host, plugin) in loaders {
+                self.insert_edge(host, plugin, symbol);
+            }
+        }
+    }
+
+    /// The rendered name of a node.
+    pub fn label(&self, node: u32) -> Option<&str> {
+        match self.labels.get(&node) {
+            Some(label) => Some(label),
+            None => self.strings.resolve(node),
+        }
+    }
+
+    /// The identifiers of the nodes in the outputs, eg. `n{id}` in DOT, stable
+    /// across runs: a hash of their label, or of their path for the nodes
+    /// sharing the label of an earlier parsed one.
+    pub fn node_ids(&self) -> HashMap<u32, u64> {
+        // with the ends of the edges and the members of the clusters, in case
+        let nodes: BTreeSet<u32> = self.nodes.keys().cloned()
+            .chain(self.edges.keys().flat_map(|(n1, n2)| vec![*n1, *n2]))
+            .chain(self.clusters.iter().flat_map(|c| c.nodes.keys().cloned()))
+            .collect();
+        let mut labels = HashSet::new();
+        nodes.into_iter()
+            .map(|n| (n, node_id(&mut labels, self.label(n).unwrap_or_default(), self.path(n).unwrap_or_default())))
+            .collect()
+    }
+
+    // the symbols of an edge by name, their ids depend on the parsing order
+    pub(crate) fn sorted_symbols(&self, properties: &EdgeProperties) -> Vec<(&str, u32)> {
+        let mut symbols: Vec<(&str, u32)> = properties.symbols().filter_map(|s| Some((self.strings.resolve(*s)?, *s))).collect();
+        symbols.sort_unstable();
+        symbols
+    }
+
+    /// The file a node was parsed from, case folded for PE inputs.
+    pub fn path(&self, node: u32) -> Option<&str> {
+        self.strings.resolve(node)
+    }
+
+    /// The name of an interned symbol or section.
+    pub fn symbol(&self, symbol: u32) -> Option<&str> {
+        self.strings.resolve(symbol)
+    }
+
+    /// Attach `value` to `node` under `name`, replacing the previous value.
+    pub fn set_attribute(&mut self, node: u32, name: &str, value: &str) {
+        if let Some(properties) = self.nodes.get_mut(&node) {
+            properties.attributes.insert(name.to_string(), value.to_string());
+        }
+    }
+
+    /// The nodes with their exported symbols.
+    pub fn nodes(&self) -> impl Iterator<Item = (u32, Node<'_>)> {
+        self.nodes.iter().map(move |(n, p)| (*n, Node { graph: self, node: *n, properties: p }))
+    }
+
+    /// The `(importer, exporter)` edges with the symbols they carry.
+    pub fn edges(&self) -> impl Iterator<Item = ((u32, u32), Edge<'_>)> {
+        self.edges.iter().map(move |(e, p)| (*e, Edge { graph: self, edge: *e, properties: p }))
+    }
+
+    /// Render the graph through `writer`: its header, the nodes of each
+    /// cluster then the others, the edges, then its end.
+    ///
+    /// The nodes come sorted by their [`Graph::node_ids`] and the edges by
+    /// the ones of their ends, so that the same inputs render the same.
+    pub fn write(&self, writer: &mut dyn GraphWriter, out: &mut dyn fmt::Write) -> fmt::Result {
+        let ids = self.node_ids();
+        writer.write_header(self, out)?;
+        for c in &self.clusters {
+            let name = self.strings.resolve(c.name);
+            let mut members: Vec<u32> = c.nodes.keys().cloned().collect();
+            members.sort_by_key(|n| ids[n]);
+            for node in members {
+                if let Some(properties) = self.nodes.get(&node) {
+                    writer.write_node(Node { graph: self, node, properties }, name, out)?;
+                }
+            }
+        }
+        let mut nodes: Vec<(&u32, &NodeProperties)> = self.nodes.iter().collect();
+        nodes.sort_by_key(|(n, _)| ids[n]);
+        for (node, properties) in nodes {
+            // already written inside its cluster
+            if !self.clusters.iter().any(|c| c.nodes.contains_key(node)) {
+                writer.write_node(Node { graph: self, node: *node, properties }, None, out)?;
+            }
+        }
+        let mut edges: Vec<(&(u32, u32), &EdgeProperties)> = self.edges.iter().collect();
+        edges.sort_by_key(|((n1, n2), _)| self.edge_direction.orient(ids[n1], ids[n2]));
+        for (edge, properties) in edges {
+            writer.write_edge(Edge { graph: self, edge: *edge, properties }, out)?;
+        }
+        writer.finish(self, out)
+    }
+
+    /// Remove the symbols from the edges, keeping their count and size.
+    pub fn merge(&mut self) {
+        for e in self.edges.values_mut() {
+            let kept = std::mem::take(&mut e.symbols);
+            e.counted.extend(kept.into_keys());
+            e.weak.clear();
+        }
+    }
+
+    /// Assign each node to the cluster named after the first matching rule.
+    pub fn group(&mut self, rules: &[GroupRule]) {
+        let mut nodes: Vec<u32> = self.nodes.keys().cloned().collect();
+        nodes.sort();
+
+        for node in nodes {
+            let attributes = &self.nodes[&node].attributes;
+            let group = match self.label(node) {
+                Some(label) => match rules.iter().filter_map(|r| r.apply(label, attributes)).next() {
+                    Some(group) => group,
+                    None => continue,
+                },
+                None => continue,
+            };
+
+            let group = self.strings.get_or_intern(group);
+            if let Some(cluster) = self.clusters.iter_mut().find(|c| c.name == group) {
+                cluster.insert(node);
+            } else {
+                let mut cluster = SubGraph::new(group);
+                cluster.insert(node);
+                self.clusters.push(cluster);
+            }
+        }
+    }
+
+    /// Collapse each cluster into a single node, rewiring the edges.
+    pub fn aggregate(&mut self) {
+        let mut renames = HashMap::new();
+        for c in self.clusters.drain(..) {
+            let mut properties = NodeProperties::default();
+            for node in c.nodes.keys() {
+                if let Some(p) = self.nodes.remove(node) {
+                    properties.extend(p.symbols);
+                    properties.definitions.extend(p.definitions);
+                    properties.names.extend(p.names);
+                }
+                renames.insert(*node, c.name);
+            }
+            self.nodes.insert(c.name, properties);
+        }
+
+        let edges = std::mem::take(&mut self.edges);
+        for ((n1, n2), p) in edges {
+            let n1 = *renames.get(&n1).unwrap_or(&n1);
+            let n2 = *renames.get(&n2).unwrap_or(&n2);
+
+            // edges internal to a group are not rendered
+            if n1 == n2 {
+                continue;
+            }
+
+            let exporter = self.nodes.get(&n2);
+            self.edges.entry((n1, n2)).or_default().extend(p, exporter);
+        }
+        // only the counts are kept once aggregated
+        for e in self.edges.values_mut() {
+            e.counted = HashSet::new();
+        }
+
+        for libs in self.undefined.values_mut().chain(self.defined.values_mut()).chain(self.imported.values_mut()) {
+            for lib in libs.iter_mut() {
+                *lib = *renames.get(lib).unwrap_or(lib);
+            }
+        }
+        for (importer, exporter, _) in self.candidates.iter_mut() {
+            *importer = *renames.get(importer).unwrap_or(importer);
+            *exporter = *renames.get(exporter).unwrap_or(exporter);
+        }
+    }
+
+    /// The clusters made by [`Graph::group`], by name with their nodes, in
+    /// the grouping order.
+    pub fn clusters(&self) -> impl Iterator<Item = (&str, BTreeSet<u32>)> {
+        self.clusters.iter()
+            .filter_map(move |c| Some((self.strings.resolve(c.name)?, c.nodes.keys().cloned().collect())))
+    }
+
+    /// Find a node by label, path or basename.
+    pub fn find_node(&self, name: &str) -> Option<u32> {
+        self.nodes.keys().cloned().find(|n| {
+            self.label(*n) == Some(name) || match self.strings.resolve(*n) {
+                Some(path) => path == name || basename(path) == name,
+                None => false,
+            }
+        })
+    }
+
+    /// The nodes providing symbols to `node`.
+    pub fn dependencies_of(&self, node: u32) -> Vec<u32> {
+        self.edges.keys().filter(|(n1, _)| *n1 == node).map(|(_, n2)| *n2).collect()
+    }
+
+    /// The nodes using symbols of `node`.
+    pub fn dependents_of(&self, node: u32) -> Vec<u32> {
+        self.edges.keys().filter(|(_, n2)| *n2 == node).map(|(n1, _)| *n1).collect()
+    }
+
+    /// The shortest chain of dependencies from `from` to `to`, both included.
+    pub fn shortest_path(&self, from: u32, to: u32) -> Option<Vec<u32>> {
+        // breadth first, remembering where each node was reached from
+        let mut previous = HashMap::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(from);
+        previous.insert(from, from);
+        while let Some(node) = queue.pop_front() {
+            if node == to {
+                let mut path = vec![to];
+                let mut node = to;
+                while node != from {
+                    node = previous[&node];
+                    path.push(node);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            let mut next = self.dependencies_of(node);
+            next.sort();
+            for n in next {
+                if let Entry::Vacant(entry) = previous.entry(n) {
+                    entry.insert(node);
+                    queue.push_back(n);
+                }
+            }
+        }
+        None
+    }
+
+    /// The shortest chain of dependencies between the nodes named `from` and
+    /// `to`, as found by [`Graph::find_node`].
+    pub fn path_between(&self, from: &str, to: &str) -> Option<Vec<u32>> {
+        self.shortest_path(self.find_node(from)?, self.find_node(to)?)
+    }
+
+    /// The nodes `node` depends on through at most `depth` edges, `node` included.
+    pub fn reachable(&self, node: u32, depth: usize) -> BTreeSet<u32> {
+        let mut reached = BTreeSet::new();
+        reached.insert(node);
+        let mut frontier = vec![node];
+        for _ in 0..depth {
+            frontier = frontier.iter()
+                .flat_map(|n| self.dependencies_of(*n))
+                .filter(|n| reached.insert(*n))
+                .collect();
+        }
+        reached
+    }
+
+    /// The `count` nodes linked to the most other nodes, the ties broken by
+    /// label.
+    pub fn most_connected(&self, count: usize, by: Degree) -> BTreeSet<u32> {
+        let mut degrees: HashMap<u32, usize> = self.nodes.keys().map(|n| (*n, 0)).collect();
+        for (n1, n2) in self.edges.keys().filter(|(n1, n2)| n1 != n2) {
+            if by != Degree::In {
+                *degrees.entry(*n1).or_default() += 1;
+            }
+            if by != Degree::Out {
+                *degrees.entry(*n2).or_default() += 1;
+            }
+        }
+        let mut nodes: Vec<(usize, &str, u32)> = degrees.into_iter()
+            .map(|(n, degree)| (degree, self.label(n).unwrap_or_default(), n))
+            .collect();
+        nodes.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(b.1)));
+        nodes.into_iter().take(count).map(|(_, _, n)| n).collect()
+    }
+
+    /// A copy of the graph restricted to `nodes` and the edges between them.
+    pub fn subgraph(&self, nodes: &BTreeSet<u32>) -> Graph {
+        let keep = |libs: &Vec<u32>| -> Vec<u32> { libs.iter().cloned().filter(|l| nodes.contains(l)).collect() };
+        let restrict = |map: &HashMap<u32, Vec<u32>>| -> HashMap<u32, Vec<u32>> {
+            map.iter()
+                .map(|(s, libs)| (*s, keep(libs)))
+                .filter(|(_, libs)| !libs.is_empty())
+                .collect()
+        };
+
+        Graph {
+            name: self.name.clone(),
+            nodes: self.nodes.iter().filter(|(n, _)| nodes.contains(n)).map(|(n, p)| (*n, p.clone())).collect(),
+            edges: self.edges.iter()
+                .filter(|((n1, n2), _)| nodes.contains(n1) && nodes.contains(n2))
+                .map(|(e, p)| (*e, p.clone()))
+                .collect(),
+            clusters: self.clusters.iter()
+                .map(|c| SubGraph {
+                    name: c.name,
+                    nodes: c.nodes.iter().filter(|(n, _)| nodes.contains(n)).map(|(n, p)| (*n, p.clone())).collect(),
+                })
+                .filter(|c| !c.nodes.is_empty())
+                .collect(),
+            strings: self.strings.clone(),
+            filters: self.filters.clone(),
+            cache: self.cache.clone(),
+            mmap: self.mmap,
+            sources: self.sources.clone(),
+            edge_symbols: self.edge_symbols,
+            resolution: self.resolution,
+            edge_direction: self.edge_direction,
+
+            labels: self.labels.iter().filter(|(n, _)| nodes.contains(n)).map(|(n, l)| (*n, l.clone())).collect(),
+            warnings: Vec::new(),
+            dropped: Vec::new(),
+            stats: Stats::default(),
+            weak_imports: self.weak_imports.iter().filter(|(n, _)| nodes.contains(n)).cloned().collect(),
+
+            undefined: restrict(&self.undefined),
+            defined: restrict(&self.defined),
+            imported: restrict(&self.imported),
+            candidates: self.candidates.iter().filter(|(n1, n2, _)| nodes.contains(n1) && nodes.contains(n2)).cloned().collect(),
+            pe_names: self.pe_names.clone(),
+            loaders: self.loaders.iter().filter(|(p, h)| nodes.contains(p) && nodes.contains(h)).map(|(p, h)| (*p, *h)).collect(),
+        }
+    }
+
+    /// The nodes exporting `symbol`.
+    pub fn providers_of_symbol(&self, symbol: &str) -> Vec<u32> {
+        let symbol = match self.strings.get(symbol) {
+            Some(symbol) => symbol,
+            None => return vec![],
+        };
+        self.nodes.iter().filter(|(_, p)| p.symbols.contains_key(&symbol)).map(|(n, _)| *n).collect()
+    }
+
+    /// The symbols exported by `node` no other node imports, sorted, the
+    /// candidates to hide.
+    pub fn unused_exports(&self, node: u32) -> Vec<&str> {
+        let imported: HashSet<u32> = self.edges.iter()
+            .filter(|((_, n2), _)| *n2 == node)
+            .flat_map(|(_, p)| p.symbols.keys().cloned())
+            .collect();
+        let mut unused: Vec<&str> = match self.nodes.get(&node) {
+            Some(p) => p.symbols.keys().filter(|s| !imported.contains(s)).filter_map(|s| self.strings.resolve(*s)).collect(),
+            None => vec![],
+        };
+        unused.sort();
+        unused
+    }
+
+    /// The libraries `node` imports from without a DT_NEEDED entry of them,
+    /// found only as another library needs them, sorted by label. The
+    /// executables are interposed rather than needed and the plugins import
+    /// from their host, neither is underlinked.
+    pub fn underlinked(&self, node: u32) -> Vec<u32> {
+        if !self.linked_kind(node) {
+            return vec![];
+        }
+        let needed = self.needed(node);
+        let mut libraries: Vec<u32> = self.edges.keys()
+            .filter(|(importer, exporter)| *importer == node && exporter != importer && !self.loaded(node, *exporter))
+            .map(|(_, exporter)| *exporter)
+            .filter(|exporter| !self.interposed(*exporter) && !needed.contains(exporter))
+            .collect();
+        libraries.sort_by_key(|n| (self.label(*n), *n));
+        libraries
+    }
+
+    /// The inputs `node` has a DT_NEEDED entry of but imports nothing from,
+    /// sorted by label.
+    pub fn overlinked(&self, node: u32) -> Vec<u32> {
+        let mut libraries: Vec<u32> = self.needed(node).into_iter()
+            .filter(|exporter| *exporter != node && !self.loaded(node, *exporter))
+            .filter(|exporter| self.edges.get(&(node, *exporter)).is_none_or(|p| p.total == 0))
+            .collect();
+        libraries.sort_by_key(|n| (self.label(*n), *n));
+        libraries
+    }
+
+    // the inputs named by the DT_NEEDED entries of node
+    fn needed(&self, node: u32) -> HashSet<u32> {
+        let sonames = self.sonames();
+        let needed = self.nodes.get(&node).and_then(|p| p.attributes.get("needed"));
+        needed.into_iter().flat_map(|n| n.split(' ')).filter_map(|n| sonames.get(n).cloned()).collect()
+    }
+
+    // whether node is a shared library or an executable, with DT_NEEDED entries
+    fn linked_kind(&self, node: u32) -> bool {
+        let kind = self.nodes.get(&node).and_then(|p| p.attributes.get("kind"));
+        matches!(kind.map(String::as_str), Some("dynamic") | Some("executable"))
+    }
+
+    // whether node is an executable, the position independent ones are
+    // dynamic objects with an interpreter and no soname
+    fn interposed(&self, node: u32) -> bool {
+        let attributes = match self.nodes.get(&node) {
+            Some(properties) => &properties.attributes,
+            None => return false,
+        };
+        attributes.get("kind").is_some_and(|k| k == "executable")
+            || attributes.contains_key("interpreter") && !attributes.contains_key("soname")
+    }
+
+    // whether one of the nodes is a plugin the other loads at runtime
+    fn loaded(&self, n1: u32, n2: u32) -> bool {
+        self.loaders.get(&n1) == Some(&n2) || self.loaders.get(&n2) == Some(&n1)
+    }
+
+    /// The number of resolved and unresolved symbols imported by `node`.
+    pub fn imports_count(&self, node: u32) -> usize {
+        let resolved: usize = self.edges.iter()
+            .filter(|((n1, _), _)| *n1 == node)
+            .map(|(_, p)| p.total)
+            .sum();
+        resolved + self.unresolved_count(node)
+    }
+
+    /// The symbols imported by `node` no input exports.
+    pub fn unresolved_count(&self, node: u32) -> usize {
+        self.undefined.values().filter(|libs| libs.contains(&node)).count()
+    }
+
+    /// The imported symbols no input exports, with the labels of their importers.
+    pub fn unresolved(&self) -> Vec<(&str, Vec<&str>)> {
+        self.undefined.iter()
+            .filter_map(|(symbol, libs)| {
+                let symbol = self.strings.resolve(*symbol)?;
+                let mut libs: Vec<&str> = libs.iter().filter_map(|l| self.label(*l)).collect();
+                libs.sort();
+                libs.dedup();
+                Some((symbol, libs))
+            })
+            .collect()
+    }
+
+    /// The dependency cycles: the strongly connected components of more than
+    /// one node, using Tarjan's algorithm.
+    pub fn cycles(&self) -> Vec<Vec<u32>> {
+        struct Tarjan<'a> {
+            successors: HashMap<u32, Vec<u32>>,
+            index: HashMap<u32, usize>,
+            lowlink: HashMap<u32, usize>,
+            stack: Vec<u32>,
+            on_stack: HashSet<u32>,
+            components: Vec<Vec<u32>>,
+            graph: &'a Graph,
+        }
+
+        impl<'a> Tarjan<'a> {
+            fn visit(&mut self, node: u32) {
+                let index = self.index.len();
+                self.index.insert(node, index);
+                self.lowlink.insert(node, index);
+                self.stack.push(node);
+                self.on_stack.insert(node);
+
+                let successors = self.successors.get(&node).cloned().unwrap_or_default();
+                for next in successors {
+                    if !self.index.contains_key(&next) {
+                        self.visit(next);
+                        let low = self.lowlink[&node].min(self.lowlink[&next]);
+                        self.lowlink.insert(node, low);
+                    } else if self.on_stack.contains(&next) {
+                        let low = self.lowlink[&node].min(self.index[&next]);
+                        self.lowlink.insert(node, low);
+                    }
+                }
+
+                if self.lowlink[&node] == self.index[&node] {
+                    let mut component = vec![];
+                    while let Some(n) = self.stack.pop() {
+                        self.on_stack.remove(&n);
+                        component.push(n);
+                        if n == node {
+                            break;
+                        }
+                    }
+                    if component.len() > 1 {
+                        component.sort_by_key(|n| self.graph.label(*n));
+                        self.components.push(component);
+                    }
+                }
+            }
+        }
+
+        let mut successors: HashMap<u32, Vec<u32>> = HashMap::new();
+        for (n1, n2) in self.edges.keys() {
+            successors.entry(*n1).or_default().push(*n2);
+        }
+
+        let mut tarjan = Tarjan {
+            successors,
+            index: HashMap::new(),
+            lowlink: HashMap::new(),
+            stack: vec![],
+            on_stack: HashSet::new(),
+            components: vec![],
+            graph: self,
+        };
+
+        let mut nodes: Vec<u32> = self.nodes.keys().cloned().collect();
+        nodes.sort();
+        for node in nodes {
+            if !tarjan.index.contains_key(&node) {
+                tarjan.visit(node);
+            }
+        }
+        tarjan.components
+    }
+
+    /// The connected components, ignoring the direction of the edges, each
+    /// sorted by label.
+    pub fn components(&self) -> Vec<Vec<u32>> {
+        let mut neighbours: HashMap<u32, Vec<u32>> = HashMap::new();
+        for (n1, n2) in self.edges.keys() {
+            neighbours.entry(*n1).or_default().push(*n2);
+            neighbours.entry(*n2).or_default().push(*n1);
+        }
+
+        let mut nodes: Vec<u32> = self.nodes.keys().cloned().collect();
+        nodes.sort();
+        let mut seen = HashSet::new();
+        let mut components = vec![];
+        for node in nodes {
+            if !seen.insert(node) {
+                continue;
+            }
+            let mut component = vec![];
+            let mut stack = vec![node];
+            while let Some(n) = stack.pop() {
+                component.push(n);
+                for next in neighbours.get(&n).into_iter().flatten() {
+                    if seen.insert(*next) {
+                        stack.push(*next);
+                    }
+                }
+            }
+            component.sort_by_key(|n| self.label(*n));
+            components.push(component);
+        }
+        components
+    }
+
+    /// The labels of all nodes.
+    pub fn node_labels(&self) -> BTreeSet<String> {
+        self.nodes.keys().filter_map(|n| self.label(*n)).map(String::from).collect()
+    }
+
+    /// The `(importer, exporter)` labels of all edges.
+    pub fn edge_labels(&self) -> BTreeSet<(String, String)> {
+        self.edges.keys()
+            .filter_map(|(n1, n2)| Some((self.label(*n1)?.to_string(), self.label(*n2)?.to_string())))
+            .collect()
+    }
+}
+
+// the hash of the label unless already used, else of the path, on 53 bits to
+// be exact in the JSON numbers
+pub(crate) fn node_id(labels: &mut HashSet<String>, label: &str, path: &str) -> u64 {
+    let name = if labels.insert(label.to_string()) { label } else { path };
+    let digest = Sha256::digest(name.as_bytes());
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    u64::from_be_bytes(bytes) >> 11
+}
+
+/// Where the time went while building a graph, the per file times are
+/// summed over the parsing threads.
+#[derive(Debug, Clone, Default)]
+pub struct Stats {
+    /// Opening and mapping the files.
+    pub open: Duration,
+    /// Reading the symbols of the files.
+    pub parse: Duration,
+    /// Interning and resolving the symbols.
+    pub resolve: Duration,
+    /// The open and parse time of each parsed file by node, use
+    /// [`Graph::path`] to get the file back.
+    pub files: Vec<(u32, Duration)>,
+}
+
+/// A library of the graph.
+#[derive(Debug, Clone, Default)]
+pub struct NodeProperties {
+    // symbol -> times exported
+    pub(crate) symbols: BTreeMap<u32, usize>,
+    // symbol -> where it is defined, when the symbol tables tell
+    pub(crate) definitions: BTreeMap<u32, Definition>,
+    // symbol -> its symbol table name, for the exports mangled into another
+    pub(crate) names: BTreeMap<u32, u32>,
+    pub(crate) attributes: BTreeMap<String, String>,
+}
+
+impl NodeProperties {
+    /// The exported symbols, each once.
+    pub fn symbols(&self) -> btree_map::Keys<'_, u32, usize> {
+        self.symbols.keys()
+    }
+
+    /// How many times `symbol` is exported, eg. once per version.
+    pub fn count(&self, symbol: u32) -> usize {
+        self.symbols.get(&symbol).cloned().unwrap_or(0)
+    }
+
+    /// The size in bytes of an exported symbol, 0 when unknown.
+    pub fn size(&self, symbol: u32) -> u64 {
+        self.definitions.get(&symbol).map_or(0, |d| d.size)
+    }
+
+    /// Whether an exported symbol is a function or data, `Unknown` when the
+    /// symbol tables do not tell.
+    pub fn kind(&self, symbol: u32) -> SymbolKind {
+        self.definitions.get(&symbol).map_or(SymbolKind::Unknown, |d| d.kind)
+    }
+
+    /// Where an exported symbol is defined, when the symbol tables tell.
+    pub fn definition(&self, symbol: u32) -> Option<&Definition> {
+        self.definitions.get(&symbol)
+    }
+
+    fn insert(&mut self, symbol: u32) {
+        *self.symbols.entry(symbol).or_insert(0) += 1;
+    }
+
+    fn extend(&mut self, symbols: BTreeMap<u32, usize>) {
+        for (symbol, count) in symbols {
+            *self.symbols.entry(symbol).or_insert(0) += count;
+        }
+    }
+
+    /// The metadata attached to the node, by name.
+    pub fn attributes(&self) -> &BTreeMap<String, String> {
+        &self.attributes
+    }
+}
+
+/// The definition of an exported symbol.
+#[derive(Debug, Clone)]
+pub struct Definition {
+    pub kind: SymbolKind,
+    /// The size in bytes, 0 when unknown.
+    pub size: u64,
+    /// The interned name of the defining section, eg. `.text` for the
+    /// functions or `.bss` for the uninitialized globals, use
+    /// [`Graph::symbol`] to get it back.
+    pub section: Option<u32>,
+    pub address: u64,
+    /// The interned ELF version, eg. `LIBFOO_1.0`, None when unversioned.
+    pub version: Option<u32>,
+}
+
+/// The dependency of a library on another.
+#[derive(Debug, Clone, Default)]
+pub struct EdgeProperties {
+    // symbol -> times imported
+    pub(crate) symbols: BTreeMap<u32, usize>,
+    // the ids of the symbols counted but not kept, so that each one is counted
+    // once until the edges are aggregated, dropped then
+    pub(crate) counted: HashSet<u32>,
+    // distinct symbols, counted even when they are not kept
+    pub(crate) total: usize,
+    // their size at the exporter
+    pub(crate) bytes: u64,
+    // the kept symbols only imported through weak references
+    pub(crate) weak: BTreeSet<u32>,
+    // at least one symbol imported through a strong reference
+    pub(crate) strong: bool,
+}
+
+impl EdgeProperties {
+    /// The imported symbols, each once, empty once merged.
+    pub fn symbols(&self) -> btree_map::Keys<'_, u32, usize> {
+        self.symbols.keys()
+    }
+
+    /// How many times `symbol` is imported through this edge.
+    pub fn count(&self, symbol: u32) -> usize {
+        self.symbols.get(&symbol).cloned().unwrap_or(0)
+    }
+
+    /// The number of imported symbols, known even when
+    /// [`Graph::edge_symbols`] is off.
+    pub fn symbols_count(&self) -> usize {
+        self.total
+    }
+
+    /// The size in bytes of the imported code and data, for the symbols of
+    /// known size.
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    /// Whether every symbol is imported through a weak reference, the
+    /// importer runs without the exporter.
+    pub fn is_weak(&self) -> bool {
+        !self.strong
+    }
+
+    /// Whether `symbol` is only imported through weak references.
+    pub fn is_weak_symbol(&self, symbol: u32) -> bool {
+        self.weak.contains(&symbol)
+    }
+
+    fn insert(&mut self, symbol: u32, keep: bool, size: u64, weak: bool) {
+        self.strong |= !weak;
+        if keep {
+            let count = self.symbols.entry(symbol).or_insert(0);
+            *count += 1;
+            if *count > 1 {
+                if !weak {
+                    self.weak.remove(&symbol);
+                }
+                return;
+            }
+            if weak {
+                self.weak.insert(symbol);
+            }
+        } else if !self.counted.insert(symbol) {
+            return;
+        }
+        self.total += 1;
+        self.bytes += size;
+    }
+
+    fn extend(&mut self, other: EdgeProperties, exporter: Option<&NodeProperties>) {
+        self.strong |= other.strong;
+        for symbol in other.counted {
+            if self.counted.insert(symbol) {
+                self.total += 1;
+                self.bytes += exporter.map_or(0, |p| p.size(symbol));
+            }
+        }
+        for (symbol, count) in other.symbols {
+            let current = self.symbols.entry(symbol).or_insert(0);
+            let weak = other.weak.contains(&symbol);
+            if *current == 0 {
+                self.total += 1;
+                self.bytes += exporter.map_or(0, |p| p.size(symbol));
+                if weak {
+                    self.weak.insert(symbol);
+                }
+            } else if !weak {
+                self.weak.remove(&symbol);
+            }
+            *current += count;
+        }
+    }
+}
+
+
+/// A node as iterated by [`Graph::nodes`], its symbols by name; it derefs to
+/// its [`NodeProperties`].
+#[derive(Clone, Copy)]
+pub struct Node<'a> {
+    graph: &'a Graph,
+    node: u32,
+    properties: &'a NodeProperties,
+}
+
+impl<'a> Node<'a> {
+    /// The interned path identifying the node.
+    pub fn id(&self) -> u32 {
+        self.node
+    }
+
+    /// The file the node was parsed from, see [`Graph::path`].
+    pub fn path(&self) -> &'a str {
+        self.graph.path(self.node).unwrap_or_default()
+    }
+
+    /// The rendered name of the node.
+    pub fn label(&self) -> &'a str {
+        self.graph.label(self.node).unwrap_or_default()
+    }
+
+    /// The exported symbols, each once, borrowed from the graph.
+    pub fn symbols(&self) -> btree_map::Keys<'a, u32, usize> {
+        self.properties.symbols.keys()
+    }
+
+    /// The names of the exported symbols, each once.
+    pub fn exports(&self) -> impl Iterator<Item = &'a str> + 'a {
+        let graph = self.graph;
+        self.symbols().filter_map(move |s| graph.symbol(*s))
+    }
+
+    /// The name of an exported symbol in the symbol tables, eg. `foo.cold`
+    /// for `foo_cold` or the case of a PE export, as version scripts and
+    /// .def files name it.
+    pub fn raw_name(&self, symbol: u32) -> Option<&'a str> {
+        match self.properties.names.get(&symbol) {
+            Some(raw) => self.graph.symbol(*raw),
+            None => self.graph.symbol(symbol),
+        }
+    }
+
+    /// The symbol table names of the exported symbols, each once.
+    pub fn raw_exports(&self) -> impl Iterator<Item = &'a str> + 'a {
+        let node = *self;
+        self.symbols().filter_map(move |s| node.raw_name(*s))
+    }
+}
+
+impl<'a> Deref for Node<'a> {
+    type Target = NodeProperties;
+
+    fn deref(&self) -> &NodeProperties {
+        self.properties
+    }
+}
+
+/// An edge as iterated by [`Graph::edges`], its ends and symbols by name; it
+/// derefs to its [`EdgeProperties`].
+#[derive(Clone, Copy)]
+pub struct Edge<'a> {
+    graph: &'a Graph,
+    edge: (u32, u32),
+    properties: &'a EdgeProperties,
+}
+
+impl<'a> Edge<'a> {
+    /// The `(importer, exporter)` nodes, as keyed by [`Graph::edges`].
+    pub fn id(&self) -> (u32, u32) {
+        self.edge
+    }
+
+    /// The path of the importing node.
+    pub fn importer(&self) -> &'a str {
+        self.graph.path(self.edge.0).unwrap_or_default()
+    }
+
+    /// The path of the exporting node.
+    pub fn exporter(&self) -> &'a str {
+        self.graph.path(self.edge.1).unwrap_or_default()
+    }
+
+    /// The imported symbols, each once, borrowed from the graph.
+    pub fn symbols(&self) -> btree_map::Keys<'a, u32, usize> {
+        self.properties.symbols.keys()
+    }
+
+    /// The names of the imported symbols, each once, none once merged.
+    pub fn imports(&self) -> impl Iterator<Item = &'a str> + 'a {
+        let graph = self.graph;
+        self.symbols().filter_map(move |s| graph.symbol(*s))
+    }
+}
+
+impl<'a> Deref for Edge<'a> {
+    type Target = EdgeProperties;
+
+    fn deref(&self) -> &EdgeProperties {
+        self.properties
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct SubGraph {
+    pub(crate) name: u32,
+    pub(crate) nodes: HashMap<u32, NodeProperties>
+}
+
+impl SubGraph {
+    fn new(name: u32) -> Self {
+        Self {
+            name,
+            nodes: HashMap::new()
+        }
+    }
+    
+    fn insert(&mut self, symbol_name: u32) {
+        self.nodes.insert(symbol_name, NodeProperties::default());
+    }
+}
+
+// the interned path, exports and imports of a parsed file
+type InternedFile = (u32, Vec<u32>, Vec<u32>);
+
+// the resolution state of a shard of the symbols
+#[derive(Default)]
+struct Resolver {
+    // resolve to every provider rather than to the first one
+    all: bool,
+    // temporary map defined symbol -> libs
+    defined: HashMap<u32, Vec<u32>>,
+    // temporary map undefined symbol -> lib
+    undefined: HashMap<u32, Vec<u32>>,
+    // resolved symbol -> importers, kept only to resolve to every provider
+    imported: HashMap<u32, Vec<u32>>,
+    // the resolved (importer, exporter, symbol)
+    edges: Vec<(u32, u32, u32)>,
+}
+
+impl Resolver {
+    // resolve, in order, the symbols of the files belonging to the shard
+    fn insert_all(&mut self, files: &[InternedFile], exported: &SymbolSet, shard: usize, shards: usize) {
+        let owned = |symbol: &&u32| **symbol as usize % shards == shard;
+        for (filename, exports, imports) in files {
+            for symbol in exports.iter().filter(owned) {
+                self.insert_exported(*filename, *symbol);
+            }
+            for symbol in imports.iter().filter(owned) {
+                if exported.contains(*symbol) {
+                    self.insert_imported(*filename, *symbol);
+                } else {
+                    self.undefined.entry(*symbol).or_default().push(*filename);
+                }
+            }
+        }
+    }
+
+    fn insert_exported(&mut self, filename: u32, symbol_name: u32) {
+        // store for later resolution
+        self.defined.entry(symbol_name).or_default().push(filename);
+
+        // cleanup undefined if needed
+        if let Some((_, libs)) = self.undefined.remove_entry(&symbol_name) {
+            for lib in libs.iter() {
+                self.edges.push((*lib, filename, symbol_name));
+            }
+            if self.all {
+                self.imported.entry(symbol_name).or_default().extend(libs);
+            }
+        } else if let Some(libs) = self.imported.get(&symbol_name) {
+            // another provider of resolved imports
+            for lib in libs.iter() {
+                self.edges.push((*lib, filename, symbol_name));
+            }
+        }
+    }
+
+    fn insert_imported(&mut self, filename: u32, symbol_name: u32) {
+        // lookup on existing libs
+        if let Some(libs) = self.defined.get(&symbol_name) {
+            // resolve to previously decoded libs 
+            if self.all {
+                for lib in libs.iter() {
+                    self.edges.push((filename, *lib, symbol_name));
+                }
+                self.imported.entry(symbol_name).or_default().push(filename);
+            } else if let Some(lib) = libs.first() {
+                self.edges.push((filename, *lib, symbol_name));
+            }
+        } else {
+            // will be resolved later, store it
+            if let Some(libs) = self.undefined.get_mut(&symbol_name) {
+                libs.push(filename);
+            } else {
+                self.undefined.insert(symbol_name, vec![filename]);
+            }
+        }
+    }
+}
+
+// the interned ids are dense, one bit per id
+#[derive(Default)]
+struct SymbolSet {
+    bits: Vec<u64>,
+}
+
+impl SymbolSet {
+    fn insert(&mut self, symbol: u32) {
+        let word = symbol as usize / 64;
+        if word >= self.bits.len() {
+            self.bits.resize(word + 1, 0);
+        }
+        self.bits[word] |= 1 << (symbol % 64);
+    }
+
+    fn contains(&self, symbol: u32) -> bool {
+        self.bits.get(symbol as usize / 64).is_some_and(|w| w & (1 << (symbol % 64)) != 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use builder::GraphBuilder;
+    use dot::DotWriter;
+    use filters::GroupRule;
+    use graph::Graph;
+
+    // app -> libfoo -> libbar, libbaz exporting bar too
+    fn graph() -> Graph {
+        let mut builder = GraphBuilder::new("test");
+        builder.add_symbols("bin/app", Vec::<String>::new(), ["foo", "unknown"])
+            .add_symbols("lib/libfoo.so", ["foo"], ["bar"])
+            .add_symbols("lib/libbar.so", ["bar"], Vec::<String>::new())
+            .add_symbols("lib/libbaz.so", ["bar", "baz"], Vec::<String>::new());
+        builder.finish()
+    }
+
+    fn labels(graph: &Graph, nodes: &[u32]) -> Vec<String> {
+        let mut labels: Vec<String> = nodes.iter().filter_map(|n| graph.label(*n)).map(String::from).collect();
+        labels.sort();
+        labels
+    }
+
+    #[test]
+    fn write_is_stable() {
+        // each graph hashes its maps with other keys
+        let render = || {
+            let mut graph = graph();
+            graph.group(&[GroupRule::parse("s/^lib(foo|bar)_so$/libs/").unwrap()]);
+            DotWriter::new(&graph).to_string()
+        };
+        let first = render();
+        for _ in 0..8 {
+            assert_eq!(render(), first);
+        }
+    }
+
+    #[test]
+    fn dependencies_of() {
+        let graph = graph();
+        let node = |name| graph.find_node(name).unwrap();
+        assert_eq!(labels(&graph, &graph.dependencies_of(node("app"))), ["libfoo_so"]);
+        assert_eq!(labels(&graph, &graph.dependencies_of(node("libfoo.so"))), ["libbar_so"]);
+        assert!(graph.dependencies_of(node("libbar.so")).is_empty());
+    }
+
+    #[test]
+    fn dependents_of() {
+        let graph = graph();
+        let node = |name| graph.find_node(name).unwrap();
+        assert_eq!(labels(&graph, &graph.dependents_of(node("libfoo.so"))), ["app"]);
+        assert_eq!(labels(&graph, &graph.dependents_of(node("libbar.so"))), ["libfoo_so"]);
+        // the first provider is the one depended on
+        assert!(graph.dependents_of(node("libbaz.so")).is_empty());
+        assert!(graph.dependents_of(node("app")).is_empty());
+    }
+
+    #[test]
+    fn providers_of_symbol() {
+        let graph = graph();
+        assert_eq!(labels(&graph, &graph.providers_of_symbol("bar")), ["libbar_so", "libbaz_so"]);
+        assert_eq!(labels(&graph, &graph.providers_of_symbol("baz")), ["libbaz_so"]);
+        // imported but never exported
+        assert!(graph.providers_of_symbol("unknown").is_empty());
+        assert!(graph.providers_of_symbol("missing").is_empty());
+    }
+
+    #[test]
+    fn path_between() {
+        let graph = graph();
+        let path = |from, to| {
+            graph.path_between(from, to).map(|p| p.iter().filter_map(|n| graph.label(*n)).collect::<Vec<_>>())
+        };
+        assert_eq!(path("app", "libbar.so"), Some(vec!["app", "libfoo_so", "libbar_so"]));
+        assert_eq!(path("lib/libfoo.so", "libbar_so"), Some(vec!["libfoo_so", "libbar_so"]));
+        assert_eq!(path("app", "app"), Some(vec!["app"]));
+        // the edges are followed from the importers only
+        assert_eq!(path("libbar.so", "app"), None);
+        assert_eq!(path("app", "libbaz.so"), None);
+        assert_eq!(path("app", "libmissing.so"), None);
+    }
+}
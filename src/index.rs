@@ -0,0 +1,100 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::io::{Read, Write};
+
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+
+use cache::{hex, unhex};
+use error::{Error, Result};
+use parse::ParsedFile;
+
+// bumped when the layout changes, the older indexes are then rejected
+const HEADER: &str = "symbols-graph providers index 1";
+
+// the libraries of a sysroot and the symbols each of them exports
+pub(crate) struct Index {
+    // path, label and attributes, in the scanning order
+    pub(crate) libraries: Vec<(String, String, BTreeMap<String, String>)>,
+    // symbol -> the libraries exporting it and its size there
+    pub(crate) providers: HashMap<String, Vec<(usize, u64)>>,
+}
+
+// the layout is a header, the libraries then one record per symbol:
+//
+//   library <hex path> <hex label>, attribute <hex name> <hex value>,
+//   symbol <hex name> <library>:<size>...
+//
+// the libraries are numbered from 0 in their order, the symbols are sorted
+pub(crate) fn write<W: Write>(files: &[ParsedFile], writer: W) -> io::Result<()> {
+    let mut writer = GzEncoder::new(writer, Compression::default());
+    writeln!(writer, "{}", HEADER)?;
+    let mut providers: BTreeMap<&str, Vec<(usize, u64)>> = BTreeMap::new();
+    for (number, file) in files.iter().enumerate() {
+        writeln!(writer, "library {} {}", hex(file.path.as_bytes()), hex(file.label.as_bytes()))?;
+        for (name, value) in &file.attributes {
+            writeln!(writer, "attribute {} {}", hex(name.as_bytes()), hex(value.as_bytes()))?;
+        }
+        let mut seen = HashSet::new();
+        for (symbol, info) in file.exports.iter().zip(&file.export_infos).filter(|(s, _)| seen.insert(s.as_str())) {
+            providers.entry(symbol).or_default().push((number, info.as_ref().map_or(0, |i| i.size)));
+        }
+    }
+    for (symbol, libraries) in providers {
+        write!(writer, "symbol {}", hex(symbol.as_bytes()))?;
+        for (number, size) in libraries {
+            write!(writer, " {}:{}", number, size)?;
+        }
+        writeln!(writer)?;
+    }
+    writer.finish()?.flush()
+}
+
+pub(crate) fn read(path: &str) -> Result<Index> {
+    let mut content = String::new();
+    let read = fs::File::open(path).and_then(|file| GzDecoder::new(file).read_to_string(&mut content));
+    if let Err(error) = read {
+        return Err(Error::Io { path: path.to_string(), error });
+    }
+
+    let mut lines = content.lines();
+    if lines.next() != Some(HEADER) {
+        return Err(Error::Index { path: path.to_string(), line: 1 });
+    }
+    let mut index = Index { libraries: vec![], providers: HashMap::new() };
+    for (number, line) in lines.enumerate() {
+        let malformed = || Error::Index { path: path.to_string(), line: number + 2 };
+        let fields: Vec<&str> = line.split(' ').collect();
+        match fields.as_slice() {
+            ["library", library, label] => {
+                let (library, label) = (unhex_string(library).ok_or_else(malformed)?, unhex_string(label).ok_or_else(malformed)?);
+                index.libraries.push((library, label, BTreeMap::new()));
+            }
+            ["attribute", name, value] => {
+                let (name, value) = (unhex_string(name).ok_or_else(malformed)?, unhex_string(value).ok_or_else(malformed)?);
+                index.libraries.last_mut().ok_or_else(malformed)?.2.insert(name, value);
+            }
+            ["symbol", symbol, libraries @ ..] => {
+                let mut providers = Vec::with_capacity(libraries.len());
+                for library in libraries {
+                    let (library, size) = library.split_once(':').ok_or_else(malformed)?;
+                    let library: usize = library.parse().map_err(|_| malformed())?;
+                    if library >= index.libraries.len() {
+                        return Err(malformed());
+                    }
+                    providers.push((library, size.parse().map_err(|_| malformed())?));
+                }
+                index.providers.insert(unhex_string(symbol).ok_or_else(malformed)?, providers);
+            }
+            _ => return Err(malformed()),
+        }
+    }
+    Ok(index)
+}
+
+// the names are valid UTF-8 once parsed
+fn unhex_string(hex: &str) -> Option<String> {
+    String::from_utf8(unhex(hex)?).ok()
+}
@@ -0,0 +1,177 @@
+use std::fmt;
+use std::fmt::Display;
+
+use graph::Graph;
+
+/// The version of the JSON graph written by [`JsonWriter`], in its
+/// `schema_version` field.
+///
+/// The fields are only added, a field is never removed, renamed nor changes
+/// meaning without bumping the version, the readers ignore the fields they do
+/// not know.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The JSON schema of the graph written by [`JsonWriter`].
+pub const SCHEMA: &str = include_str!("../schema/graph.schema.json");
+
+/// Renders a [`Graph`] as a JSON document, for the tools not reading DOT.
+///
+/// The nodes and the edges are sorted by id, the ids of
+/// [`Graph::node_ids`]. The nodes list their exports with
+/// the defining section and address, the edges list each symbol once with the
+/// number of times it is imported and its size, 0 when unknown, and are weak
+/// when made only of weak references, as described by [`SCHEMA`]. The edges point
+/// the way of [`Graph::edge_direction`]:
+///
+/// ```json
+/// {"schema_version": 1, "name": "deps", "edge_direction": "uses",
+///  "nodes": [{"id": 0, "label": "libfoo", "path": "/lib/libfoo.so", "cluster": null, "attributes": {},
+///             "exports": [{"name": "bar_init", "section": ".text", "address": 4352, "size": 42}]}],
+///  "edges": [{"from": 0, "to": 1, "symbols": ["bar_init"], "counts": [1], "sizes": [42], "bytes": 42, "weak": false}]}
+/// ```
+pub struct JsonWriter<'a> {
+    graph: &'a Graph,
+    metadata: &'a [(String, String)],
+}
+
+impl<'a> JsonWriter<'a> {
+    pub fn new(graph: &'a Graph) -> Self {
+        Self { graph, metadata: &[] }
+    }
+
+    /// Add a `metadata` object with these entries, eg. the version and the
+    /// options that made the graph.
+    pub fn metadata(mut self, metadata: &'a [(String, String)]) -> Self {
+        self.metadata = metadata;
+        self
+    }
+}
+
+impl<'a> Display for JsonWriter<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let graph = self.graph;
+        writeln!(f, "{{")?;
+        writeln!(f, "  \"schema_version\": {},", SCHEMA_VERSION)?;
+        writeln!(f, "  \"name\": {},", quote(&graph.name))?;
+        writeln!(f, "  \"edge_direction\": {},", quote(graph.edge_direction.name()))?;
+        if !self.metadata.is_empty() {
+            let entries: Vec<String> = self.metadata.iter().map(|(name, value)| format!("{}: {}", quote(name), quote(value))).collect();
+            writeln!(f, "  \"metadata\": {{{}}},", entries.join(", "))?;
+        }
+
+        let ids = graph.node_ids();
+        let mut nodes: Vec<u32> = graph.nodes.keys().cloned().collect();
+        nodes.sort_by_key(|n| ids[n]);
+        writeln!(f, "  \"nodes\": [")?;
+        for (i, node) in nodes.iter().enumerate() {
+            let cluster = graph.clusters.iter()
+                .find(|c| c.nodes.contains_key(node))
+                .and_then(|c| graph.strings.resolve(c.name));
+            let attributes: Vec<String> = graph.nodes[node].attributes.iter()
+                .map(|(name, value)| format!("{}: {}", quote(name), quote(value)))
+                .collect();
+            let properties = &graph.nodes[node];
+            let exports: Vec<String> = properties.symbols()
+                .filter_map(|s| {
+                    let definition = properties.definition(*s);
+                    Some(format!("{{\"name\": {}, \"section\": {}, \"address\": {}, \"size\": {}}}",
+                                 quote(graph.strings.resolve(*s)?),
+                                 optional(definition.and_then(|d| d.section).and_then(|s| graph.strings.resolve(s))),
+                                 definition.map_or(0, |d| d.address),
+                                 definition.map_or(0, |d| d.size)))
+                })
+                .collect();
+            write!(f, "    {{\"id\": {}, \"label\": {}, \"path\": {}, \"cluster\": {}, \"attributes\": {{{}}}, \"exports\": [{}]}}",
+                   ids[node],
+                   optional(graph.label(*node)),
+                   optional(graph.strings.resolve(*node)),
+                   optional(cluster),
+                   attributes.join(", "),
+                   exports.join(", "))?;
+            writeln!(f, "{}", if i + 1 < nodes.len() { "," } else { "" })?;
+        }
+        writeln!(f, "  ],")?;
+
+        let mut edges: Vec<&(u32, u32)> = graph.edges.keys().collect();
+        edges.sort_by_key(|(n1, n2)| graph.edge_direction.orient(ids[n1], ids[n2]));
+        writeln!(f, "  \"edges\": [")?;
+        for (i, edge) in edges.iter().enumerate() {
+            let properties = &graph.edges[edge];
+            let exporter = graph.nodes.get(&edge.1);
+            let symbols: Vec<(&str, usize, u64)> = graph.sorted_symbols(properties).into_iter()
+                .map(|(name, s)| (name, properties.count(s), exporter.map_or(0, |e| e.size(s))))
+                .collect();
+            let names: Vec<String> = symbols.iter().map(|(s, _, _)| quote(s)).collect();
+            let counts: Vec<String> = symbols.iter().map(|(_, c, _)| c.to_string()).collect();
+            let sizes: Vec<String> = symbols.iter().map(|(_, _, size)| size.to_string()).collect();
+            let (from, to) = graph.edge_direction.orient(ids[&edge.0], ids[&edge.1]);
+            write!(f, "    {{\"from\": {}, \"to\": {}, \"symbols\": [{}], \"counts\": [{}], \"sizes\": [{}], \"bytes\": {}, \"weak\": {}}}",
+                   from, to, names.join(", "), counts.join(", "), sizes.join(", "), properties.bytes(), properties.is_weak())?;
+            writeln!(f, "{}", if i + 1 < edges.len() { "," } else { "" })?;
+        }
+        writeln!(f, "  ]")?;
+
+        writeln!(f, "}}")
+    }
+}
+
+/// Renders the metadata of the nodes of a [`Graph`] keyed by node id, the
+/// sidecar of a DOT graph for the tools enriching it:
+///
+/// ```json
+/// {"0": {"label": "libfoo", "path": "/lib/libfoo.so", "attributes": {"arch": "x86_64", "soname": "libfoo.so.1"}}}
+/// ```
+pub struct NodesMetaWriter<'a> {
+    graph: &'a Graph,
+}
+
+impl<'a> NodesMetaWriter<'a> {
+    pub fn new(graph: &'a Graph) -> Self {
+        Self { graph }
+    }
+}
+
+impl<'a> Display for NodesMetaWriter<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let graph = self.graph;
+        let ids = graph.node_ids();
+        let mut nodes: Vec<u32> = graph.nodes.keys().cloned().collect();
+        nodes.sort_by_key(|n| ids[n]);
+        writeln!(f, "{{")?;
+        for (i, node) in nodes.iter().enumerate() {
+            let attributes: Vec<String> = graph.nodes[node].attributes.iter()
+                .map(|(name, value)| format!("{}: {}", quote(name), quote(value)))
+                .collect();
+            write!(f, "  \"{}\": {{\"label\": {}, \"path\": {}, \"attributes\": {{{}}}}}",
+                   ids[node],
+                   optional(graph.label(*node)),
+                   optional(graph.strings.resolve(*node)),
+                   attributes.join(", "))?;
+            writeln!(f, "{}", if i + 1 < nodes.len() { "," } else { "" })?;
+        }
+        writeln!(f, "}}")
+    }
+}
+
+fn optional(value: Option<&str>) -> String {
+    match value {
+        Some(value) => quote(value),
+        None => String::from("null"),
+    }
+}
+
+// a quoted and escaped JSON string
+pub(crate) fn quote(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => quoted += "\\\"",
+            '\\' => quoted += "\\\\",
+            '\n' => quoted += "\\n",
+            c if (c as u32) < 0x20 => quoted += &format!("\\u{:04x}", c as u32),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
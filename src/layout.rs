@@ -0,0 +1,381 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+// the spacing of the drawings, in points as the Graphviz defaults
+const NODE_SEPARATION: f64 = 18.0;
+const RANK_SEPARATION: f64 = 36.0;
+const MARGIN: f64 = 8.0;
+// the width kept for an edge crossing a rank
+const DUMMY_WIDTH: f64 = 10.0;
+// the length of the arrowheads
+pub(crate) const ARROW: f64 = 10.0;
+
+// a drawing of the nodes and edges, in points with the origin at the bottom
+// left as in xdot
+pub(crate) struct Layout {
+    // node -> center and size
+    pub(crate) nodes: HashMap<String, Rectangle>,
+    // (from, to) -> the cubic B-spline control points, 3n+1 of them, and the
+    // tip of the arrowhead
+    pub(crate) edges: HashMap<(String, String), (Vec<Point>, Point)>,
+    pub(crate) width: f64,
+    pub(crate) height: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Point {
+    pub(crate) x: f64,
+    pub(crate) y: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Rectangle {
+    pub(crate) center: Point,
+    pub(crate) width: f64,
+    pub(crate) height: f64,
+}
+
+// a node of the ranks, the real ones first then one per rank crossed by a
+// long edge
+struct Vertex {
+    width: f64,
+    height: f64,
+    group: Option<usize>,
+}
+
+// lay the nodes, given with their size, in ranks from the top down along the
+// edges: the cycles are broken by reversing the edges closing them, the
+// nodes are ranked by their longest path from a source, ordered in their
+// rank by the barycenter of their neighbors to cross fewer edges, the groups
+// kept together, then pulled toward their neighbors
+//
+// the same input always gives the same drawing
+pub(crate) fn layout(nodes: &[(String, f64, f64)], edges: &[(String, String)], groups: &[Vec<String>]) -> Layout {
+    let index: HashMap<&str, usize> = nodes.iter().enumerate().map(|(i, (n, _, _))| (n.as_str(), i)).collect();
+    let mut group_of: HashMap<usize, usize> = HashMap::new();
+    for (group, members) in groups.iter().enumerate() {
+        for member in members.iter().filter_map(|m| index.get(m.as_str())) {
+            group_of.insert(*member, group);
+        }
+    }
+    let mut vertices: Vec<Vertex> = nodes.iter().enumerate()
+        .map(|(i, (_, width, height))| Vertex { width: *width, height: *height, group: group_of.get(&i).cloned() })
+        .collect();
+
+    // each pair once, without the loops
+    let mut links: Vec<(usize, usize)> = edges.iter()
+        .filter_map(|(from, to)| Some((*index.get(from.as_str())?, *index.get(to.as_str())?)))
+        .filter(|(from, to)| from != to)
+        .collect();
+    links.sort();
+    links.dedup();
+
+    let reversed = back_edges(nodes.len(), &links);
+    let acyclic: Vec<(usize, usize)> = links.iter()
+        .map(|(from, to)| if reversed.contains(&(*from, *to)) { (*to, *from) } else { (*from, *to) })
+        .collect();
+    let mut rank = ranks(nodes.len(), &acyclic);
+
+    // the chains of vertices of each link, top down
+    let mut chains: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+    for (link, (top, bottom)) in links.iter().zip(&acyclic) {
+        let mut chain = vec![*top];
+        for r in rank[*top] + 1..rank[*bottom] {
+            vertices.push(Vertex { width: DUMMY_WIDTH, height: 0.0, group: None });
+            rank.push(r);
+            chain.push(vertices.len() - 1);
+        }
+        chain.push(*bottom);
+        chains.insert(*link, chain);
+    }
+
+    let mut above: Vec<Vec<usize>> = vec![vec![]; vertices.len()];
+    let mut below: Vec<Vec<usize>> = vec![vec![]; vertices.len()];
+    for chain in chains.values() {
+        for pair in chain.windows(2) {
+            below[pair[0]].push(pair[1]);
+            above[pair[1]].push(pair[0]);
+        }
+    }
+
+    let ranks_count = rank.iter().max().map_or(0, |r| r + 1);
+    let mut layers: Vec<Vec<usize>> = vec![vec![]; ranks_count];
+    for (vertex, r) in rank.iter().enumerate() {
+        layers[*r].push(vertex);
+    }
+    order(&mut layers, &vertices, &above, &below);
+    let x = positions(&layers, &vertices, &above, &below);
+
+    // the ranks from the top, each as high as its highest node
+    let heights: Vec<f64> = layers.iter()
+        .map(|layer| layer.iter().map(|v| vertices[*v].height).fold(0.0, f64::max))
+        .collect();
+    let height = 2.0 * MARGIN + heights.iter().sum::<f64>() + RANK_SEPARATION * heights.len().saturating_sub(1) as f64;
+    let mut y = vec![0.0; layers.len()];
+    let mut top = height - MARGIN;
+    for (r, h) in heights.iter().enumerate() {
+        y[r] = top - h / 2.0;
+        top -= h + RANK_SEPARATION;
+    }
+    let center = |v: usize| Point { x: x[v], y: y[rank[v]] };
+    let width = (0..vertices.len()).map(|v| x[v] + vertices[v].width / 2.0).fold(0.0, f64::max) + MARGIN;
+
+    let mut layout = Layout { nodes: HashMap::new(), edges: HashMap::new(), width: width.max(2.0 * MARGIN), height };
+    for (i, (name, w, h)) in nodes.iter().enumerate() {
+        layout.nodes.insert(name.clone(), Rectangle { center: center(i), width: *w, height: *h });
+    }
+    for (from, to) in edges {
+        let (f, t) = match (index.get(from.as_str()), index.get(to.as_str())) {
+            (Some(f), Some(t)) => (*f, *t),
+            _ => continue,
+        };
+        let route = if f == t {
+            self_loop(&layout.nodes[from])
+        } else {
+            let chain = &chains[&(f, t)];
+            let mut points: Vec<Point> = chain.iter().map(|v| center(*v)).collect();
+            if reversed.contains(&(f, t)) {
+                points.reverse();
+            }
+            // opposite edges side by side
+            if chains.contains_key(&(t, f)) {
+                let shift = if f < t { -4.0 } else { 4.0 };
+                for point in points.iter_mut() {
+                    point.x += shift;
+                }
+            }
+            let (first, last) = (points[0], points[points.len() - 1]);
+            points[0] = boundary(&layout.nodes[from], first, points[1]);
+            let n = points.len();
+            points[n - 1] = boundary(&layout.nodes[to], last, points[n - 2]);
+            spline(&points)
+        };
+        layout.edges.insert((from.clone(), to.clone()), route);
+    }
+    layout
+}
+
+impl Layout {
+    // move the drawing up by dy, the room below left blank
+    pub(crate) fn raise(&mut self, dy: f64) {
+        for node in self.nodes.values_mut() {
+            node.center.y += dy;
+        }
+        for (points, tip) in self.edges.values_mut() {
+            for point in points.iter_mut().chain(std::iter::once(tip)) {
+                point.y += dy;
+            }
+        }
+        self.height += dy;
+    }
+}
+
+// the links closing a cycle, found depth first in the nodes order
+fn back_edges(count: usize, links: &[(usize, usize)]) -> HashSet<(usize, usize)> {
+    let mut successors: Vec<Vec<usize>> = vec![vec![]; count];
+    for (from, to) in links {
+        successors[*from].push(*to);
+    }
+    // 0 unvisited, 1 on the stack, 2 done
+    let mut state = vec![0u8; count];
+    let mut back = HashSet::new();
+    for start in 0..count {
+        if state[start] != 0 {
+            continue;
+        }
+        let mut stack = vec![(start, 0)];
+        state[start] = 1;
+        while let Some((node, next)) = stack.pop() {
+            match successors[node].get(next) {
+                Some(successor) => {
+                    stack.push((node, next + 1));
+                    match state[*successor] {
+                        0 => {
+                            state[*successor] = 1;
+                            stack.push((*successor, 0));
+                        }
+                        1 => {
+                            back.insert((node, *successor));
+                        }
+                        _ => (),
+                    }
+                }
+                None => state[node] = 2,
+            }
+        }
+    }
+    back
+}
+
+// the length of the longest path from a source to each node
+fn ranks(count: usize, links: &[(usize, usize)]) -> Vec<usize> {
+    let mut successors: Vec<Vec<usize>> = vec![vec![]; count];
+    let mut incoming = vec![0; count];
+    for (from, to) in links {
+        successors[*from].push(*to);
+        incoming[*to] += 1;
+    }
+    let mut rank = vec![0; count];
+    let mut ready: Vec<usize> = (0..count).rev().filter(|n| incoming[*n] == 0).collect();
+    while let Some(node) = ready.pop() {
+        for successor in &successors[node] {
+            rank[*successor] = rank[*successor].max(rank[node] + 1);
+            incoming[*successor] -= 1;
+            if incoming[*successor] == 0 {
+                ready.push(*successor);
+            }
+        }
+    }
+    rank
+}
+
+// sweep down then up the ranks, sorting each by the mean position of the
+// neighbors in the previous one, the groups at the mean of their members
+fn order(layers: &mut [Vec<usize>], vertices: &[Vertex], above: &[Vec<usize>], below: &[Vec<usize>]) {
+    let mut position = vec![0.0; vertices.len()];
+    // the groups together from the start
+    for layer in layers.iter_mut() {
+        layer.sort_by_key(|v| (vertices[*v].group.is_none(), vertices[*v].group, *v));
+    }
+    update_positions(layers, &mut position);
+
+    for sweep in 0..8 {
+        let down = sweep % 2 == 0;
+        let range: Vec<usize> = if down { (1..layers.len()).collect() } else { (0..layers.len().saturating_sub(1)).rev().collect() };
+        for r in range {
+            let neighbors = if down { above } else { below };
+            let barycenter = |v: usize| -> f64 {
+                match neighbors[v].len() {
+                    0 => position[v],
+                    n => neighbors[v].iter().map(|u| position[*u]).sum::<f64>() / n as f64,
+                }
+            };
+            let keys: HashMap<usize, f64> = layers[r].iter().map(|v| (*v, barycenter(*v))).collect();
+            let mut sums: HashMap<usize, (f64, usize)> = HashMap::new();
+            for v in &layers[r] {
+                if let Some(group) = vertices[*v].group {
+                    let sum = sums.entry(group).or_insert((0.0, 0));
+                    sum.0 += keys[v];
+                    sum.1 += 1;
+                }
+            }
+            let group_key = |v: usize| match vertices[v].group {
+                Some(group) => sums[&group].0 / sums[&group].1 as f64,
+                None => keys[&v],
+            };
+            let mut sorted: Vec<(f64, usize, f64, usize)> = layers[r].iter()
+                .map(|v| (group_key(*v), vertices[*v].group.unwrap_or(usize::MAX), keys[v], *v))
+                .collect();
+            sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+            layers[r] = sorted.into_iter().map(|(_, _, _, v)| v).collect();
+            update_positions(layers, &mut position);
+        }
+    }
+}
+
+// the index of each vertex in its rank
+fn update_positions(layers: &[Vec<usize>], position: &mut [f64]) {
+    for layer in layers {
+        for (i, v) in layer.iter().enumerate() {
+            position[*v] = i as f64;
+        }
+    }
+}
+
+// the x of the centers: packed left to right, then pulled toward the mean of
+// their neighbors without overlapping
+fn positions(layers: &[Vec<usize>], vertices: &[Vertex], above: &[Vec<usize>], below: &[Vec<usize>]) -> Vec<f64> {
+    let mut x = vec![0.0; vertices.len()];
+    let gap = |a: usize, b: usize| (vertices[a].width + vertices[b].width) / 2.0 + NODE_SEPARATION;
+    for layer in layers {
+        let mut left = MARGIN;
+        for v in layer {
+            x[*v] = left + vertices[*v].width / 2.0;
+            left += vertices[*v].width + NODE_SEPARATION;
+        }
+    }
+
+    for _ in 0..8 {
+        for layer in layers {
+            let wanted: Vec<f64> = layer.iter()
+                .map(|v| {
+                    let neighbors: Vec<f64> = above[*v].iter().chain(&below[*v]).map(|u| x[*u]).collect();
+                    match neighbors.len() {
+                        0 => x[*v],
+                        n => neighbors.iter().sum::<f64>() / n as f64,
+                    }
+                })
+                .collect();
+            // the mean of the wanted positions pushed apart rightward and
+            // leftward, both without overlap
+            let (mut right, mut left) = (wanted.clone(), wanted.clone());
+            for i in 1..layer.len() {
+                right[i] = right[i].max(right[i - 1] + gap(layer[i - 1], layer[i]));
+            }
+            for i in (0..layer.len().saturating_sub(1)).rev() {
+                left[i] = left[i].min(left[i + 1] - gap(layer[i], layer[i + 1]));
+            }
+            for (v, (r, l)) in layer.iter().zip(right.into_iter().zip(left)) {
+                x[*v] = (r + l) / 2.0;
+            }
+        }
+    }
+
+    // back inside the margin
+    let left = (0..vertices.len()).map(|v| x[v] - vertices[v].width / 2.0).fold(f64::INFINITY, f64::min);
+    if left.is_finite() {
+        for value in x.iter_mut() {
+            *value += MARGIN - left;
+        }
+    }
+    x
+}
+
+// where the segment from the center of node toward next leaves its
+// bounding ellipse
+fn boundary(node: &Rectangle, center: Point, next: Point) -> Point {
+    let (dx, dy) = (next.x - center.x, next.y - center.y);
+    let (a, b) = (node.width / 2.0, node.height / 2.0);
+    let scale = ((dx / a).powi(2) + (dy / b).powi(2)).sqrt();
+    if scale == 0.0 {
+        return center;
+    }
+    Point { x: center.x + dx / scale, y: center.y + dy / scale }
+}
+
+// the B-spline through the points, ended an arrowhead before its last point
+fn spline(points: &[Point]) -> (Vec<Point>, Point) {
+    let tip = points[points.len() - 1];
+    let mut points = points.to_vec();
+    let n = points.len();
+    let (before, end) = (points[n - 2], points[n - 1]);
+    let length = ((end.x - before.x).powi(2) + (end.y - before.y).powi(2)).sqrt();
+    if length > ARROW {
+        points[n - 1] = Point { x: end.x - (end.x - before.x) * ARROW / length, y: end.y - (end.y - before.y) * ARROW / length };
+    }
+
+    // Catmull-Rom tangents, one cubic per segment
+    let mut controls = vec![points[0]];
+    for i in 0..n - 1 {
+        let previous = points[i.saturating_sub(1)];
+        let next = points[(i + 2).min(n - 1)];
+        let (p0, p1) = (points[i], points[i + 1]);
+        controls.push(Point { x: p0.x + (p1.x - previous.x) / 6.0, y: p0.y + (p1.y - previous.y) / 6.0 });
+        controls.push(Point { x: p1.x - (next.x - p0.x) / 6.0, y: p1.y - (next.y - p0.y) / 6.0 });
+        controls.push(p1);
+    }
+    (controls, tip)
+}
+
+// a loop on the right side of the node
+fn self_loop(node: &Rectangle) -> (Vec<Point>, Point) {
+    let Point { x, y } = node.center;
+    let (right, quarter) = (x + node.width / 2.0, node.height / 4.0);
+    let points = vec![
+        Point { x: right, y: y + quarter },
+        Point { x: right + 2.0 * NODE_SEPARATION, y: y + 2.0 * quarter },
+        Point { x: right + 2.0 * NODE_SEPARATION, y: y - 2.0 * quarter },
+        Point { x: right + ARROW / 2.0, y: y - quarter },
+    ];
+    (points, Point { x: right, y: y - quarter })
+}
@@ -0,0 +1,86 @@
+//! Parse shared objects and compute their internal and external dependencies.
+//!
+//! Each parsed binary is a node of the [`Graph`], each symbol imported by one
+//! binary and exported by another produces an edge between them.
+//!
+//! ```no_run
+//! # use symbols_graph::{DotWriter, Graph};
+//! # fn main() -> symbols_graph::Result<()> {
+//! let mut graph = Graph::new("deps");
+//! graph.filters.ignore_system = true;
+//! graph.parse_file("libfoo.so")?;
+//! graph.parse_file("libbar.so")?;
+//!
+//! for (_, edge) in graph.edges() {
+//!     let symbols: Vec<&str> = edge.imports().collect();
+//!     println!("{} -> {} : {}", edge.importer(), edge.exporter(), symbols.join(" "));
+//! }
+//! print!("{}", DotWriter::new(&graph));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! With the `serde` feature, [`Graph`] implements `Serialize` and
+//! `Deserialize`: its name, its nodes with their attributes and symbols, and
+//! its edges. The edges are resolved again from the nodes when deserialized,
+//! as for the graphs loaded by [`Graph::parse_file`] from [`Graph::save`].
+
+#![allow(unknown_lints)]
+#![warn(clippy::all)]
+
+extern crate flate2;
+extern crate object;
+extern crate memmap;
+extern crate string_interner;
+extern crate regex;
+extern crate sha2;
+extern crate thiserror;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[macro_use]
+extern crate log;
+
+mod abi;
+mod analyzer;
+mod bazel;
+mod builder;
+mod cache;
+mod dot;
+mod elf;
+mod error;
+mod filters;
+mod graph;
+mod index;
+mod json;
+mod layout;
+mod metrics;
+mod parse;
+mod saved;
+mod source;
+mod sarif;
+#[cfg(feature = "serde")]
+mod serialize;
+mod spill;
+mod stream;
+mod strings;
+mod suggest;
+mod svg;
+mod writer;
+
+pub use abi::{abi_changes, broken_imports, AbiChange, Export};
+pub use analyzer::{Analyzer, Analyzers, BannedSymbols, DependencyCycles, ExportAllowlist, Finding, Severity, Underlinking, UnresolvedImports};
+pub use bazel::BazelWriter;
+pub use builder::GraphBuilder;
+pub use dot::{DotWriter, EdgeWeight, Highlight};
+pub use error::{Degraded, Error, Result};
+pub use filters::{parse_aliases, DroppedSymbol, Filters, GroupRule, SpecialSymbol, Visibility};
+pub use graph::{Definition, Degree, Edge, EdgeDirection, EdgeProperties, Graph, Node, NodeProperties, Resolution, Stats};
+pub use json::{JsonWriter, NodesMetaWriter, SCHEMA, SCHEMA_VERSION};
+pub use metrics::{Condition, Metrics, NodeMetrics, Provider};
+pub use sarif::SarifWriter;
+pub use source::{ObjectSource, Symbols, SymbolSource};
+pub use stream::{DotStream, ParseStream, ParseVisitor};
+pub use suggest::{HiddenExports, LinkChanges};
+pub use svg::SvgWriter;
+pub use writer::GraphWriter;
+pub use object::SymbolKind;
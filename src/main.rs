@@ -2,352 +2,2690 @@
 #![warn(clippy::all)]
 
 extern crate clap;
-extern crate object;
-extern crate memmap;
-extern crate string_interner;
-
-use clap::{Command, Arg, ArgAction};
-use std::collections::HashMap;
-use std::fmt;
-use std::fmt::Display;
+extern crate flate2;
+#[macro_use]
+extern crate log;
+extern crate notify;
+extern crate ratatui;
+extern crate symbols_graph;
+
+mod buildlog;
+mod churn;
+mod serve;
+mod tui;
+
+use clap::{Command, Arg, ArgAction, ArgGroup, ArgMatches};
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use notify::{RecursiveMode, Watcher};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::env;
+use std::error;
 use std::fs;
 use std::io;
-use std::io::Write;
-use std::path::Path;
-use std::str;
-use object::{Object, ObjectSymbol};
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::panic;
+use std::process;
+use std::sync::Mutex;
+use std::sync::mpsc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use symbols_graph::{abi_changes, broken_imports, parse_aliases, AbiChange, Analyzers, BannedSymbols, BazelWriter, Condition, ExportAllowlist, Degree, DotStream, EdgeDirection, DotWriter, EdgeWeight, Error, Filters, Graph, GroupRule, HiddenExports, Highlight, JsonWriter, LinkChanges, Metrics, NodeMetrics, NodesMetaWriter, ParseStream, Provider, Resolution, SarifWriter, Severity, SpecialSymbol, Stats, SvgWriter, SymbolKind, Underlinking, Visibility};
+
+// exit codes, clap already exits with 2 on usage errors
+const EXIT_FAILURE: i32 = 1;
+const EXIT_INPUTS_FAILED: i32 = 3;
+const EXIT_CHECK_FAILED: i32 = 4;
+
+// the exit code of a subcommand, or a fatal error
+type CommandResult = Result<i32, Box<dyn error::Error>>;
+
+// the input and filter options shared by all the subcommands
+fn input_args(command: Command) -> Command {
+    command
+        .arg(
+            Arg::new("verbose")
+                .short('v')
+                .long("verbose")
+                .action(clap::ArgAction::Count)
+                .help("Log the steps with -v, each parsed file with -vv, on stderr")
+                .required(false),
+        )
+        .arg(
+            Arg::new("log-format")
+                .long("log-format")
+                .num_args(1)
+                .value_parser(["text", "json"])
+                .default_value("text")
+                .help("Write the log records as text lines or as one JSON object per line")
+                .action(ArgAction::Set)
+                .required(false),
+        )
+        .arg(
+            Arg::new("diagnostics")
+                .long("diagnostics")
+                .num_args(1)
+                .value_parser(["text", "json"])
+                .default_value("text")
+                .help("Report the failed inputs, skipped and dropped symbols and unresolved imports as JSON lines on stderr")
+                .action(ArgAction::Set)
+                .required(false),
+        )
+        .arg(
+            Arg::new("group")
+                .short('g')
+                .long("group")
+                .num_args(1)
+                .value_name("s/REGEX/NAME/")
+                .value_parser(GroupRule::parse)
+                .help("Group the nodes matching REGEX into the NAME cluster, captures are expanded")
+                .action(ArgAction::Append)
+                .required(false),
+        )
+        .arg(
+            Arg::new("aggregate")
+                .long("aggregate")
+                .action(clap::ArgAction::SetTrue)
+                .help("Collapse each group into a single node")
+                .required(false),
+        )
+        .arg(
+            Arg::new("ignore-system")
+                .long("ignore-system")
+                .action(clap::ArgAction::SetTrue)
+                .help("Omit the system libraries (libc, libm, libstdc++, kernel32, ...) and their edges")
+                .required(false),
+        )
+        .arg(
+            Arg::new("keep-reserved")
+                .long("keep-reserved")
+                .action(clap::ArgAction::SetTrue)
+                .help("Keep the _ prefixed symbols reserved to the compiler and runtime")
+                .required(false),
+        )
+        .arg(
+            Arg::new("drop-markers")
+                .long("drop-markers")
+                .num_args(1)
+                .value_name("NAMES")
+                .value_delimiter(',')
+                .default_value("$a,$t,$d,$x")
+                .help("Drop these assembler markers, comma separated, even with --keep-reserved; none to keep them")
+                .action(ArgAction::Append)
+                .required(false),
+        )
+        .arg(
+            Arg::new("only")
+                .long("only")
+                .num_args(1)
+                .value_parser(["functions", "data"])
+                .help("Keep only the function or the data symbols")
+                .action(ArgAction::Set)
+                .required(false),
+        )
+        .arg(
+            Arg::new("min-visibility")
+                .long("min-visibility")
+                .num_args(1)
+                .value_parser(["default", "protected", "hidden", "internal"])
+                .help("Exclude the symbols less visible than this ELF visibility")
+                .action(ArgAction::Set)
+                .required(false),
+        )
+        .arg(
+            Arg::new("no-weak")
+                .long("no-weak")
+                .action(clap::ArgAction::SetTrue)
+                .help("Drop the weak definitions and the weak undefined imports")
+                .required(false),
+        )
+        .arg(
+            Arg::new("special-exports")
+                .long("special-exports")
+                .num_args(1)
+                .value_name("KINDS")
+                .value_delimiter(',')
+                .value_parser(["unique", "common", "absolute", "none"])
+                .default_value("unique")
+                .help("Export the GNU unique, common (SHN_COMMON) or absolute (SHN_ABS) ELF definitions like the others, comma separated; the others are dropped")
+                .action(ArgAction::Append)
+                .required(false),
+        )
+        .arg(
+            Arg::new("forwarders")
+                .long("forwarders")
+                .num_args(1)
+                .value_parser(["hop", "final"])
+                .default_value("hop")
+                .help("Draw the forwarded PE exports (kernel32!HeapAlloc -> ntdll!RtlAllocateHeap) as an edge through the forwarding DLL, or resolve them to the final DLL")
+                .action(ArgAction::Set)
+                .required(false),
+        )
+        .arg(
+            Arg::new("go-runtime")
+                .long("go-runtime")
+                .action(clap::ArgAction::SetTrue)
+                .help("Keep the symbols of the Go runtime and internal packages in the Go binaries")
+                .required(false),
+        )
+        .arg(
+            Arg::new("go-packages")
+                .long("go-packages")
+                .action(clap::ArgAction::SetTrue)
+                .help("Name the symbols of the Go binaries after their package path, one per package")
+                .required(false),
+        )
+        .arg(
+            Arg::new("hash")
+                .long("hash")
+                .num_args(1)
+                .value_parser(["sha256"])
+                .help("Record the digest of each input as a node attribute")
+                .action(ArgAction::Set)
+                .required(false),
+        )
+        .arg(
+            Arg::new("ignore-case")
+                .long("ignore-case")
+                .action(clap::ArgAction::SetTrue)
+                .help("Match the symbols and the modules regardless of the case, always on for PE inputs")
+                .required(false),
+        )
+        .arg(
+            Arg::new("strip-prefix")
+                .long("strip-prefix")
+                .num_args(1)
+                .value_name("PREFIX")
+                .value_parser(expand)
+                .help("Label the nodes with their path relative to PREFIX instead of their basename")
+                .action(ArgAction::Append)
+                .required(false),
+        )
+        .arg(
+            Arg::new("aliases")
+                .long("aliases")
+                .num_args(1)
+                .value_name("FILE")
+                .value_parser(expand)
+                .help("Rename the nodes using the `name -> alias` lines of FILE")
+                .action(ArgAction::Set)
+                .required(false),
+        )
+        .arg(
+            Arg::new("jobs")
+                .short('j')
+                .long("jobs")
+                .num_args(1)
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .help("Parse N files in parallel, defaults to the number of CPUs")
+                .action(ArgAction::Set)
+                .required(false),
+        )
+        .arg(
+            Arg::new("cache")
+                .long("cache")
+                .num_args(1)
+                .value_name("DIR")
+                .value_parser(expand)
+                .help("Keep the symbols of each input in DIR, only the changed files are parsed again")
+                .action(ArgAction::Set)
+                .required(false),
+        )
+        .arg(
+            Arg::new("node-exec")
+                .long("node-exec")
+                .num_args(1)
+                .value_name("COMMAND")
+                .help("Run the shell COMMAND for each parsed file, {path} is replaced by the file, and keep its output as the exec node attribute")
+                .action(ArgAction::Set)
+                .required(false),
+        )
+        .arg(
+            Arg::new("resolution")
+                .long("resolution")
+                .num_args(1)
+                .value_parser(["all", "first", "elf-order"])
+                .default_value("first")
+                .help("Resolve the symbols exported by several inputs to all of them, to the first one parsed, or to the one found first in the load order of the importer")
+                .action(ArgAction::Set)
+                .required(false),
+        )
+        .arg(
+            Arg::new("edge-direction")
+                .long("edge-direction")
+                .num_args(1)
+                .value_parser(["uses", "provides"])
+                .default_value("uses")
+                .help("Point the edges from the importers to the libraries they use, or from the exporters to the libraries they provide to")
+                .action(ArgAction::Set)
+                .required(false),
+        )
+        .arg(
+            Arg::new("resolve-against")
+                .long("resolve-against")
+                .num_args(1)
+                .value_name("SYSROOT")
+                .value_parser(expand)
+                .help("Attribute the imports no input exports to the libraries found under SYSROOT, added as grey external nodes, defaults to the library path with --sysroot or --library-path")
+                .action(ArgAction::Set)
+                .required(false),
+        )
+        .arg(
+            Arg::new("providers")
+                .long("providers")
+                .num_args(1)
+                .value_name("INDEX")
+                .value_parser(expand)
+                .help("Attribute the imports no input exports to the libraries of INDEX, written by the index subcommand, without parsing them")
+                .action(ArgAction::Set)
+                .required(false),
+        )
+        .arg(
+            Arg::new("sysroot")
+                .long("sysroot")
+                .num_args(1)
+                .value_name("DIR")
+                .value_parser(expand)
+                .help("Look the libraries up inside DIR, the root of the target image when cross-compiling")
+                .action(ArgAction::Set)
+                .required(false),
+        )
+        .arg(
+            Arg::new("library-path")
+                .long("library-path")
+                .num_args(1)
+                .value_name("DIR")
+                .value_parser(expand)
+                .help("Look the libraries up in DIR, inside the --sysroot when set, instead of /lib, /lib64, /usr/lib and /usr/lib64; repeat for several directories. The libraries found there are the system ones for --ignore-system")
+                .action(ArgAction::Append)
+                .required(false),
+        )
+        .arg(
+            Arg::new("quick")
+                .long("quick")
+                .action(clap::ArgAction::SetTrue)
+                .help("Link the inputs by their needed libraries only, without reading the symbol tables nor using the cache, for a first look at large images")
+                .required(false),
+        )
+        .arg(
+            Arg::new("plugins")
+                .long("plugins")
+                .num_args(1)
+                .value_name("DIR=HOST")
+                .value_parser(plugins)
+                .help("Parse the libraries under DIR as plugins loaded at runtime by HOST, an input or a binary parsed first: HOST depends on each of them through a loaded at runtime edge and their imports resolve to its exports; repeat for several directories")
+                .action(ArgAction::Append)
+                .required(false),
+        )
+        .arg(
+            Arg::new("follow-deps")
+                .long("follow-deps")
+                .action(clap::ArgAction::SetTrue)
+                .help("Also parse the needed libraries of the inputs, found next to them or in the library path, and theirs in turn")
+                .required(false),
+        )
+        .arg(
+            Arg::new("max-depth")
+                .long("max-depth")
+                .num_args(1)
+                .value_name("N")
+                .value_parser(clap::value_parser!(usize))
+                .help("Follow the needed libraries at most N levels away from the inputs")
+                .action(ArgAction::Set)
+                .requires("follow-deps")
+                .required(false),
+        )
+        .arg(
+            Arg::new("stop-at")
+                .long("stop-at")
+                .num_args(1)
+                .value_name("GLOB")
+                .help("Parse the needed libraries matching GLOB without following their own, eg. 'libc.so*'; matched against the soname, or the path when GLOB has a /; repeat for several globs")
+                .action(ArgAction::Append)
+                .requires("follow-deps")
+                .required(false),
+        )
+        .arg(
+            Arg::new("save")
+                .long("save")
+                .num_args(1)
+                .value_name("FILE")
+                .value_parser(expand)
+                .help("Save the parsed graph to FILE.sgz, to pass it again as an input merged with other files without parsing them")
+                .action(ArgAction::Set)
+                .required(false),
+        )
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .action(clap::ArgAction::SetTrue)
+                .help("List the files that would be parsed and the outputs that would be written, then exit")
+                .required(false),
+        )
+        .arg(
+            Arg::new("no-mmap")
+                .long("no-mmap")
+                .action(clap::ArgAction::SetTrue)
+                .help("Read the inputs into memory instead of mapping them")
+                .required(false),
+        )
+        .arg(
+            Arg::new("stats")
+                .long("stats")
+                .action(clap::ArgAction::SetTrue)
+                .help("Report the time spent in each phase, the slowest inputs and the peak memory on stderr")
+                .required(false),
+        )
+        .arg(
+            Arg::new("watch")
+                .long("watch")
+                .action(clap::ArgAction::SetTrue)
+                .help("Regenerate the output each time a file read or a directory searched changes")
+                .required(false),
+        )
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .num_args(1)
+                .value_name("[FORMAT:]FILE")
+                .value_parser(Output::parse)
+                .help("Write to FILE, - for stdout, the format defaults to the extension of FILE; repeat to write several formats")
+                .action(ArgAction::Append)
+                .required(false),
+        )
+        .arg(
+            Arg::new("compress")
+                .long("compress")
+                .action(clap::ArgAction::SetTrue)
+                .help("Compress the outputs with gzip, implied by the .gz outputs")
+                .required(false),
+        )
+        .arg(
+            Arg::new("file")
+                .value_parser(expand)
+                .help("Sets the input file to use, or a .ninja_log, .ninja_deps or make -p database to use the libraries and executables it lists")
+                .action(ArgAction::Append)
+                .required(true),
+        )
+}
+
+// the directory of the plugins and their host
+fn plugins(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((dir, host)) if !dir.is_empty() && !host.is_empty() => Ok((expand(dir)?, expand(host)?)),
+        _ => Err(format!("Expected DIR=HOST, got {}", value)),
+    }
+}
+
+// expand a leading ~ and the $NAME or ${NAME} environment variables
+fn expand(value: &str) -> Result<String, String> {
+    let value = match value.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => match env::var("HOME") {
+            Ok(home) => format!("{}{}", home, rest),
+            Err(_) => return Err(format!("Unable to expand ~ in {} : HOME is not set", value)),
+        },
+        _ => value.to_string(),
+    };
+
+    let mut expanded = String::new();
+    let mut rest = value.as_str();
+    while let Some(index) = rest.find('$') {
+        expanded += &rest[..index];
+        rest = &rest[index+1..];
+        let (name, next) = match rest.strip_prefix('{') {
+            Some(braced) => match braced.find('}') {
+                Some(end) => (&braced[..end], &braced[end+1..]),
+                None => return Err(format!("Unable to expand {} : missing }}", value)),
+            },
+            None => {
+                let end = rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(rest.len());
+                (&rest[..end], &rest[end..])
+            }
+        };
+        // a lone $ is kept
+        if name.is_empty() {
+            expanded.push('$');
+            continue;
+        }
+        match env::var(name) {
+            Ok(variable) => expanded += &variable,
+            Err(_) => return Err(format!("Unable to expand {} : {} is not set", value, name)),
+        }
+        rest = next;
+    }
+    expanded += rest;
+    Ok(expanded)
+}
+
+// the filters set by the shared options
+fn filters(matches: &ArgMatches) -> Result<Filters, Box<dyn error::Error>> {
+    let aliases = match matches.get_one::<String>("aliases") {
+        Some(aliases) => {
+            let content = match fs::read_to_string(aliases) {
+                Ok(content) => content,
+                Err(error) => return Err(format!("Unable to read {} : {}", aliases, error).into()),
+            };
+            let aliases = match parse_aliases(&content) {
+                Ok(aliases) => aliases,
+                Err(error) => return Err(format!("Unable to parse {} : {}", aliases, error).into()),
+            };
+            let mut expanded = HashMap::new();
+            for (name, alias) in aliases {
+                expanded.insert(expand(&name)?, expand(&alias)?);
+            }
+            expanded
+        }
+        None => HashMap::new(),
+    };
+
+    Ok(Filters {
+        ignore_system: matches.get_flag("ignore-system"),
+        system_dirs: library_dirs(matches).unwrap_or_default(),
+        keep_reserved: matches.get_flag("keep-reserved"),
+        markers: matches.get_many::<String>("drop-markers")
+            .map(|m| m.filter(|m| *m != "none").cloned().collect())
+            .unwrap_or_default(),
+        ignore_case: matches.get_flag("ignore-case"),
+        only: match matches.get_one::<String>("only").map(String::as_str) {
+            Some("functions") => Some(SymbolKind::Text),
+            Some("data") => Some(SymbolKind::Data),
+            _ => None,
+        },
+        min_visibility: match matches.get_one::<String>("min-visibility").map(String::as_str) {
+            Some("default") => Some(Visibility::Default),
+            Some("protected") => Some(Visibility::Protected),
+            Some("hidden") => Some(Visibility::Hidden),
+            Some("internal") => Some(Visibility::Internal),
+            _ => None,
+        },
+        no_weak: matches.get_flag("no-weak"),
+        special_exports: matches.get_many::<String>("special-exports")
+            .map(|k| k.filter_map(|k| SpecialSymbol::from_name(k)).collect())
+            .unwrap_or_default(),
+        follow_forwarders: matches.get_one::<String>("forwarders").map(String::as_str) == Some("final"),
+        go_runtime: matches.get_flag("go-runtime"),
+        go_packages: matches.get_flag("go-packages"),
+        needed_only: matches.get_flag("quick"),
+        sha256: matches.get_one::<String>("hash").is_some(),
+        strip_prefixes: matches.get_many::<String>("strip-prefix").map(|p| p.cloned().collect()).unwrap_or_default(),
+        aliases,
+    })
+}
+
+// the input files in order, without the duplicates
+fn unique_files<'a, I: Iterator<Item = &'a String>>(files: I) -> Vec<String> {
+    let mut seen = HashSet::new();
+    // the build logs stand for the binaries they list
+    files.flat_map(|f| match buildlog::outputs(f) {
+            Some(outputs) => {
+                info!("Found {} libraries and executables in {}", outputs.len(), f);
+                outputs
+            }
+            None => vec![f.clone()],
+        })
+        .filter(|f| seen.insert(f.clone()))
+        .collect()
+}
+
+// the --library-path directories inside the --sysroot, or its default ones,
+// none without these options
+fn library_dirs(matches: &ArgMatches) -> Option<Vec<PathBuf>> {
+    let sysroot = matches.get_one::<String>("sysroot").map(PathBuf::from);
+    let dirs: Vec<&str> = match matches.get_many::<String>("library-path") {
+        Some(dirs) => dirs.map(String::as_str).collect(),
+        None if sysroot.is_some() => vec!["/lib", "/lib64", "/usr/lib", "/usr/lib64"],
+        None => return None,
+    };
+    Some(dirs.into_iter()
+        .map(|d| match &sysroot {
+            Some(sysroot) => sysroot.join(d.trim_start_matches('/')),
+            None => PathBuf::from(d),
+        })
+        .filter(|d| d.is_dir())
+        .collect())
+}
+
+fn edge_direction(matches: &ArgMatches) -> EdgeDirection {
+    match matches.get_one::<String>("edge-direction").map(String::as_str) {
+        Some("provides") => EdgeDirection::Provides,
+        _ => EdgeDirection::Uses,
+    }
+}
+
+fn resolution(matches: &ArgMatches) -> Resolution {
+    match matches.get_one::<String>("resolution").map(String::as_str) {
+        Some("all") => Resolution::All,
+        Some("elf-order") => Resolution::ElfOrder,
+        _ => Resolution::First,
+    }
+}
+
+// the --jobs threads count, defaults to the CPUs count
+fn jobs(matches: &ArgMatches) -> usize {
+    match matches.get_one::<usize>("jobs") {
+        Some(jobs) => *jobs,
+        None => thread::available_parallelism().map(|n| n.get()).unwrap_or(1),
+    }
+}
+
+// parse the files into a graph, applying the shared options
+//
+// the failing inputs are skipped and logged on stderr
+fn parse_inputs<'a, I: Iterator<Item = &'a String>>(matches: &ArgMatches, files: I) -> Result<(Graph, Inputs), Box<dyn error::Error>> {
+    let mut graph = Graph::new(matches.try_get_one::<String>("name").ok().flatten().map_or("", String::as_str));
+    graph.filters = filters(matches)?;
+    graph.cache = matches.get_one::<String>("cache").map(PathBuf::from);
+    graph.mmap = !matches.get_flag("no-mmap");
+    graph.resolution = resolution(matches);
+    graph.edge_direction = edge_direction(matches);
+    // the edges symbols are not rendered when merged or only counted, the
+    // saved graphs need them to know the imports
+    graph.edge_symbols = (matches.try_get_one::<bool>("merge").ok().flatten() != Some(&true)
+        && matches.try_get_one::<usize>("max-edge-symbols").ok().flatten() != Some(&0))
+        || matches.contains_id("save");
+
+    let files = unique_files(files);
+    let jobs = jobs(matches);
+    let Sources { failures, count, .. } = parse_sources(matches, &mut graph, &files, jobs)?;
+    if let Some(index) = matches.get_one::<String>("providers") {
+        info!("Resolving against the providers of {}", index);
+        graph.resolve_against_index(index)?;
+    }
+
+    let json = matches.get_one::<String>("diagnostics").map(String::as_str) == Some("json");
+    if json {
+        let mut records = vec![];
+        for error in &failures {
+            records.push(diagnostic("error", "unparsable_file", error.path(), &[("message", error.to_string())]));
+        }
+        for warning in graph.warnings() {
+            match warning {
+                Error::NonUtf8Symbol { path, symbol } => records.push(diagnostic("warning", "non_utf8_symbol", path, &[
+                    ("symbol", String::from_utf8_lossy(symbol).into_owned()),
+                ])),
+                Error::MixedVersioning { path, symbols, versioned } => records.push(diagnostic("warning", "mixed_versioning", path, &[
+                    ("symbols", symbols.join(" ")),
+                    ("versioned", versioned.to_string()),
+                ])),
+                Error::Degraded { path, degraded } => records.push(diagnostic("warning", "degraded_node", path, &[
+                    ("degraded", degraded.name().to_string()),
+                ])),
+                Error::WriteFailed { path, error } => records.push(diagnostic("warning", "unwritable_cache", path, &[
+                    ("message", error.to_string()),
+                ])),
+                warning => records.push(diagnostic("warning", "unparsable_file", warning.path(), &[("message", warning.to_string())])),
+            }
+        }
+        for dropped in graph.dropped() {
+            records.push(diagnostic("info", "dropped_symbol", &dropped.path, &[
+                ("symbol", dropped.symbol.clone()),
+                ("reason", dropped.reason.to_string()),
+            ]));
+        }
+        for (symbol, importers) in graph.unresolved() {
+            for importer in importers {
+                records.push(diagnostic("warning", "unresolved_import", importer, &[("symbol", symbol.to_string())]));
+            }
+        }
+        let mut stderr = io::stderr().lock();
+        for record in records {
+            writeln!(stderr, "{}", record)?;
+        }
+    } else {
+        for warning in graph.warnings() {
+            warn!("{}", warning);
+        }
+        for dropped in graph.dropped() {
+            trace!("Dropped {} {} of {}", dropped.reason, dropped.symbol, dropped.path);
+        }
+        for error in &failures {
+            error!("{}", error);
+        }
+    }
+    if !failures.is_empty() {
+        error!("{} of {} inputs failed", failures.len(), count);
+    }
+
+    // before grouping, the rules may match the attribute
+    if let Some(command) = matches.get_one::<String>("node-exec") {
+        info!("Running {} for each node", command);
+        node_exec(&mut graph, command, jobs);
+    }
+
+    // the nodes as parsed, before grouping
+    if let Some(path) = matches.get_one::<String>("save") {
+        info!("Saving the graph to {}", path);
+        let saved = fs::File::create(path).and_then(|file| graph.save(io::BufWriter::new(file)));
+        if let Err(error) = saved {
+            return Err(format!("Unable to save {} : {}", path, error).into());
+        }
+    }
+
+    if let Some(rules) = matches.get_many::<GroupRule>("group") {
+        let rules: Vec<GroupRule> = rules.cloned().collect();
+        info!("Grouping");
+        graph.group(&rules);
+
+        if matches.get_flag("aggregate") {
+            info!("Aggregating");
+            graph.aggregate();
+        }
+    }
+
+    let stats = if matches.get_flag("stats") {
+        Some((graph.stats().clone(), Instant::now()))
+    } else {
+        None
+    };
+    Ok((graph, Inputs { files: count, failed: failures.len(), stats }))
+}
+
+// the files parse_sources read: the failing ones, how many were tried and
+// the libraries resolved against
+struct Sources {
+    failures: Vec<Error>,
+    count: usize,
+    libraries: Vec<String>,
+}
+
+// parse the files, the --plugins libraries, the --follow-deps ones and the
+// --resolve-against sysroot into the graph, the files discovery shared by
+// parse_inputs and --dry-run
+fn parse_sources(matches: &ArgMatches, graph: &mut Graph, files: &[String], jobs: usize) -> Result<Sources, Box<dyn error::Error>> {
+    let progress = Progress::on_terminal(files);
+    let mut failures = graph.parse_files(files, jobs, |f| progress.start(f));
+    progress.finish();
+    let mut count = files.len();
+
+    for (dir, host) in matches.get_many::<(String, String)>("plugins").unwrap_or_default() {
+        let (parsed, errors) = attach_plugins(graph, dir, host, jobs)?;
+        count += parsed;
+        failures.extend(errors);
+    }
+
+    if matches.get_flag("follow-deps") {
+        let dirs = library_dirs(matches).unwrap_or_else(|| {
+            ["/lib", "/lib64", "/usr/lib", "/usr/lib64"].iter().map(PathBuf::from).filter(|d| d.is_dir()).collect()
+        });
+        let stop_at: Vec<String> = matches.get_many::<String>("stop-at").unwrap_or_default().cloned().collect();
+        let max_depth = matches.get_one::<usize>("max-depth").cloned().unwrap_or(usize::MAX);
+        for error in graph.follow_dependencies(&dirs, &stop_at, max_depth, jobs) {
+            warn!("Unable to follow {}", error);
+        }
+    }
+
+    let dirs = match matches.get_one::<String>("resolve-against") {
+        Some(sysroot) => Some(vec![PathBuf::from(sysroot)]),
+        None => library_dirs(matches),
+    };
+    let mut libraries = vec![];
+    if let Some(dirs) = dirs {
+        libraries = sysroot_libraries(&dirs)?;
+        info!("Resolving against the {} libraries of {}", libraries.len(), dirs.iter().map(|d| d.display().to_string()).collect::<Vec<_>>().join(", "));
+        // the sysroot holds linker scripts and other non binary files
+        for error in graph.resolve_against(&libraries, jobs) {
+            debug!("Skipped {}", error);
+        }
+    }
+    Ok(Sources { failures, count, libraries })
+}
+
+// parse the libraries under dir and attach them to host, parsed first when
+// it is not an input; returns the number of files parsed and their errors
+fn attach_plugins(graph: &mut Graph, dir: &str, host: &str, jobs: usize) -> Result<(usize, Vec<Error>), Box<dyn error::Error>> {
+    let mut errors = vec![];
+    let mut count = 0;
+    if graph.find_node(host).is_none() && Path::new(host).is_file() {
+        errors = graph.parse_files(&[host.to_string()], jobs, |_| ());
+        count += 1;
+    }
+    let host_node = match graph.find_node(host) {
+        Some(node) => node,
+        None => return Err(format!("Unknown plugin host {}", host).into()),
+    };
+
+    let host_path = graph.path(host_node).map(String::from);
+    let mut libraries = sysroot_libraries(&[PathBuf::from(dir)])?;
+    libraries.retain(|l| Some(l) != host_path.as_ref());
+    info!("Attaching {} plugins of {} to {}", libraries.len(), dir, host);
+    errors.extend(graph.parse_files(&libraries, jobs, |_| ()));
+    count += libraries.len();
+
+    let libraries: HashSet<&String> = libraries.iter().collect();
+    let plugins: Vec<u32> = graph.nodes()
+        .map(|(n, _)| n)
+        .filter(|n| graph.path(*n).is_some_and(|p| libraries.contains(&p.to_string())))
+        .collect();
+    graph.attach_plugins(host_node, &plugins);
+    Ok((count, errors))
+}
+
+// the shared libraries under the dirs, sorted in each, each file once
+// whatever its links
+fn sysroot_libraries(dirs: &[PathBuf]) -> Result<Vec<String>, Box<dyn error::Error>> {
+    let mut libraries = vec![];
+    for dir in dirs {
+        let mut found = vec![];
+        list_libraries(dir, &mut found).map_err(|e| format!("Unable to list {} : {}", dir.display(), e))?;
+        found.sort();
+        libraries.extend(found);
+    }
+
+    let mut seen = HashSet::new();
+    libraries.retain(|l| seen.insert(fs::canonicalize(l).unwrap_or_else(|_| PathBuf::from(l))));
+    Ok(libraries)
+}
+
+fn list_libraries(dir: &Path, libraries: &mut Vec<String>) -> io::Result<()> {
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            let name = path.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default();
+            if name.contains(".so") || name.ends_with(".dll") || name.ends_with(".dylib") {
+                libraries.extend(path.to_str().map(String::from));
+            }
+        }
+    }
+    Ok(())
+}
+
+// a JSON diagnostic record, path is the file or the node label
+fn diagnostic(severity: &str, kind: &str, path: &str, fields: &[(&str, String)]) -> String {
+    let mut record = format!("{{\"severity\":\"{}\",\"kind\":\"{}\",\"path\":{}", severity, kind, json_string(path));
+    for (name, value) in fields {
+        record += &format!(",\"{}\":{}", name, json_string(value));
+    }
+    record + "}"
+}
+
+// attach the trimmed output of command run for each node as the exec attribute
+fn node_exec(graph: &mut Graph, command: &str, jobs: usize) {
+    let mut nodes: Vec<(u32, String)> = graph.nodes()
+        .filter_map(|(n, _)| Some((n, graph.path(n)?.to_string())))
+        .collect();
+    nodes.sort();
+
+    let next = AtomicUsize::new(0);
+    let outputs: Vec<(u32, String)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs.max(1))
+            .map(|_| scope.spawn(|| {
+                let mut outputs = vec![];
+                while let Some((node, path)) = nodes.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    // single quoted for sh, a quote closes, escapes and reopens
+                    let quoted = format!("'{}'", path.replace('\'', "'\\''"));
+                    let line = command.replace("{path}", &quoted);
+                    match process::Command::new("sh").arg("-c").arg(&line).output() {
+                        Ok(output) if output.status.success() => {
+                            outputs.push((*node, String::from_utf8_lossy(&output.stdout).trim().to_string()));
+                        }
+                        Ok(output) => warn!("{} failed with {}", line, output.status),
+                        Err(error) => warn!("Unable to run {} : {}", line, error),
+                    }
+                }
+                outputs
+            }))
+            .collect();
+        workers.into_iter().flat_map(|w| w.join().unwrap_or_else(|e| panic::resume_unwind(e))).collect()
+    });
+
+    for (node, output) in outputs {
+        graph.set_attribute(node, "exec", &output);
+    }
+}
+
+// the parsed input counts of a run
+struct Inputs {
+    files: usize,
+    failed: usize,
+    // with --stats, the graph ones and the end of the parsing
+    stats: Option<(Stats, Instant)>,
+}
+
+impl Inputs {
+    fn ok(&self) -> bool {
+        self.failed == 0
+    }
+
+    fn summarize(&self, title: &str, graph: &Graph) {
+        self.print_summary(title, graph.nodes().count(), graph.edges().count(), graph.unresolved().len(), Some(graph.cycles().len()));
+        if let Some((stats, parsed)) = &self.stats {
+            print_stats(title, graph, stats, parsed.elapsed());
+        }
+    }
+
+    // one line on stderr, colored on terminals unless NO_COLOR is set
+    fn print_summary(&self, title: &str, nodes: usize, edges: usize, unresolved: usize, cycles: Option<usize>) {
+        let color = io::stderr().is_terminal() && env::var_os("NO_COLOR").is_none();
+        let paint = |count: usize, what: &str, bad: &str| {
+            let code = match count {
+                0 => "32",
+                _ => bad,
+            };
+            if color {
+                format!("\x1b[{}m{} {}\x1b[0m", code, count, what)
+            } else {
+                format!("{} {}", count, what)
+            }
+        };
+
+        // the cycles are unknown when streaming
+        let cycles = match cycles {
+            Some(cycles) => format!(", {}", paint(cycles, "cycles", "33")),
+            None => String::new(),
+        };
+        eprintln!("{}{} parsed, {}, {} nodes, {} edges, {}{}",
+                  title,
+                  paint(self.files - self.failed, "files", "32"),
+                  paint(self.failed, "failed", "31"),
+                  nodes,
+                  edges,
+                  paint(unresolved, "unresolved symbols", "33"),
+                  cycles);
+    }
+}
+
+// the phases timings then the slowest files on stderr, write is everything
+// done after the parsing
+fn print_stats(title: &str, graph: &Graph, stats: &Stats, write: Duration) {
+    let memory = match peak_memory() {
+        Some(kb) => format!("{:.1} MB", kb as f64 / 1024.0),
+        None => String::from("unknown"),
+    };
+    eprintln!("{}open {:.1?}, parse {:.1?}, resolve {:.1?}, write {:.1?}, peak memory {}",
+              title, stats.open, stats.parse, stats.resolve, write, memory);
+
+    let mut files = stats.files.clone();
+    files.sort_by_key(|(_, time)| std::cmp::Reverse(*time));
+    for (node, time) in files.iter().take(5) {
+        eprintln!("{}  {:.1?} {}", title, time, graph.path(*node).unwrap_or_default());
+    }
+}
+
+// the resident set high water mark in kB, only known on Linux
+fn peak_memory() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with("VmHWM:"))?;
+    line.split_whitespace().nth(1)?.parse().ok()
+}
+
+// a one line progress bar on stderr, updated as each file is started
+struct Progress {
+    // false when stderr is not a terminal or has log lines
+    visible: bool,
+    files: usize,
+    bytes: u64,
+    started: AtomicUsize,
+    started_bytes: AtomicU64,
+    // serialize the redraws of the worker threads
+    line: Mutex<()>,
+}
+
+impl Progress {
+    fn on_terminal(files: &[String]) -> Self {
+        // the progress bar would be torn by the log lines
+        let visible = !log_enabled!(log::Level::Info) && io::stderr().is_terminal();
+        let bytes = if visible {
+            files.iter().filter_map(|f| fs::metadata(f).ok()).map(|m| m.len()).sum()
+        } else {
+            0
+        };
+        Self {
+            visible,
+            files: files.len(),
+            bytes,
+            started: AtomicUsize::new(0),
+            started_bytes: AtomicU64::new(0),
+            line: Mutex::new(()),
+        }
+    }
+
+    fn start(&self, filename: &str) {
+        debug!("Parsing file {}", filename);
+        if !self.visible {
+            return;
+        }
+        let size = fs::metadata(filename).map(|m| m.len()).unwrap_or(0);
+        let started = self.started.fetch_add(1, Ordering::Relaxed) + 1;
+        let started_bytes = self.started_bytes.fetch_add(size, Ordering::Relaxed) + size;
+
+        let width = 30;
+        let filled = match (started_bytes * width as u64).checked_div(self.bytes) {
+            Some(filled) => filled as usize,
+            None => width,
+        };
+        let _line = self.line.lock();
+        eprint!("\r\x1b[K[{}{}] {}/{} files, {:.1}/{:.1} MiB {}",
+            "#".repeat(filled.min(width)),
+            " ".repeat(width - filled.min(width)),
+            started,
+            self.files,
+            started_bytes as f64 / 1048576.0,
+            self.bytes as f64 / 1048576.0,
+            filename);
+    }
+
+    fn finish(&self) {
+        if self.visible {
+            let _line = self.line.lock();
+            eprint!("\r\x1b[K");
+        }
+    }
+}
+
+fn exit_code(inputs_ok: bool) -> i32 {
+    if inputs_ok {
+        0
+    } else {
+        EXIT_INPUTS_FAILED
+    }
+}
+
+// an -o destination
+#[derive(Debug, Clone)]
+struct Output {
+    format: Option<String>,
+    path: String,
+}
+
+impl Output {
+    const FORMATS: [&'static str; 7] = ["text", "dot", "json", "xdot", "svg", "sarif", "bazel"];
+
+    fn parse(output: &str) -> Result<Self, String> {
+        // a known format prefix, windows paths start with a drive letter
+        let (format, path) = match output.find(':') {
+            Some(index) if Self::FORMATS.contains(&&output[..index]) => (Some(output[..index].to_string()), &output[index+1..]),
+            _ => (None, output),
+        };
+        if path.is_empty() {
+            return Err(format!("missing file in {}, use - for stdout", output));
+        }
+        Ok(Output { format, path: expand(path)? })
+    }
+
+    // the explicit format, else the one named by the extension, else the first one
+    fn format(&self, formats: &[&'static str]) -> Result<&'static str, String> {
+        let format = match &self.format {
+            Some(format) => format.as_str(),
+            // graph.dot.gz is a compressed DOT file
+            None => match Path::new(self.path.trim_end_matches(".gz")).extension().and_then(|e| e.to_str()) {
+                Some("gv") => "dot",
+                Some(extension) if formats.contains(&extension) => extension,
+                _ => formats[0],
+            },
+        };
+        match formats.iter().find(|f| **f == format) {
+            Some(format) => Ok(format),
+            None => Err(format!("Unable to write {} as {}, expected {}", self.path, format, formats.join(" or "))),
+        }
+    }
+
+    // gzip compressed when asked or named .gz
+    fn create(&self, compress: bool) -> Result<Created, Box<dyn error::Error>> {
+        let writer: Box<dyn Write> = if self.path == "-" {
+            Box::new(io::stdout())
+        } else {
+            match fs::File::create(&self.path) {
+                Ok(file) => Box::new(io::BufWriter::new(file)),
+                Err(error) => return Err(format!("Unable to create {} : {}", self.path, error).into()),
+            }
+        };
+        let writer = if compress || self.path.ends_with(".gz") {
+            Encoding::Gzip(GzEncoder::new(writer, Compression::default()))
+        } else {
+            Encoding::Plain(writer)
+        };
+        let path = if self.path == "-" { String::from("stdout") } else { self.path.clone() };
+        Ok(Created { path, writer })
+    }
+}
+
+// a created output, closed to report the writes failed when flushing it or
+// finishing its gzip stream
+struct Created {
+    path: String,
+    writer: Encoding,
+}
+
+enum Encoding {
+    Plain(Box<dyn Write>),
+    Gzip(GzEncoder<Box<dyn Write>>),
+}
+
+impl Created {
+    fn close(self) -> Result<(), Error> {
+        let Created { path, writer } = self;
+        let closed = match writer {
+            Encoding::Plain(mut writer) => writer.flush(),
+            Encoding::Gzip(encoder) => encoder.finish().and_then(|mut writer| writer.flush()),
+        };
+        closed.map_err(|error| Error::WriteFailed { path, error })
+    }
+}
+
+impl Write for Created {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.writer {
+            Encoding::Plain(writer) => writer.write(buf),
+            Encoding::Gzip(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.writer {
+            Encoding::Plain(writer) => writer.flush(),
+            Encoding::Gzip(encoder) => encoder.flush(),
+        }
+    }
+}
+
+// a created output with its format
+type Destination = (&'static str, Created);
+
+// the destinations with their format, stdout in the first format by default
+fn outputs(matches: &ArgMatches, formats: &[&'static str]) -> Result<Vec<Destination>, Box<dyn error::Error>> {
+    let stdout = [Output { format: None, path: String::from("-") }];
+    let outputs: Vec<&Output> = match matches.get_many::<Output>("output") {
+        Some(outputs) => outputs.collect(),
+        None => stdout.iter().collect(),
+    };
+
+    // check all the formats before creating any file
+    let formats = outputs.iter()
+        .map(|o| o.format(formats))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut writers = vec![];
+    for (output, format) in outputs.into_iter().zip(formats) {
+        writers.push((format, output.create(matches.get_flag("compress"))?));
+    }
+    Ok(writers)
+}
+
+// the text reports are written the same to every destination
+fn output(matches: &ArgMatches) -> Result<Tee, Box<dyn error::Error>> {
+    let writers = outputs(matches, &["text"])?;
+    Ok(Tee(writers.into_iter().map(|(_, w)| w).collect()))
+}
+
+struct Tee(Vec<Created>);
+
+impl Tee {
+    fn close(self) -> Result<(), Error> {
+        self.0.into_iter().try_for_each(Created::close)
+    }
+}
+
+impl Write for Tee {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for writer in &mut self.0 {
+            writer.write_all(buf)?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        for writer in &mut self.0 {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+// the plain graphs are written while parsing, the options needing the whole
+// graph build it first
+fn can_stream(matches: &ArgMatches) -> bool {
+    !matches.get_flag("merge")
+        && !matches.get_flag("stats")
+        && !matches.contains_id("max-edge-symbols")
+        && !matches.contains_id("weight-by")
+        && !matches.contains_id("top")
+        && !matches.contains_id("per-input")
+        && !matches.get_flag("color-edges")
+        && !matches.contains_id("label-attribute")
+        && !matches.contains_id("nodes-meta")
+        && !matches.get_flag("symbol-kinds")
+        && matches.get_one::<String>("node-labels").map(String::as_str) != Some("detailed")
+        && !matches.contains_id("group")
+        && !matches.contains_id("node-exec")
+        && !matches.contains_id("resolve-against")
+        && !matches.contains_id("providers")
+        && !matches.contains_id("plugins")
+        && !matches.get_flag("follow-deps")
+        && !matches.get_flag("quick")
+        && !matches.contains_id("save")
+        && resolution(matches) != Resolution::ElfOrder
+        && !matches.contains_id("sysroot")
+        && !matches.contains_id("library-path")
+        && matches.get_one::<String>("diagnostics").map(String::as_str) != Some("json")
+}
 
-struct Graph {
-    name: String,
+fn stream_graph_command(matches: &ArgMatches, writers: Vec<Destination>) -> CommandResult {
+    let highlights: Vec<Highlight> = matches.get_many::<Highlight>("highlight")
+        .map(|h| h.cloned().collect())
+        .unwrap_or_default();
+    let files = unique_files(matches.get_many::<String>("file").unwrap_or_default());
+    let mut writer = Tee(writers.into_iter().map(|(_, w)| w).collect());
+    // the comments of DotWriter::metadata
+    for (name, value) in metadata(matches, files.len(), &filters(matches)?) {
+        writeln!(writer, "// {}: {}", name, value.replace('\n', " "))?;
+    }
+    let name = matches.get_one::<String>("name").map_or("", String::as_str);
+    let mut dot = DotStream::new(writer, name)?.highlights(&highlights);
+    if let Some(title) = matches.get_one::<String>("title") {
+        dot = dot.title(title)?;
+    }
+    dot = dot.layout(&layout_attributes(matches), matches.get_one::<f64>("font-size").cloned())?;
+    dot.edge_direction = edge_direction(matches);
+
+    let mut stream = ParseStream::new(dot);
+    if let Some(dir) = matches.get_one::<String>("spill") {
+        stream = stream.spill(Path::new(dir))?;
+    }
+    stream.filters = filters(matches)?;
+    stream.cache = matches.get_one::<String>("cache").map(PathBuf::from);
+    stream.resolution = resolution(matches);
+    stream.mmap = !matches.get_flag("no-mmap");
 
-    nodes: HashMap<usize, NodeProperties>,
-    edges: HashMap<(usize, usize), EdgeProperties>,
+    let progress = Progress::on_terminal(&files);
+    let failures = stream.parse_files(&files, jobs(matches), |f| progress.start(f))?;
+    progress.finish();
+
+    for warning in stream.warnings() {
+        warn!("{}", warning);
+    }
+    for error in &failures {
+        error!("{}", error);
+    }
+    if !failures.is_empty() {
+        error!("{} of {} inputs failed", failures.len(), files.len());
+    }
 
-    clusters: Vec<SubGraph>,
-    strings: string_interner::StringInterner<usize>,
-    
-    // temporary map undefined symbol ->  lib
-    undefined: HashMap<usize, Vec<usize>>,
-    // temporary map defined symbol -> lib 
-    defined: HashMap<usize, Vec<usize>>,
+    let inputs = Inputs { files: files.len(), failed: failures.len(), stats: None };
+    stream.resolve_spilled()?;
+    let (nodes, edges) = stream.counts();
+    let unresolved = stream.unresolved_count();
+    stream.finish()?.finish()?.close()?;
+    inputs.print_summary("", nodes, edges, unresolved, None);
+    Ok(exit_code(inputs.ok()))
 }
 
-impl Graph {
-    fn new(name: &str) -> Self {
-        Self {
-            name: String::from(name),
-            nodes: HashMap::new(),
-            edges: HashMap::new(),
+// the formats of the graph, the one of --format or --render first as the default
+fn graph_formats(matches: &ArgMatches) -> Vec<&'static str> {
+    let mut formats = vec!["dot", "json", "xdot", "svg", "bazel"];
+    let default = matches.get_one::<String>("format").or(matches.get_one::<String>("render"));
+    if let Some(index) = default.and_then(|d| formats.iter().position(|f| f == d)) {
+        let format = formats.remove(index);
+        formats.insert(0, format);
+    }
+    formats
+}
 
-            clusters: Vec::new(),
-            strings: string_interner::StringInterner::new(),
-            
-            undefined: HashMap::new(),
-            defined: HashMap::new(),
-        }
+fn graph_command(matches: &ArgMatches) -> CommandResult {
+    let mut writers = outputs(matches, &graph_formats(matches))?;
+    if can_stream(matches) && writers.iter().all(|(format, _)| *format == "dot") {
+        return stream_graph_command(matches, writers);
     }
+    if matches.contains_id("spill") {
+        return Err("--spill only applies to the plain DOT graphs, without the options needing the whole graph".into());
+    }
+    let files = matches.get_many::<String>("file").unwrap_or_default();
+    let (mut graph, inputs) = parse_inputs(matches, files)?;
+
+    let highlights: Vec<Highlight> = matches.get_many::<Highlight>("highlight")
+        .map(|h| h.cloned().collect())
+        .unwrap_or_default();
 
-    // parse a binary file using object
-    fn parse_binary(&mut self, filename: &str) {
-        let file = fs::File::open(filename);
-        let file = match file {
-            Ok(file) => file,
-            Err(error) => panic!("Unable to open {} : {:?}", filename, error)
+    if matches.get_flag("merge") {
+        info!("Merging");
+        graph.merge();
+    }
+    if let Some(count) = matches.get_one::<usize>("top") {
+        let by = match matches.get_one::<String>("by").map(String::as_str) {
+            Some("in") => Degree::In,
+            Some("out") => Degree::Out,
+            _ => Degree::Total,
         };
+        graph = graph.subgraph(&graph.most_connected(*count, by));
+    }
 
-        let memory = unsafe { memmap::Mmap::map(&file) };
-        let memory = match memory {
-            Ok(memory) => memory,
-            Err(error) => panic!("Unable to mmap {} : {:?}", filename, error)
+    info!("Exporting graph");
+    let label_attributes: Vec<String> = matches.get_many::<String>("label-attribute")
+        .map(|a| a.cloned().collect())
+        .unwrap_or_default();
+    let weight_by = match matches.get_one::<String>("weight-by").map(String::as_str) {
+        Some("size") => Some(EdgeWeight::Size),
+        Some(_) => Some(EdgeWeight::Count),
+        None => None,
+    };
+    // merged edges have no label at all
+    let max_edge_symbols = if matches.get_flag("merge") {
+        None
+    } else {
+        matches.get_one::<usize>("max-edge-symbols").cloned()
+    };
+    let metadata = metadata(matches, inputs.files, &graph.filters);
+    let layout = layout_attributes(matches);
+    let targets = targets(matches)?;
+    let builtin = matches.get_one::<String>("engine").map(String::as_str) == Some("builtin");
+    let write_graph = |graph: &Graph, writer: &mut dyn Write, format: &str| {
+        let dot = DotWriter::new(graph)
+            .metadata(&metadata)
+            .title(matches.get_one::<String>("title").map(String::as_str))
+            .graph_attributes(&layout)
+            .font_size(matches.get_one::<f64>("font-size").cloned())
+            .resolution_colors(matches.get_flag("color-edges"))
+            .max_edge_symbols(max_edge_symbols)
+            .weight_by(weight_by)
+            .symbol_kinds(matches.get_flag("symbol-kinds"))
+            .detailed_labels(matches.get_one::<String>("node-labels").map(String::as_str) == Some("detailed"))
+            .highlights(&highlights)
+            .label_attributes(&label_attributes);
+        match format {
+            "xdot" => write!(writer, "{}", dot.xdot(true)),
+            // laid out without Graphviz, for the machines without it
+            "svg" if builtin => write!(writer, "{}", SvgWriter::new(dot)),
+            "svg" => writer.write_all(&graphviz_svg(&dot.to_string())?),
+            _ => write!(writer, "{}", dot),
+        }
+    };
+    let write_dot = |graph: &Graph, writer: &mut dyn Write| write_graph(graph, writer, "dot");
+    if let Some(dir) = matches.get_one::<String>("split-output") {
+        split_output(&graph, Path::new(dir), &write_dot)?;
+        // the whole graph only when asked for too
+        if !matches.contains_id("output") {
+            writers.clear();
+        }
+    }
+    if let Some(dir) = matches.get_one::<String>("per-input") {
+        per_input(&graph, Path::new(dir), &write_dot)?;
+    }
+    for (format, writer) in &mut writers {
+        match *format {
+            "json" => write!(writer, "{}", JsonWriter::new(&graph).metadata(&metadata))?,
+            "bazel" => write!(writer, "{}", BazelWriter::new(&graph).targets(&targets))?,
+            format => write_graph(&graph, writer, format)?,
+        }
+    }
+    for (_, writer) in writers {
+        writer.close()?;
+    }
+    if matches.contains_id("nodes-meta") {
+        let path = match matches.get_one::<String>("nodes-meta") {
+            Some(path) => path.clone(),
+            None => nodes_meta_path(matches),
         };
+        let mut writer = Output { format: None, path }.create(matches.get_flag("compress"))?;
+        write!(writer, "{}", NodesMetaWriter::new(&graph))?;
+        writer.close()?;
+    }
+    inputs.summarize("", &graph);
+    Ok(exit_code(inputs.ok()))
+}
+
+// the --dpi, --size and --ratio graph attributes
+fn layout_attributes(matches: &ArgMatches) -> Vec<(String, String)> {
+    ["dpi", "size", "ratio"].iter()
+        .filter_map(|name| Some((name.to_string(), matches.get_one::<String>(name)?.clone())))
+        .collect()
+}
+
+// a positive number, as the DOT attributes take them
+fn positive_number(value: &str) -> Result<String, String> {
+    match value.parse::<f64>() {
+        Ok(number) if number > 0.0 => Ok(value.to_string()),
+        _ => Err(format!("{} is not a positive number", value)),
+    }
+}
+
+// WIDTH,HEIGHT in inches, or a single number for both, ! to scale the
+// drawing up to that size
+fn drawing_size(value: &str) -> Result<String, String> {
+    let sizes = value.strip_suffix('!').unwrap_or(value);
+    if sizes.split(',').count() <= 2 && sizes.split(',').all(|s| positive_number(s).is_ok()) {
+        Ok(value.to_string())
+    } else {
+        Err(format!("{} is not WIDTH,HEIGHT in inches", value))
+    }
+}
+
+fn ratio(value: &str) -> Result<String, String> {
+    match value {
+        "fill" | "compress" | "expand" | "auto" => Ok(value.to_string()),
+        _ => positive_number(value).map_err(|_| format!("{} is not fill, compress, expand, auto or a number", value)),
+    }
+}
+
+// the tool version, command line, time, inputs count and filters heading the
+// graphs, none with --no-metadata
+fn metadata(matches: &ArgMatches, inputs: usize, filters: &Filters) -> Vec<(String, String)> {
+    if matches.get_flag("no-metadata") {
+        return vec![];
+    }
+    // quoted when needed to paste it back in a shell
+    let command: Vec<String> = env::args()
+        .map(|a| if !a.is_empty() && a.chars().all(|c| c.is_ascii_alphanumeric() || "-_./:=,+@%".contains(c)) { a } else { format!("'{}'", a.replace('\'', "'\\''")) })
+        .collect();
+    // SOURCE_DATE_EPOCH for the reproducible builds
+    let seconds = env::var("SOURCE_DATE_EPOCH").ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or_else(|| SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map_or(0, |d| d.as_secs()));
+    vec![
+        (String::from("tool"), format!("symbols-graph {}", env!("CARGO_PKG_VERSION"))),
+        (String::from("command"), command.join(" ")),
+        (String::from("timestamp"), utc_time(seconds)),
+        (String::from("inputs"), inputs.to_string()),
+        (String::from("filters"), describe_filters(filters)),
+    ]
+}
+
+// the seconds since the epoch as 2024-01-31T12:00:00Z
+fn utc_time(seconds: u64) -> String {
+    let (days, time) = (seconds / 86400, seconds % 86400);
+    // the civil date of a day count, from Howard Hinnant's algorithm
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, time / 3600, time / 60 % 60, time % 60)
+}
+
+// the filters as their options, the flags only when set
+fn describe_filters(filters: &Filters) -> String {
+    let joined = |values: &[String]| values.join(",");
+    let mut described = vec![];
+    let mut flag = |name: &str, value: bool| if value {
+        described.push(name.to_string());
+    };
+    flag("ignore-system", filters.ignore_system);
+    flag("keep-reserved", filters.keep_reserved);
+    flag("ignore-case", filters.ignore_case);
+    flag("no-weak", filters.no_weak);
+    flag("forwarders=final", filters.follow_forwarders);
+    flag("go-runtime", filters.go_runtime);
+    flag("go-packages", filters.go_packages);
+    flag("quick", filters.needed_only);
+    flag("hash=sha256", filters.sha256);
+    if !filters.system_dirs.is_empty() {
+        let dirs: Vec<String> = filters.system_dirs.iter().map(|d| d.display().to_string()).collect();
+        described.push(format!("system-dirs={}", joined(&dirs)));
+    }
+    match filters.markers.is_empty() {
+        true => described.push(String::from("drop-markers=none")),
+        false => described.push(format!("drop-markers={}", joined(&filters.markers))),
+    }
+    if let Some(only) = filters.only {
+        described.push(format!("only={}", if only == SymbolKind::Data { "data" } else { "functions" }));
+    }
+    if let Some(visibility) = filters.min_visibility {
+        described.push(format!("min-visibility={:?}", visibility).to_lowercase());
+    }
+    if !filters.special_exports.is_empty() {
+        let special: Vec<String> = filters.special_exports.iter().map(|s| s.name().to_string()).collect();
+        described.push(format!("special-exports={}", joined(&special)));
+    }
+    if !filters.strip_prefixes.is_empty() {
+        described.push(format!("strip-prefix={}", joined(&filters.strip_prefixes)));
+    }
+    if !filters.aliases.is_empty() {
+        described.push(format!("aliases={}", filters.aliases.len()));
+    }
+    described.join(" ")
+}
 
-        // parse the mapped file, borrowed by memory
-        let object_file = object::File::parse(&*memory);
-        if let Err(error) = object_file {
-            eprintln!("Unable to parse {} : {:?}", filename, error);
-            return
+// write dir/index.dot with a node per cluster, and a DOT file per cluster with
+// its nodes and the edges between them
+fn split_output<F>(graph: &Graph, dir: &Path, write_dot: &F) -> Result<(), Box<dyn error::Error>>
+    where F: Fn(&Graph, &mut dyn Write) -> io::Result<()>
+{
+    let clusters: Vec<(&str, BTreeSet<u32>)> = graph.clusters().collect();
+    if clusters.is_empty() {
+        return Err("--split-output needs the groups made by --group, none matched".into());
+    }
+    fs::create_dir_all(dir).map_err(|e| format!("Unable to create {} : {}", dir.display(), e))?;
+
+    let mut components = graph.subgraph(&graph.nodes().map(|(n, _)| n).collect());
+    components.aggregate();
+    write_dot_file(&dir.join("index.dot"), &components, write_dot)?;
+    for (name, nodes) in &clusters {
+        write_dot_file(&dir.join(format!("{}.dot", file_name(name))), &graph.subgraph(nodes), write_dot)?;
+    }
+    info!("Wrote {} clusters to {}", clusters.len(), dir.display());
+    Ok(())
+}
+
+// write a DOT file per node of the graph into dir, with the node and the nodes
+// it depends on directly
+fn per_input<F>(graph: &Graph, dir: &Path, write_dot: &F) -> Result<(), Box<dyn error::Error>>
+    where F: Fn(&Graph, &mut dyn Write) -> io::Result<()>
+{
+    fs::create_dir_all(dir).map_err(|e| format!("Unable to create {} : {}", dir.display(), e))?;
+    let mut nodes: Vec<(&str, u32)> = graph.nodes().filter_map(|(n, _)| Some((graph.label(n)?, n))).collect();
+    nodes.sort();
+    // the inputs of the same label are numbered
+    let mut names: HashMap<String, usize> = HashMap::new();
+    for (label, node) in &nodes {
+        let mut name = file_name(label);
+        let count = names.entry(name.clone()).or_default();
+        *count += 1;
+        if *count > 1 {
+            name = format!("{}-{}", name, count);
         }
-        let object_file = object_file.unwrap();
+        let focused: BTreeSet<u32> = graph.dependencies_of(*node).into_iter().chain(Some(*node)).collect();
+        write_dot_file(&dir.join(format!("{}.dot", name)), &graph.subgraph(&focused), write_dot)?;
+    }
+    info!("Wrote {} inputs to {}", nodes.len(), dir.display());
+    Ok(())
+}
+
+fn write_dot_file<F>(path: &Path, graph: &Graph, write_dot: &F) -> Result<(), Box<dyn error::Error>>
+    where F: Fn(&Graph, &mut dyn Write) -> io::Result<()>
+{
+    fs::File::create(path)
+        .and_then(|file| {
+            let mut writer = io::BufWriter::new(file);
+            write_dot(graph, &mut writer)?;
+            writer.flush()
+        })
+        .map_err(|e| format!("Unable to write {} : {}", path.display(), e).into())
+}
+
+// the SVG drawn by the dot command of Graphviz from the DOT graph
+fn graphviz_svg(dot: &str) -> io::Result<Vec<u8>> {
+    let unavailable = |error: io::Error| io::Error::new(error.kind(), format!("Unable to run dot, install Graphviz or use --engine builtin : {}", error));
+    let mut child = process::Command::new("dot")
+        .arg("-Tsvg")
+        .stdin(process::Stdio::piped())
+        .stdout(process::Stdio::piped())
+        .spawn()
+        .map_err(unavailable)?;
+    let mut stdin = child.stdin.take().expect("piped stdin");
+    // written while dot writes, large graphs fill both pipes
+    let (output, written) = thread::scope(|scope| {
+        let input = scope.spawn(move || stdin.write_all(dot.as_bytes()));
+        (child.wait_with_output(), input.join().expect("dot input thread"))
+    });
+    let output = output?;
+    // its own errors are on stderr
+    if !output.status.success() {
+        return Err(io::Error::other(format!("dot failed with {}", output.status)));
+    }
+    written?;
+    Ok(output.stdout)
+}
+
+// a file name whatever the group or node name
+fn file_name(name: &str) -> String {
+    name.chars().map(|c| if c.is_ascii_alphanumeric() || "-_.".contains(c) { c } else { '_' }).collect()
+}
+
+// nodes-meta.json in the directory of the first output file, else the current one
+fn nodes_meta_path(matches: &ArgMatches) -> String {
+    let directory = matches.get_many::<Output>("output")
+        .and_then(|mut outputs| outputs.find(|o| o.path != "-"))
+        .and_then(|o| Path::new(&o.path).parent().map(Path::to_path_buf))
+        .unwrap_or_default();
+    directory.join("nodes-meta.json").to_string_lossy().into_owned()
+}
 
-        let filename = match self.mangle_as_valid_dot_name(filename) {
-            Some(v) => v,
-            None => return,
+fn analyze_command(matches: &ArgMatches) -> CommandResult {
+    let mut writers = outputs(matches, &["text", "sarif"])?;
+    let files = matches.get_many::<String>("file").unwrap_or_default();
+    let (graph, inputs) = parse_inputs(matches, files)?;
+
+    let mut analyzers = Analyzers::default();
+    if let Some(banned) = matches.get_one::<String>("banned") {
+        let content = match fs::read_to_string(banned) {
+            Ok(content) => content,
+            Err(error) => return Err(format!("Unable to read {} : {}", banned, error).into()),
+        };
+        // one symbol per line, # starts a comment
+        let symbols = content.lines()
+            .map(|l| l.split('#').next().unwrap_or_default().trim())
+            .filter(|l| !l.is_empty())
+            .map(String::from);
+        analyzers.register(Box::new(BannedSymbols::new(symbols)));
+    }
+    if let Some(allowlist) = matches.get_one::<String>("allowlist") {
+        let content = match fs::read_to_string(allowlist) {
+            Ok(content) => content,
+            Err(error) => return Err(format!("Unable to read {} : {}", allowlist, error).into()),
         };
+        match ExportAllowlist::parse(&content) {
+            Ok(allowlist) => analyzers.register(Box::new(allowlist)),
+            Err(error) => return Err(format!("Unable to parse {} : {}", allowlist, error).into()),
+        }
+    }
+    if matches.get_flag("underlinking") {
+        analyzers.register(Box::new(Underlinking));
+    }
+
+    let results = analyzers.run(&graph);
+    let failed = results.iter().flat_map(|(_, findings)| findings).any(|f| f.severity >= Severity::Warning);
+    for (format, writer) in &mut writers {
+        if *format == "sarif" {
+            write!(writer, "{}", SarifWriter::new(&graph, &results))?;
+            continue;
+        }
+        for (name, findings) in &results {
+            writeln!(writer, "{}: {}", name, findings.len())?;
+            for finding in findings {
+                writeln!(writer, "    {}", finding.message)?;
+            }
+        }
+    }
+    for (_, writer) in writers {
+        writer.close()?;
+    }
+
+    inputs.summarize("", &graph);
+    if failed_conditions(matches, &Metrics::new(&graph)) || matches.get_flag("check") && failed {
+        return Ok(EXIT_CHECK_FAILED);
+    }
+    Ok(exit_code(inputs.ok()))
+}
+
+fn query_command(matches: &ArgMatches) -> CommandResult {
+    let mut writer = output(matches)?;
+    let files = matches.get_many::<String>("file").unwrap_or_default();
+    let (graph, inputs) = parse_inputs(matches, files)?;
+
+    let ends: Vec<&String> = matches.get_many::<String>("path-between").unwrap_or_default().collect();
+    let results = if let Some(name) = matches.get_one::<String>("dependencies-of") {
+        graph.find_node(name).map(|n| graph.dependencies_of(n)).unwrap_or_default()
+    } else if let Some(name) = matches.get_one::<String>("dependents-of") {
+        graph.find_node(name).map(|n| graph.dependents_of(n)).unwrap_or_default()
+    } else if let Some(symbol) = matches.get_one::<String>("providers-of") {
+        graph.providers_of_symbol(symbol)
+    } else if let [from, to] = ends.as_slice() {
+        graph.path_between(from, to).unwrap_or_default()
+    } else {
+        vec![]
+    };
+
+    let mut labels: Vec<&str> = results.iter().filter_map(|n| graph.label(*n)).collect();
+    // the chain is listed in order
+    if ends.is_empty() {
+        labels.sort();
+    }
+    for label in labels {
+        writeln!(writer, "{}", label)?;
+    }
+    writer.close()?;
+    inputs.summarize("", &graph);
+    Ok(exit_code(inputs.ok()))
+}
+
+fn diff_command(matches: &ArgMatches) -> CommandResult {
+    let mut writer = output(matches)?;
+    let (old, old_inputs) = parse_inputs(matches, matches.get_many::<String>("old").unwrap_or_default())?;
+    let (new, new_inputs) = parse_inputs(matches, matches.get_many::<String>("file").unwrap_or_default())?;
 
-        let filename = self.strings.get_or_intern(filename);
-        let mut properties = NodeProperties { symbols: vec![] };
-        
-        // add the exported symbols to the graph
-        if let Ok(symbols) = object_file.exports() {
-            for sym in symbols {
-                self.insert_exported(&mut properties, filename, sym.name());
+    // nodes are compared by label, paths differ between two build trees
+    let (old_nodes, old_edges) = (old.node_labels(), old.edge_labels());
+    let (new_nodes, new_edges) = (new.node_labels(), new.edge_labels());
+
+    for n in old_nodes.difference(&new_nodes) {
+        writeln!(writer, "- {}", n)?;
+    }
+    for n in new_nodes.difference(&old_nodes) {
+        writeln!(writer, "+ {}", n)?;
+    }
+    for (n1, n2) in old_edges.difference(&new_edges) {
+        let (from, to) = new.edge_direction.orient(n1, n2);
+        writeln!(writer, "- {} -> {}", from, to)?;
+    }
+    for (n1, n2) in new_edges.difference(&old_edges) {
+        let (from, to) = new.edge_direction.orient(n1, n2);
+        writeln!(writer, "+ {} -> {}", from, to)?;
+    }
+    writer.close()?;
+    old_inputs.summarize("old: ", &old);
+    new_inputs.summarize("new: ", &new);
+    Ok(exit_code(old_inputs.ok() && new_inputs.ok()))
+}
+
+// compare the exports of two versions of a library, and the consumers
+// importing the removed ones
+fn abidiff_command(matches: &ArgMatches) -> CommandResult {
+    let mut writer = output(matches)?;
+    let files: Vec<&String> = matches.get_many::<String>("file").unwrap_or_default().collect();
+    let (old, old_inputs) = parse_inputs(matches, files[..1].iter().cloned())?;
+    let (new, new_inputs) = parse_inputs(matches, files[1..].iter().cloned())?;
+
+    let mut removed = vec![];
+    for change in abi_changes(&old, &new) {
+        match change {
+            AbiChange::Removed(export) => {
+                writeln!(writer, "- {}", export)?;
+                removed.push(export.symbol);
             }
+            AbiChange::Changed(export, new_export) => writeln!(writer, "~ {} -> {}", export, new_export)?,
+            AbiChange::Added(export) => writeln!(writer, "+ {}", export)?,
         }
+    }
 
-        // add the imported symbols to the graph (in case of plain object files)
-        if let Ok(symbols) = object_file.imports() {
-            for sym in symbols {
-                self.insert_imported(&mut properties, filename, sym.name());
+    // the consumers resolved against the old version
+    let mut ok = old_inputs.ok() && new_inputs.ok();
+    if let Some(consumers) = matches.get_many::<String>("consumers") {
+        let (graph, inputs) = parse_inputs(matches, files[..1].iter().cloned().chain(consumers))?;
+        if let Some(library) = graph.find_node(files[0]) {
+            for (consumer, symbol) in broken_imports(&graph, library, &removed) {
+                writeln!(writer, "! {} imports the removed {}", consumer, symbol)?;
             }
         }
+        inputs.summarize("consumers: ", &graph);
+        ok &= inputs.ok();
+    }
+    writer.close()?;
+    old_inputs.summarize("old: ", &old);
+    new_inputs.summarize("new: ", &new);
+    Ok(exit_code(ok))
+}
 
-        self.nodes.insert(filename, properties);
+fn report_command(matches: &ArgMatches) -> CommandResult {
+    let mut writer = output(matches)?;
+    let files = matches.get_many::<String>("file").unwrap_or_default();
+    let (graph, inputs) = parse_inputs(matches, files)?;
+    let separator = match matches.get_one::<String>("format").map(String::as_str) {
+        Some("tsv") => Some("\t"),
+        Some("csv") => Some(","),
+        _ => None,
+    };
+    match matches.get_one::<String>("report").map(String::as_str) {
+        Some("providers") => report_providers(&graph, separator, &mut writer)?,
+        Some("metrics") => report_metrics(&graph, separator, &mut writer)?,
+        _ => report_summary(&graph, &mut writer)?,
     }
+    writer.close()?;
+    inputs.summarize("", &graph);
+    Ok(exit_code(inputs.ok()))
+}
 
-    fn insert_exported(&mut self, properties: &mut NodeProperties, filename: usize, exported_symbol: &[u8]) {
-        let symbol_name = str::from_utf8(exported_symbol).unwrap();
+fn report_summary(graph: &Graph, writer: &mut dyn Write) -> io::Result<()> {
 
-        let symbol_name = match self.mangle_as_valid_dot_name(symbol_name) {
-            Some(v) => v,
-            None => return,
-        };
+    let mut nodes: Vec<(&str, u32, usize)> = graph.nodes()
+        .filter_map(|(n, p)| graph.label(n).map(|l| (l, n, p.symbols().len())))
+        .collect();
+    nodes.sort();
+
+    writeln!(writer, "{} libraries, {} dependencies", nodes.len(), graph.edges().count())?;
+    for (label, n, exports) in nodes {
+        writeln!(writer, "{} : {} exports, {} imports, {} dependencies, {} dependents",
+            label,
+            exports,
+            graph.imports_count(n),
+            graph.dependencies_of(n).len(),
+            graph.dependents_of(n).len())?;
+    }
+    Ok(())
+}
 
-        let symbol_name = self.strings.get_or_intern(symbol_name);
+// a row per library with its degrees, its symbol counts and its file size,
+// to trend them across builds
+fn report_metrics(graph: &Graph, separator: Option<&str>, writer: &mut dyn Write) -> io::Result<()> {
+    let mut rows = vec![["library", "in_degree", "out_degree", "exports", "imports", "unresolved", "size"].iter().map(|c| c.to_string()).collect::<Vec<_>>()];
+    for metrics in NodeMetrics::all(graph) {
+        rows.push(vec![
+            metrics.label.to_string(),
+            metrics.in_degree.to_string(),
+            metrics.out_degree.to_string(),
+            metrics.exports.to_string(),
+            metrics.imports.to_string(),
+            metrics.unresolved.to_string(),
+            metrics.size.map(|s| s.to_string()).unwrap_or_default(),
+        ]);
+    }
 
-        // render in the label
-        properties.symbols.push(symbol_name);
+    if let Some(separator) = separator {
+        for row in rows {
+            let fields: Vec<&str> = row.iter().map(String::as_str).collect();
+            writeln!(writer, "{}", separated(&fields, separator))?;
+        }
+        return Ok(());
+    }
+    // aligned columns, the numbers on the right
+    let widths: Vec<usize> = (0..rows[0].len()).map(|i| rows.iter().map(|r| r[i].chars().count()).max().unwrap_or(0)).collect();
+    for row in rows {
+        let mut line = format!("{:<1$}", row[0], widths[0]);
+        for (field, width) in row.iter().zip(&widths).skip(1) {
+            line += &format!("  {:>1$}", field, width);
+        }
+        writeln!(writer, "{}", line)?;
+    }
+    Ok(())
+}
 
-        // store for later resolution
-        if let Some(libs) = self.defined.get_mut(&filename) {
-            libs.push(filename);
+// the fields joined by separator, quoted as needed in the CSV rows
+fn separated(fields: &[&str], separator: &str) -> String {
+    let quoted: Vec<String> = fields.iter()
+        .map(|f| if separator == "," && f.contains([',', '"', '\n']) {
+            format!("\"{}\"", f.replace('"', "\"\""))
         } else {
-            self.defined.insert(symbol_name, vec![filename]);
-        }
+            f.to_string()
+        })
+        .collect();
+    quoted.join(separator)
+}
 
-        // cleanup undefined if needed
-        if let Some((_, libs)) = self.undefined.remove_entry(&symbol_name) {
-            for lib in libs.iter() {
-                let edge = (*lib, filename);
-                if let Some(properties) = self.edges.get_mut(&edge) {
-                    properties.symbols.push(symbol_name);
-                } else {
-                    self.edges.insert(edge, EdgeProperties { symbols: vec![symbol_name]});
-                }
-            }
+// whether one of the --fail-if conditions holds, logging them
+fn failed_conditions(matches: &ArgMatches, metrics: &Metrics) -> bool {
+    let mut failed = false;
+    for condition in matches.get_many::<Condition>("fail-if").unwrap_or_default() {
+        if condition.holds(metrics) {
+            error!("Failed: {} is {} {} {}", condition.metric, metrics.get(&condition.metric), condition.operator, condition.value);
+            failed = true;
         }
     }
+    failed
+}
 
-    fn insert_imported(&mut self, properties: &mut NodeProperties, filename: usize, imported_symbol: &[u8]) {
-        let symbol_name = str::from_utf8(imported_symbol).unwrap();
+fn fail_if_arg() -> Arg {
+    Arg::new("fail-if")
+        .long("fail-if")
+        .num_args(1)
+        .value_name("CONDITION")
+        .value_parser(Condition::parse)
+        .help(format!("Exit with status 4 when CONDITION holds, eg. 'unresolved>0', on one of the metrics {}", Metrics::NAMES.join(", ")))
+        .action(ArgAction::Append)
+        .required(false)
+}
 
-        let symbol_name = match self.mangle_as_valid_dot_name(symbol_name) {
-            Some(v) => v,
-            None => return,
-        };
+fn stats_command(matches: &ArgMatches) -> CommandResult {
+    let mut writer = output(matches)?;
+    let files = matches.get_many::<String>("file").unwrap_or_default();
+    let (graph, inputs) = parse_inputs(matches, files)?;
 
-        let symbol_name = self.strings.get_or_intern(symbol_name);
-
-        // lookup on existing libs
-        if let Some(libs) = self.defined.get(&symbol_name) {
-            // resolve to previously decoded libs 
-            for lib in libs.iter() {
-                let edge = (filename, *lib);
-                if let Some(properties) = self.edges.get_mut(&edge) {
-                    properties.symbols.push(symbol_name);
-                } else {
-                    self.edges.insert(edge, EdgeProperties { symbols: vec![symbol_name]});
-                }
+    let metrics = Metrics::new(&graph);
+    writeln!(writer, "nodes: {}", metrics.nodes)?;
+    writeln!(writer, "edges: {}", metrics.edges)?;
+    writeln!(writer, "resolved imports: {}", metrics.resolved)?;
+    writeln!(writer, "unresolved imports: {}", metrics.unresolved)?;
+    writeln!(writer, "average degree: {:.2}", metrics.average_degree)?;
+    match metrics.max_degree {
+        Some((degree, label)) => writeln!(writer, "max degree: {} ({})", degree, label)?,
+        None => writeln!(writer, "max degree: 0")?,
+    }
+    writeln!(writer, "cycles: {}", metrics.cycles)?;
+    writeln!(writer, "strongly connected components: {}", metrics.strong)?;
+    writeln!(writer, "connected components: {}", metrics.components)?;
+    writer.close()?;
+    inputs.summarize("", &graph);
+    if failed_conditions(matches, &metrics) {
+        return Ok(EXIT_CHECK_FAILED);
+    }
+    Ok(exit_code(inputs.ok()))
+}
+
+// a row per exported symbol and library with the libraries resolved to it,
+// sorted by symbol
+fn report_providers(graph: &Graph, separator: Option<&str>, writer: &mut dyn Write) -> io::Result<()> {
+    if let Some(separator) = separator {
+        writeln!(writer, "{}", separated(&["symbol", "provider", "importers"], separator))?;
+    }
+    for Provider { symbol, provider, importers: libs } in Provider::all(graph) {
+        if let Some(separator) = separator {
+            let columns: Vec<&str> = [symbol, provider].iter().cloned().chain(libs).collect();
+            writeln!(writer, "{}", separated(&columns, separator))?;
+        } else if libs.is_empty() {
+            writeln!(writer, "{} : {}", symbol, provider)?;
+        } else {
+            writeln!(writer, "{} : {} <- {}", symbol, provider, libs.join(", "))?;
+        }
+    }
+    Ok(())
+}
+
+// suggest hiding the exports no other input imports, with the build changes
+// keeping the imported ones public
+fn visibility_command(matches: &ArgMatches) -> CommandResult {
+    let mut writer = output(matches)?;
+    let files = matches.get_many::<String>("file").unwrap_or_default();
+    let (graph, inputs) = parse_inputs(matches, files)?;
+
+    for HiddenExports { label, unused, used, pe, .. } in HiddenExports::all(&graph) {
+        writeln!(writer, "{} : {} of {} exports are not imported by the other inputs", label, unused.len(), unused.len() + used.len())?;
+        if pe {
+            writeln!(writer, "    export only these with __declspec(dllexport), or list them in the .def file:")?;
+            writeln!(writer, "    EXPORTS")?;
+            for symbol in &used {
+                writeln!(writer, "        {}", symbol)?;
             }
         } else {
-            // will be resolved later, store it
-            if let Some(libs) = self.undefined.get_mut(&symbol_name) {
-                libs.push(filename);
-            } else {
-                self.undefined.insert(symbol_name, vec![filename]);
+            writeln!(writer, "    compile with -fvisibility=hidden and link with -Wl,--version-script of:")?;
+            writeln!(writer, "    {{")?;
+            if !used.is_empty() {
+                writeln!(writer, "      global:")?;
+                for symbol in &used {
+                    writeln!(writer, "        {};", symbol)?;
+                }
             }
+            writeln!(writer, "      local: *;")?;
+            writeln!(writer, "    }};")?;
         }
     }
+    writer.close()?;
+    inputs.summarize("", &graph);
+    Ok(exit_code(inputs.ok()))
+}
 
-    fn mangle_as_valid_dot_name(&self, v: &str) -> Option<String> {
-        // blacklisted symbols
-        let v = match &v[0..] {
-            "_GLOBAL_OFFSET_TABLE_" => return None,
-            "" => return None,
-            _ => v,
-        };
+// the --targets of the libraries: a library and its target per line, #
+// starts a comment
+fn targets(matches: &ArgMatches) -> Result<HashMap<String, String>, Box<dyn error::Error>> {
+    let mut targets = HashMap::new();
+    let path = match matches.get_one::<String>("targets") {
+        Some(path) => path,
+        None => return Ok(targets),
+    };
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(error) => return Err(format!("Unable to read {} : {}", path, error).into()),
+    };
+    for (number, line) in content.lines().enumerate() {
+        let fields: Vec<&str> = line.split('#').next().unwrap_or_default().split_whitespace().collect();
+        match fields.as_slice() {
+            [] => {}
+            [library, target] => {
+                targets.insert(library.to_string(), target.to_string());
+            }
+            _ => return Err(format!("Unable to parse {} : line {}: expected LIBRARY TARGET", path, number + 1).into()),
+        }
+    }
+    Ok(targets)
+}
 
-        // .LC0 and .LC1 are used for constants
-        if v.starts_with(".LC") {
-            return None;
+// the target_link_libraries changes fixing the under- and overlinking of
+// the CMake targets, as a patch of their link lines
+fn cmake_command(matches: &ArgMatches) -> CommandResult {
+    let mut writer = output(matches)?;
+    let targets = targets(matches)?;
+    let files = matches.get_many::<String>("file").unwrap_or_default();
+    let (graph, inputs) = parse_inputs(matches, files)?;
+    for LinkChanges { label, target, added, removed, .. } in LinkChanges::all(&graph, &targets) {
+        writeln!(writer, "@@ {} ({})", target, label)?;
+        for (library, total, first) in added {
+            writeln!(writer, "+target_link_libraries({} PRIVATE {})  # {} symbol{}, eg. {}", target, library, total,
+                     if total > 1 { "s" } else { "" }, first)?;
         }
-        // _ prefixed symbols are compiler reserved
-        if v.starts_with('_') {
-            return None;
+        for library in removed {
+            writeln!(writer, "-target_link_libraries({} PRIVATE {})  # no symbol imported", target, library)?;
         }
-
-        // escape file names: return basename
-        let dot = if v.ends_with(".o") {
-            v.len() - 2
-        } else {
-            v.len()
-        };
-        let slash = match v.rfind('/') {
-            Some(index) => index+1,
-            None => 0,
-        };
-
-        // filter invalid dot symbols
-        Some(v[slash..dot].chars()
-            // dot use dash as a edge symbol, translate it
-            .map(|c: char| if c == '-' { '_' } else { c })
-            // dot use dot as a edge symbol, translate it
-            .map(|c: char| if c == '.' { '_' } else { c })
-            .collect::<String>())
     }
+    writer.close()?;
+    inputs.summarize("", &graph);
+    Ok(exit_code(inputs.ok()))
+}
 
-    // remove all labels information from edges
-    fn merge(&mut self) {
-        for e in self.edges.values_mut() {
-            e.symbols.clear();
+// list what the subcommand would read and write, without parsing
+fn dry_run(matches: &ArgMatches) -> CommandResult {
+    let mut stdout = io::stdout();
+    for (file, skipped) in discover(matches)? {
+        match skipped {
+            Some(reason) => writeln!(stdout, "skip {} ({})", file, reason)?,
+            None => writeln!(stdout, "parse {}", file)?,
         }
     }
+    match matches.get_many::<Output>("output") {
+        Some(outputs) => for output in outputs {
+            match output.path.as_str() {
+                "-" => writeln!(stdout, "write stdout")?,
+                path => writeln!(stdout, "write {}", path)?,
+            }
+        },
+        None => writeln!(stdout, "write stdout")?,
+    }
+    Ok(0)
 }
 
-impl Display for Graph {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        writeln!(f, "digraph {} {{", self.name)?;
+// a file the subcommand would read, with the reason it is skipped if it is
+type Discovered = (String, Option<String>);
 
-        for c in &self.clusters {
-            if let Some(label) = self.strings.resolve(c.name) {
-                writeln!(f, "    subgraph {} {{", label)?;
-            } else {
-                writeln!(f, "    subgraph {{")?;
+// the files the subcommand would read, the skipped ones included
+fn discover(matches: &ArgMatches) -> Result<Vec<Discovered>, Box<dyn error::Error>> {
+    let filters = filters(matches)?;
+    let jobs = jobs(matches);
+    let mut discovered = vec![];
+    let mut seen = HashSet::new();
+    for id in ["old", "file"].iter() {
+        let files = match matches.try_get_many::<String>(id) {
+            Ok(Some(values)) => unique_files(values),
+            _ => continue,
+        };
+        for file in &files {
+            match filters.skip_reason(file) {
+                Some(reason) => discovered.push((file.clone(), Some(reason.to_string()))),
+                None if fs::metadata(file).is_err() => discovered.push((file.clone(), Some(String::from("not found")))),
+                None => continue,
             }
+            seen.insert(file.clone());
+        }
 
-            for (idx, _) in c.nodes.iter() {
-                if let Some(label) = self.strings.resolve(*idx) {
-                    writeln!(f, "        n{} [label=\"{}\"]", idx, label)?;
-                } else {
-                    writeln!(f, "        n{}", idx)?;
-                }
+        // the same discovery as parse_inputs, reading only the needed
+        // libraries of each file
+        let mut graph = Graph::new("");
+        graph.filters = Filters { needed_only: true, ..filters.clone() };
+        graph.mmap = !matches.get_flag("no-mmap");
+        graph.edge_symbols = false;
+        let sources = parse_sources(matches, &mut graph, &files, jobs)?;
+        for (node, _) in &graph.stats().files {
+            if let Some(path) = graph.path(*node).filter(|p| seen.insert(p.to_string())) {
+                discovered.push((path.to_string(), None));
+            }
+        }
+        for error in &sources.failures {
+            if seen.insert(error.path().to_string()) {
+                discovered.push((error.path().to_string(), Some(error.to_string())));
             }
+        }
+        for library in sources.libraries.iter().filter(|l| seen.insert(l.to_string())) {
+            discovered.push((library.clone(), None));
+        }
+    }
+    Ok(discovered)
+}
+
+// the export, import and dependency counts of each library across the
+// snapshots, in the given order
+fn churn_command(matches: &ArgMatches) -> CommandResult {
+    let mut writer = output(matches)?;
+    let files: Vec<&String> = matches.get_many::<String>("file").unwrap_or_default().collect();
+    let mut contents = vec![];
+    for file in &files {
+        contents.push(fs::read_to_string(file).map_err(|e| format!("Unable to read {} : {}", file, e))?);
+    }
+    churn::churn(&files, &contents, &mut writer)??;
+    writer.close()?;
+    Ok(0)
+}
+
+// the symbols exported by every library of the sysroots, for --providers
+fn index_command(matches: &ArgMatches) -> CommandResult {
+    let dirs: Vec<PathBuf> = matches.get_many::<String>("file").unwrap_or_default().map(PathBuf::from).collect();
+    let libraries = sysroot_libraries(&dirs)?;
+    let mut graph = Graph::new("");
+    // the system libraries are what is looked for
+    graph.filters = Filters { ignore_system: false, ..filters(matches)? };
+    graph.cache = matches.get_one::<String>("cache").map(PathBuf::from);
+    graph.mmap = !matches.get_flag("no-mmap");
+    graph.edge_symbols = false;
+
+    info!("Indexing the {} libraries of {}", libraries.len(), dirs.iter().map(|d| d.display().to_string()).collect::<Vec<_>>().join(", "));
+    let progress = Progress::on_terminal(&libraries);
+    // the sysroot holds linker scripts and other non binary files
+    for error in graph.parse_files(&libraries, jobs(matches), |f| progress.start(f)) {
+        debug!("Skipped {}", error);
+    }
+    progress.finish();
+
+    let mut writer = output(matches)?;
+    graph.save_index(&mut writer)?;
+    writer.close()?;
+    info!("Indexed {} libraries", graph.nodes().count());
+    Ok(0)
+}
+
+// the saved graph with the changed files parsed again, written in place
+// unless -o names another file
+fn update_command(matches: &ArgMatches) -> CommandResult {
+    let saved = matches.get_one::<String>("saved").ok_or("A saved graph is required")?;
+    let files = unique_files(matches.get_many::<String>("file").unwrap_or_default());
+    let mut graph = Graph::new("");
+    graph.filters = filters(matches)?;
+    graph.cache = matches.get_one::<String>("cache").map(PathBuf::from);
+    graph.mmap = !matches.get_flag("no-mmap");
+    graph.resolution = resolution(matches);
 
-            writeln!(f, "    }}")?;
+    info!("Updating {} with {} files", saved, files.len());
+    let progress = Progress::on_terminal(&files);
+    let failures = graph.update(saved, &files, jobs(matches), |f| progress.start(f))?;
+    progress.finish();
+    for error in &failures {
+        error!("{}", error);
+    }
+
+    let destination = matches.get_many::<Output>("output").and_then(|mut o| o.next()).map_or(saved.as_str(), |o| o.path.as_str());
+    if destination == "-" {
+        graph.save(io::stdout().lock())?;
+    } else {
+        // renamed once complete, the saved graph is never left half written
+        let partial = format!("{}.partial", destination);
+        let written = fs::File::create(&partial)
+            .and_then(|file| graph.save(io::BufWriter::new(file)))
+            .and_then(|_| fs::rename(&partial, destination));
+        if let Err(error) = written {
+            let _ = fs::remove_file(&partial);
+            return Err(format!("Unable to save {} : {}", destination, error).into());
         }
+    }
+    let inputs = Inputs { files: files.len(), failed: failures.len(), stats: None };
+    inputs.summarize("", &graph);
+    Ok(exit_code(inputs.ok()))
+}
+
+fn serve_command(matches: &ArgMatches) -> CommandResult {
+    let files = matches.get_many::<String>("file").unwrap_or_default();
+    let (graph, inputs) = parse_inputs(matches, files)?;
+    inputs.summarize("", &graph);
+
+    let address = format!("{}:{}", matches.get_one::<String>("bind").map(String::as_str).unwrap_or("127.0.0.1"),
+                          matches.get_one::<u16>("port").cloned().unwrap_or(8080));
+    match serve::serve(&graph, &address) {
+        Ok(()) => Ok(exit_code(inputs.ok())),
+        Err(error) => Err(format!("Unable to serve on {} : {}", address, error).into()),
+    }
+}
+
+fn tui_command(matches: &ArgMatches) -> CommandResult {
+    if !io::stdout().is_terminal() {
+        return Err("The tui subcommand needs a terminal".into());
+    }
+    let files = matches.get_many::<String>("file").unwrap_or_default();
+    let (graph, inputs) = parse_inputs(matches, files)?;
+
+    tui::explore(&graph)?;
+    inputs.summarize("", &graph);
+    Ok(exit_code(inputs.ok()))
+}
+
+fn run(name: &str, matches: &ArgMatches) -> CommandResult {
+    match name {
+        "graph" => graph_command(matches),
+        "analyze" => analyze_command(matches),
+        "query" => query_command(matches),
+        "diff" => diff_command(matches),
+        "abidiff" => abidiff_command(matches),
+        "report" => report_command(matches),
+        "stats" => stats_command(matches),
+        "visibility" => visibility_command(matches),
+        "cmake" => cmake_command(matches),
+        "churn" => churn_command(matches),
+        "index" => index_command(matches),
+        "update" => update_command(matches),
+        "serve" => serve_command(matches),
+        "tui" => tui_command(matches),
+        _ => unreachable!("unknown subcommand {}", name),
+    }
+}
 
-        for (idx, _) in self.nodes.iter() {
-            if let Some(label) = self.strings.resolve(*idx) {
-                writeln!(f, "    n{} [label=\"{}\"]", idx, label)?;
+// run the subcommand again each time a file it read or a directory it
+// searched changes, until interrupted
+fn watch(name: &str, matches: &ArgMatches) -> CommandResult {
+    // the events name the absolute paths
+    let cwd = env::current_dir()?;
+    loop {
+        // the files are discovered again, the followed libraries may change
+        let mut files: HashSet<PathBuf> = discover(matches)?.into_iter().map(|(f, _)| cwd.join(f)).collect();
+        for id in ["file", "old", "aliases"].iter() {
+            if let Ok(Some(values)) = matches.try_get_many::<String>(id) {
+                files.extend(values.map(|f| cwd.join(f)));
             }
         }
+        // the libraries added to these count too
+        let mut dirs: Vec<PathBuf> = matches.get_many::<(String, String)>("plugins").unwrap_or_default().map(|(d, _)| cwd.join(d)).collect();
+        match matches.get_one::<String>("resolve-against") {
+            Some(sysroot) => dirs.push(cwd.join(sysroot)),
+            None => dirs.extend(library_dirs(matches).unwrap_or_default().iter().map(|d| cwd.join(d))),
+        }
 
-        for ((n1, n2), p) in &self.edges {
-            if p.symbols.len() == 0 {
-                writeln!(f, "    n{} -> n{}", n1, n2)?;
-            } else {
-                for symbol in p.symbols.iter() {
-                    if let Some(label) = self.strings.resolve(*symbol) {
-                        writeln!(f, "    n{} -> n{} [label=\"{}\"]", n1, n2, label)?;
-                    }
-                }
+        // the parent directories are watched, the linkers replace the files
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        let parents: BTreeSet<&Path> = files.iter().filter_map(|f| f.parent()).collect();
+        for (dir, mode) in parents.iter().map(|p| (*p, RecursiveMode::NonRecursive)).chain(dirs.iter().map(|d| (d.as_path(), RecursiveMode::Recursive))) {
+            if let Err(error) = watcher.watch(dir, mode) {
+                warn!("Unable to watch {} : {}", dir.display(), error);
             }
         }
+        let changed = |event: notify::Result<notify::Event>| match event {
+            Ok(event) => !event.kind.is_access() && event.paths.iter().any(|p| files.contains(p) || dirs.iter().any(|d| p.starts_with(d))),
+            Err(error) => {
+                warn!("Unable to watch : {}", error);
+                false
+            }
+        };
+
+        match run(name, matches) {
+            Ok(_) => (),
+            Err(error) => error!("{}", error),
+        }
 
-        writeln!(f, "}}")
+        eprintln!("Watching {} files and {} directories for changes", files.len(), dirs.len());
+        while !changed(events.recv()?) {}
+        // a rebuild writes several files, wait for it to settle
+        while events.recv_timeout(Duration::from_millis(500)).is_ok() {}
+        eprintln!("Regenerating");
+        drop(watcher);
     }
 }
 
-#[derive(Debug)]
-struct NodeProperties {
-    symbols: Vec<usize>,
+// the log records on stderr, stdout is kept for the outputs
+struct Logger {
+    json: bool,
 }
 
-#[derive(Debug)]
-struct EdgeProperties {
-    symbols: Vec<usize>,
+impl Logger {
+    // warnings and errors by default, more with each -v
+    fn init(matches: &ArgMatches) {
+        let level = match matches.get_count("verbose") {
+            0 => log::LevelFilter::Warn,
+            1 => log::LevelFilter::Info,
+            2 => log::LevelFilter::Debug,
+            _ => log::LevelFilter::Trace,
+        };
+        let json = matches.get_one::<String>("log-format").map(String::as_str) == Some("json");
+        if log::set_boxed_logger(Box::new(Logger { json })).is_ok() {
+            log::set_max_level(level);
+        }
+    }
 }
 
-#[derive(Debug)]
-struct SubGraph {
-    name: usize,
-    nodes: HashMap<usize, NodeProperties>
-}
+impl log::Log for Logger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
 
-impl SubGraph {
-    fn new(name: usize) -> Self {
-        Self {
-            name: name,
-            nodes: HashMap::new()
+    fn log(&self, record: &log::Record) {
+        if !self.enabled(record.metadata()) {
+            return;
         }
+        let line = if self.json {
+            format!("{{\"level\":\"{}\",\"target\":{},\"message\":{}}}",
+                    record.level(), json_string(record.target()), json_string(&record.args().to_string()))
+        } else {
+            format!("{}: {}", record.level().as_str().to_lowercase(), record.args())
+        };
+        let _ = writeln!(io::stderr().lock(), "{}", line);
     }
-    
-    fn insert(&mut self, symbol_name: usize) {
-        self.nodes.insert(symbol_name, NodeProperties { symbols: vec![] });
+
+    fn flush(&self) {}
+}
+
+// a quoted and escaped JSON string
+fn json_string(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => quoted += "\\\"",
+            '\\' => quoted += "\\\\",
+            '\n' => quoted += "\\n",
+            c if (c as u32) < 0x20 => quoted += &format!("\\u{:04x}", c as u32),
+            c => quoted.push(c),
+        }
     }
+    quoted.push('"');
+    quoted
 }
 
 fn main() {
     let matches = Command::new("Symbols graph")
         .version("0.1")
         .about("Parse shared objects and compute their internal and external dependencies.")
-        .arg(
-            Arg::new("verbose")
-                .long("verbose")
-                .action(clap::ArgAction::SetTrue)
-                .help("Sets the level of verbosity")
-                .required(false),
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .subcommand(
+            input_args(Command::new("graph").about("Write the dependency graph in the DOT format"))
+                .arg(
+                    Arg::new("merge")
+                        .short('m')
+                        .long("merge")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Generate only one edge between libraries")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("max-edge-symbols")
+                        .long("max-edge-symbols")
+                        .num_args(1)
+                        .value_name("K")
+                        .value_parser(clap::value_parser!(usize))
+                        .help("Generate one edge between libraries listing at most K symbols, 0 keeps only their count")
+                        .action(ArgAction::Set)
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("label-attribute")
+                        .long("label-attribute")
+                        .num_args(1)
+                        .value_name("NAME")
+                        .help("Add the NAME node attribute to the node labels: size, arch, endianness, kind, soname, needed, interpreter, build_id, timestamp, pdb_id, hardening, sha256, external or exec")
+                        .action(ArgAction::Append)
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("weight-by")
+                        .long("weight-by")
+                        .num_args(1)
+                        .value_name("WEIGHT")
+                        .value_parser(["count", "size"])
+                        .help("Draw the edges thicker as they import more symbols or more bytes of code and data")
+                        .action(ArgAction::Set)
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("top")
+                        .long("top")
+                        .num_args(1)
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(usize))
+                        .help("Keep only the N most connected nodes and the edges between them, for an overview")
+                        .action(ArgAction::Set)
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("by")
+                        .long("by")
+                        .num_args(1)
+                        .value_name("DEGREE")
+                        .value_parser(["in", "out", "total"])
+                        .default_value("total")
+                        .help("Rank the nodes of --top by their dependents, their dependencies or both")
+                        .action(ArgAction::Set)
+                        .requires("top")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("node-labels")
+                        .long("node-labels")
+                        .num_args(1)
+                        .value_parser(["plain", "detailed"])
+                        .default_value("plain")
+                        .help("Render the nodes as their name, or as tables with their kind, exports count and largest exports")
+                        .action(ArgAction::Set)
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("symbol-kinds")
+                        .long("symbol-kinds")
+                        .action(ArgAction::SetTrue)
+                        .help("Suffix the symbols of the edge labels with () for the functions and [data] for the data")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("nodes-meta")
+                        .long("nodes-meta")
+                        .num_args(0..=1)
+                        .value_name("FILE")
+                        .value_parser(expand)
+                        .help("Also write the node attributes keyed by node id as JSON to FILE, nodes-meta.json next to the first output by default")
+                        .action(ArgAction::Set)
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("spill")
+                        .long("spill")
+                        .num_args(1)
+                        .value_name("DIR")
+                        .value_parser(expand)
+                        .help("Keep the symbol tables in temporary files inside DIR, for inputs too large for the memory")
+                        .action(ArgAction::Set)
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .num_args(1)
+                        .value_parser(["dot", "json", "xdot", "bazel"])
+                        .help("Write stdout and the outputs of no known extension as DOT, JSON, xdot laid out by the built-in engine, or Bazel targets, instead of DOT")
+                        .action(ArgAction::Set)
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("render")
+                        .long("render")
+                        .num_args(1)
+                        .value_parser(["svg"])
+                        .help("Write stdout and the outputs of no known extension as an SVG drawing, laid out by the --engine")
+                        .action(ArgAction::Set)
+                        .conflicts_with("format")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("engine")
+                        .long("engine")
+                        .num_args(1)
+                        .value_parser(["graphviz", "builtin"])
+                        .default_value("graphviz")
+                        .help("Lay out the SVG outputs with the dot command of Graphviz, or with the built-in engine on the machines without it")
+                        .action(ArgAction::Set)
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("split-output")
+                        .long("split-output")
+                        .num_args(1)
+                        .value_name("DIR")
+                        .value_parser(expand)
+                        .help("Write DIR/index.dot with a node per group and a DOT file per group, for graphs too large to lay out at once")
+                        .action(ArgAction::Set)
+                        .requires("group")
+                        .conflicts_with("aggregate")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("per-input")
+                        .long("per-input")
+                        .num_args(1)
+                        .value_name("DIR")
+                        .value_parser(expand)
+                        .help("Also write a DOT file per input into DIR, with the input and the libraries it depends on directly")
+                        .action(ArgAction::Set)
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("name")
+                        .long("name")
+                        .num_args(1)
+                        .help("Set the identifier of the DOT graph and the name of the JSON one")
+                        .action(ArgAction::Set)
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("targets")
+                        .long("targets")
+                        .num_args(1)
+                        .value_name("FILE")
+                        .value_parser(expand)
+                        .help("Name the nodes of -o bazel:FILE by the Bazel labels of FILE, one LIBRARY LABEL pair per line, LIBRARY being the label, soname or file name")
+                        .action(ArgAction::Set)
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("title")
+                        .long("title")
+                        .num_args(1)
+                        .help("Label the DOT graph with TITLE, under the diagram")
+                        .action(ArgAction::Set)
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("dpi")
+                        .long("dpi")
+                        .num_args(1)
+                        .value_parser(positive_number)
+                        .help("Render the graph at DPI dots per inch in the bitmap formats")
+                        .action(ArgAction::Set)
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("size")
+                        .long("size")
+                        .num_args(1)
+                        .value_name("WIDTH,HEIGHT")
+                        .value_parser(drawing_size)
+                        .help("Fit the drawing into WIDTH,HEIGHT inches, suffixed with ! to also scale it up, eg. 33,47! for a poster")
+                        .action(ArgAction::Set)
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("ratio")
+                        .long("ratio")
+                        .num_args(1)
+                        .value_parser(ratio)
+                        .help("Set the aspect ratio of the drawing: fill, compress, expand, auto or the height over width")
+                        .action(ArgAction::Set)
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("font-size")
+                        .long("font-size")
+                        .num_args(1)
+                        .value_name("POINTS")
+                        .value_parser(clap::value_parser!(f64))
+                        .help("Set the font size of the node labels")
+                        .action(ArgAction::Set)
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("color-edges")
+                        .long("color-edges")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Color the edges by resolution: black within the inputs, blue to the --resolve-against libraries, dotted grey to the needed libraries no symbol comes from, red to an unresolved node")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("no-metadata")
+                        .long("no-metadata")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Leave out the header recording the version, command line, time, inputs count and filters of the graph")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("highlight")
+                        .long("highlight")
+                        .num_args(1)
+                        .value_name("REGEX[=COLOR]")
+                        .value_parser(Highlight::parse)
+                        .help("Color the nodes and the symbol edges matching REGEX, red by default")
+                        .action(ArgAction::Append)
+                        .required(false),
+                ),
         )
-        .arg(
-            Arg::new("merge")
-                .short('m')
-                .long("merge")
-                .action(clap::ArgAction::SetTrue)
-                .help("Generate only one edge between libraries")
-                .required(false),
+        .subcommand(
+            input_args(Command::new("analyze").about("Report the unresolved imports and the dependency cycles, as text or as SARIF with -o sarif:FILE"))
+                .arg(
+                    Arg::new("check")
+                        .long("check")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Exit with status 4 when unresolved imports, cycles, banned symbols, exports off the allowlist or underlinked imports are found")
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("banned")
+                        .long("banned")
+                        .num_args(1)
+                        .value_name("FILE")
+                        .value_parser(expand)
+                        .help("Report the imports of the symbols listed in FILE, one per line, and where they come from")
+                        .action(ArgAction::Set)
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("allowlist")
+                        .long("allowlist")
+                        .num_args(1)
+                        .value_name("FILE")
+                        .value_parser(expand)
+                        .help("Report the exports missing from the [library] sections of FILE, one symbol per line")
+                        .action(ArgAction::Set)
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("underlinking")
+                        .long("underlinking")
+                        .action(clap::ArgAction::SetTrue)
+                        .help("Report the imports from the libraries missing from the DT_NEEDED entries of the importer")
+                        .required(false),
+                )
+                .arg(fail_if_arg()),
         )
-        .arg(
-            Arg::new("output")
-                .short('o')
-                .long("output")
-                .num_args(1)
-                .help("Sets the output file")
-                .action(ArgAction::Set)
-                .required(false),
+        .subcommand(
+            input_args(Command::new("query").about("List the libraries related to a library or a symbol, or the chain of dependencies between two libraries"))
+                .arg(
+                    Arg::new("dependencies-of")
+                        .long("dependencies-of")
+                        .num_args(1)
+                        .value_name("LIBRARY")
+                        .help("List the libraries providing symbols to LIBRARY")
+                        .action(ArgAction::Set),
+                )
+                .arg(
+                    Arg::new("dependents-of")
+                        .long("dependents-of")
+                        .num_args(1)
+                        .value_name("LIBRARY")
+                        .help("List the libraries using symbols of LIBRARY")
+                        .action(ArgAction::Set),
+                )
+                .arg(
+                    Arg::new("providers-of")
+                        .long("providers-of")
+                        .num_args(1)
+                        .value_name("SYMBOL")
+                        .help("List the libraries exporting SYMBOL")
+                        .action(ArgAction::Set),
+                )
+                .arg(
+                    Arg::new("path-between")
+                        .long("path-between")
+                        .num_args(2)
+                        .value_names(["FROM", "TO"])
+                        .help("List the libraries of the shortest dependency chain from FROM to TO, in order")
+                        .action(ArgAction::Set),
+                )
+                .group(
+                    ArgGroup::new("question")
+                        .args(["dependencies-of", "dependents-of", "providers-of", "path-between"])
+                        .required(true),
+                ),
         )
-        .arg(
-            Arg::new("file")
-                .help("Sets the input file to use")
-                .action(ArgAction::Append)
-                .required(true),
+        .subcommand(
+            input_args(Command::new("diff").about("Compare the graph of the --old files to the graph of the files"))
+                .arg(
+                    Arg::new("old")
+                        .long("old")
+                        .num_args(1..)
+                        .value_name("FILE")
+                        .value_parser(expand)
+                        .help("Sets the input files of the previous version")
+                        .action(ArgAction::Append)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            input_args(Command::new("abidiff").about("Compare the exports of two versions of a library, with their versions and sizes"))
+                .mut_arg("file", |a| a.num_args(2).value_names(["OLD", "NEW"]).help("Sets the old and the new version of the library"))
+                .arg(
+                    Arg::new("consumers")
+                        .long("consumers")
+                        .num_args(1..)
+                        .value_name("FILE")
+                        .value_parser(expand)
+                        .help("Report which of these inputs import the removed symbols")
+                        .action(ArgAction::Append)
+                        .required(false),
+                ),
+        )
+        .subcommand(
+            input_args(Command::new("report").about("Summarize the exports, imports and dependencies of each library"))
+                .arg(
+                    Arg::new("report")
+                        .long("report")
+                        .num_args(1)
+                        .value_name("REPORT")
+                        .value_parser(["summary", "providers", "metrics"])
+                        .default_value("summary")
+                        .help("Write the summary, a row per exported symbol with its library and the libraries importing it from there, or a row per library with its in and out degrees, its export, import and unresolved counts and its file size")
+                        .action(ArgAction::Set)
+                        .required(false),
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .num_args(1)
+                        .value_parser(["text", "tsv", "csv"])
+                        .default_value("text")
+                        .help("Write the providers and the metrics as text, or as tab or comma separated values with one importing library per column")
+                        .action(ArgAction::Set)
+                        .required(false),
+                ),
+        )
+        .subcommand(
+            input_args(Command::new("stats").about("Write the node, edge and import counts, the degrees and the components of the graph"))
+                .arg(fail_if_arg()),
+        )
+        .subcommand(
+            input_args(Command::new("visibility").about("Suggest the export lists hiding the symbols no other input imports")),
+        )
+        .subcommand(
+            input_args(Command::new("cmake").about("Suggest the target_link_libraries additions and removals fixing the under- and overlinking of the CMake targets"))
+                .arg(
+                    Arg::new("targets")
+                        .long("targets")
+                        .num_args(1)
+                        .value_name("FILE")
+                        .value_parser(expand)
+                        .help("Map the libraries to their CMake targets, one LIBRARY TARGET pair per line, LIBRARY being the label, soname or file name; the other libraries are linked by path")
+                        .action(ArgAction::Set)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            input_args(Command::new("churn").about("Write the export and import counts and the new cross-component dependencies of each library across snapshots"))
+                .mut_arg("file", |a| a.value_name("SNAPSHOT").help("Sets the JSON graphs written by graph -o FILE.json, from the oldest to the newest")),
+        )
+        .subcommand(
+            input_args(Command::new("index").about("Write the symbols exported by every library of the sysroots, for --providers"))
+                .mut_arg("file", |a| a.value_name("SYSROOT").help("Sets the directories whose libraries are indexed"))
+                .mut_arg("output", |a| a.value_name("FILE").required(true)),
+        )
+        .subcommand(
+            input_args(Command::new("update").about("Parse the changed files again in place of their nodes of a graph saved by --save, and save it again"))
+                .arg(
+                    Arg::new("saved")
+                        .value_name("SAVED")
+                        .value_parser(expand)
+                        .help("Sets the graph saved by --save to update")
+                        .index(1)
+                        .required(true),
+                )
+                .mut_arg("file", |a| a.index(2).value_name("CHANGED").help("Sets the changed files, the ones not in the saved graph are added"))
+                .mut_arg("output", |a| a.value_name("FILE").help("Write the updated graph to FILE instead of SAVED, - for stdout")),
+        )
+        .subcommand(
+            input_args(Command::new("serve").about("Answer JSON queries on the graph over HTTP: /nodes, /nodes/NAME, /providers, /path, /subgraph and /clusters, browsed by the web UI at /"))
+                .arg(
+                    Arg::new("port")
+                        .long("port")
+                        .num_args(1)
+                        .value_parser(clap::value_parser!(u16))
+                        .default_value("8080")
+                        .help("Listen on PORT")
+                        .action(ArgAction::Set),
+                )
+                .arg(
+                    Arg::new("bind")
+                        .long("bind")
+                        .num_args(1)
+                        .value_name("ADDRESS")
+                        .default_value("127.0.0.1")
+                        .help("Listen on ADDRESS, 0.0.0.0 for every interface")
+                        .action(ArgAction::Set),
+                ),
+        )
+        .subcommand(
+            input_args(Command::new("tui").about("Browse the nodes, their edges and exports in the terminal")),
         )
         .get_matches();
 
-    // the file to write into
-    let mut writer: Box<dyn Write> = match matches.get_one::<String>("output") {
-        Some(output) => {
-            let path = Path::new(output);
-            Box::new(fs::File::create(&path).unwrap())
-        }
-        None => Box::new(io::stdout()),
-    };
-
-    // read inputs and write dot file directly
-    let graph = if let Some(files) = matches.get_many::<String>("file") {
-        let mut graph = Graph::new("");
-
-        for f in files {
-            if matches.get_flag("verbose") {
-                println!("Parsing file {}", f);
-            }
-
-            graph.parse_binary(f);
-        }
-
-        if matches.get_flag("merge") {
-            if matches.get_flag("verbose") {
-                println!("merging");
-            }
-            graph.merge();
-        }
+    if let Some((_, matches)) = matches.subcommand() {
+        Logger::init(matches);
+    }
 
-        graph
-    } else {
-        Graph::new("")
+    let result = match matches.subcommand() {
+        Some((_, matches)) if matches.get_flag("dry-run") => dry_run(matches),
+        Some((name, matches)) if matches.get_flag("watch") => watch(name, matches),
+        Some((name, matches)) => run(name, matches),
+        _ => unreachable!("a subcommand is required"),
     };
 
-    // write as dot format
-    if matches.get_flag("verbose") {
-        println!("Exporting graph");
+    match result {
+        Ok(code) => process::exit(code),
+        Err(error) => {
+            error!("{}", error);
+            process::exit(EXIT_FAILURE);
+        }
     }
-    write!(writer, "{}", graph).expect("Unable to write the graph");
 }
+
@@ -0,0 +1,180 @@
+use std::collections::HashMap;
+
+use graph::{Graph, Node};
+
+/// The counts of the whole graph, to check it before rendering it.
+#[derive(Debug, Clone)]
+pub struct Metrics<'a> {
+    pub nodes: usize,
+    pub edges: usize,
+    /// The symbols resolved by the edges.
+    pub resolved: usize,
+    /// The imports no input exports, once per importer.
+    pub unresolved: usize,
+    /// The libraries linked to each node, on average.
+    pub average_degree: f64,
+    /// The most linked node with its label.
+    pub max_degree: Option<(usize, &'a str)>,
+    pub cycles: usize,
+    /// The strongly connected components, a node out of the cycles is one.
+    pub strong: usize,
+    pub components: usize,
+}
+
+impl<'a> Metrics<'a> {
+    /// The names of the metrics, as used by [`Metrics::get`] and [`Condition`].
+    pub const NAMES: [&'static str; 9] = ["nodes", "edges", "resolved", "unresolved", "average_degree", "max_degree", "cycles", "sccs", "components"];
+
+    /// The metrics of `graph`.
+    pub fn new(graph: &'a Graph) -> Self {
+        let nodes = graph.nodes().count();
+        // the libraries linked to each node, as in --top
+        let mut degrees: HashMap<u32, usize> = HashMap::new();
+        for ((n1, n2), _) in graph.edges().filter(|((n1, n2), _)| n1 != n2) {
+            *degrees.entry(n1).or_default() += 1;
+            *degrees.entry(n2).or_default() += 1;
+        }
+        let total: usize = degrees.values().sum();
+        // the nodes out of the cycles are components of their own
+        let cycles = graph.cycles();
+        Metrics {
+            nodes,
+            edges: graph.edges().count(),
+            resolved: graph.edges().map(|(_, e)| e.symbols_count()).sum(),
+            unresolved: graph.unresolved().iter().map(|(_, libs)| libs.len()).sum(),
+            average_degree: if nodes == 0 { 0.0 } else { total as f64 / nodes as f64 },
+            max_degree: degrees.iter()
+                .map(|(n, d)| (*d, graph.label(*n).unwrap_or_default()))
+                .max_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(a.1))),
+            cycles: cycles.len(),
+            strong: cycles.len() + nodes - cycles.iter().map(Vec::len).sum::<usize>(),
+            components: graph.components().len(),
+        }
+    }
+
+    /// The metric of one of the [`Metrics::NAMES`], panics on the others.
+    pub fn get(&self, name: &str) -> f64 {
+        match name {
+            "nodes" => self.nodes as f64,
+            "edges" => self.edges as f64,
+            "resolved" => self.resolved as f64,
+            "unresolved" => self.unresolved as f64,
+            "average_degree" => self.average_degree,
+            "max_degree" => self.max_degree.map_or(0, |(d, _)| d) as f64,
+            "cycles" => self.cycles as f64,
+            "sccs" => self.strong as f64,
+            "components" => self.components as f64,
+            _ => unreachable!("unknown metric {}", name),
+        }
+    }
+}
+
+/// A comparison of one of the [`Metrics`] to a number, eg. `unresolved>0`.
+#[derive(Debug, Clone)]
+pub struct Condition {
+    pub metric: String,
+    pub operator: &'static str,
+    pub value: f64,
+}
+
+impl Condition {
+    // the two characters operators first
+    const OPERATORS: [&'static str; 6] = [">=", "<=", "==", "!=", ">", "<"];
+
+    /// Parse a `metric<operator><number>`, the error tells what is invalid.
+    pub fn parse(condition: &str) -> Result<Self, String> {
+        let (index, operator) = Self::OPERATORS.iter()
+            .filter_map(|o| condition.find(o).map(|i| (i, *o)))
+            .min_by_key(|(i, o)| (*i, 2 - o.len()))
+            .ok_or_else(|| format!("missing operator in {}, expected one of {}", condition, Self::OPERATORS.join(" ")))?;
+        let metric = condition[..index].trim();
+        if !Metrics::NAMES.contains(&metric) {
+            return Err(format!("unknown metric {}, expected one of {}", metric, Metrics::NAMES.join(", ")));
+        }
+        let value = condition[index + operator.len()..].trim();
+        let value = value.parse().map_err(|_| format!("invalid number {} in {}", value, condition))?;
+        Ok(Condition { metric: metric.to_string(), operator, value })
+    }
+
+    /// Whether the metric of `metrics` compares as the condition says.
+    pub fn holds(&self, metrics: &Metrics) -> bool {
+        let metric = metrics.get(&self.metric);
+        match self.operator {
+            ">=" => metric >= self.value,
+            "<=" => metric <= self.value,
+            "==" => metric == self.value,
+            "!=" => metric != self.value,
+            ">" => metric > self.value,
+            _ => metric < self.value,
+        }
+    }
+}
+
+/// The degrees, the symbol counts and the file size of a library, to trend
+/// them across builds.
+#[derive(Debug, Clone)]
+pub struct NodeMetrics<'a> {
+    pub node: u32,
+    pub label: &'a str,
+    /// The libraries importing from it.
+    pub in_degree: usize,
+    /// The libraries it imports from.
+    pub out_degree: usize,
+    pub exports: usize,
+    pub imports: usize,
+    pub unresolved: usize,
+    /// The file size, none for the nodes not parsed from a file.
+    pub size: Option<u64>,
+}
+
+impl<'a> NodeMetrics<'a> {
+    /// The metrics of the labelled nodes, sorted by label.
+    pub fn all(graph: &'a Graph) -> Vec<Self> {
+        let mut nodes: Vec<(&str, u32, Node)> = graph.nodes().filter_map(|(n, p)| Some((graph.label(n)?, n, p))).collect();
+        nodes.sort_by_key(|(label, n, _)| (*label, *n));
+        nodes.into_iter()
+            .map(|(label, n, properties)| NodeMetrics {
+                node: n,
+                label,
+                in_degree: graph.dependents_of(n).len(),
+                out_degree: graph.dependencies_of(n).len(),
+                exports: properties.symbols().len(),
+                imports: graph.imports_count(n),
+                unresolved: graph.unresolved_count(n),
+                size: properties.attributes().get("size").and_then(|s| s.parse().ok()),
+            })
+            .collect()
+    }
+}
+
+/// An exported symbol with the libraries resolved to it.
+#[derive(Debug, Clone)]
+pub struct Provider<'a> {
+    pub symbol: &'a str,
+    /// The label of the exporting library.
+    pub provider: &'a str,
+    /// The labels of the importing libraries, sorted.
+    pub importers: Vec<&'a str>,
+}
+
+impl<'a> Provider<'a> {
+    /// A provider per exported symbol and library, sorted by symbol.
+    pub fn all(graph: &'a Graph) -> Vec<Self> {
+        let mut importers: HashMap<(u32, u32), Vec<&str>> = HashMap::new();
+        for ((n1, n2), edge) in graph.edges() {
+            for symbol in edge.symbols() {
+                importers.entry((n2, *symbol)).or_default().extend(graph.label(n1));
+            }
+        }
+        let mut rows: Vec<(&str, &str, Vec<&str>)> = graph.nodes()
+            .flat_map(|(n, p)| p.symbols().map(move |s| (n, *s)))
+            .filter_map(|(n, s)| {
+                let mut libs = importers.remove(&(n, s)).unwrap_or_default();
+                libs.sort();
+                Some((graph.symbol(s)?, graph.label(n)?, libs))
+            })
+            .collect();
+        rows.sort();
+        rows.into_iter().map(|(symbol, provider, importers)| Provider { symbol, provider, importers }).collect()
+    }
+}
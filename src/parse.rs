@@ -0,0 +1,754 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::Read;
+use std::ops::Deref;
+use std::panic;
+use std::path::Path;
+use std::str;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use memmap;
+use object;
+use object::{Object, ObjectSection, ObjectSymbol};
+use object::read::pe::{ExportTarget, ImageNtHeaders, Import, PeFile};
+use sha2::{Digest, Sha256};
+
+use cache;
+use saved;
+use source::SymbolSource;
+use elf::{dynamic_strings, hardening_issues, interpreter};
+use error::{Degraded, Error, Result};
+use filters::{basename, symbol_infos, DroppedSymbol, Filters, SymbolInfo};
+
+// the exports and imports of one file, ready to be merged into a graph
+pub(crate) struct ParsedFile {
+    // the node identity, case folded if needed
+    pub(crate) path: String,
+    pub(crate) label: String,
+    pub(crate) exports: Vec<String>,
+    // the size and the section of each export, when the symbol tables have them
+    pub(crate) export_infos: Vec<Option<SymbolInfo>>,
+    // the symbol table name of each export mangled into another one, eg.
+    // foo.cold exported as foo_cold or the case of the PE exports
+    pub(crate) export_names: Vec<Option<String>>,
+    pub(crate) imports: Vec<String>,
+    // whether each import is a weak reference, resolved only if available
+    pub(crate) import_weak: Vec<bool>,
+    // the names of the PE exports by ordinal, for the importers by ordinal
+    pub(crate) ordinals: Vec<(u32, String)>,
+    // the forwarded PE exports and their target, when the forwarders are
+    // followed rather than exported
+    pub(crate) forwards: Vec<(String, String)>,
+    // the node attributes describing the file, eg. arch
+    pub(crate) attributes: BTreeMap<String, String>,
+    pub(crate) warnings: Vec<Error>,
+    pub(crate) dropped: Vec<DroppedSymbol>,
+    // opening and mapping the file, zero when read from the cache
+    pub(crate) open_time: Duration,
+    // reading the symbols, from the file or the cache
+    pub(crate) parse_time: Duration,
+}
+
+// the symbols of a file before filtering, as stored in the cache
+pub(crate) struct FileSymbols {
+    pub(crate) pe: bool,
+    // COFF objects and import libraries, matched like the PE files
+    pub(crate) coff: bool,
+    pub(crate) soname: Option<String>,
+    // size, arch, endianness, kind, soname, build ids and missing hardenings
+    pub(crate) attributes: BTreeMap<String, String>,
+    pub(crate) exports: Vec<RawSymbol>,
+    pub(crate) imports: Vec<RawSymbol>,
+    // the ordinals of the named PE exports
+    pub(crate) ordinals: Vec<(u32, Vec<u8>)>,
+    // the forwarded PE exports and their target, dll#ordinal for an ordinal
+    pub(crate) forwards: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+pub(crate) struct RawSymbol {
+    pub(crate) name: Vec<u8>,
+    // None when the symbol tables do not describe it
+    pub(crate) info: Option<SymbolInfo>,
+}
+
+// parse a binary file using the first source reading it else object, None if
+// the filters skip it
+//
+// this does not touch the graph so that files can be parsed in parallel
+pub(crate) fn parse(filename: &str, filters: &Filters, cache: Option<&Path>, mmap: bool, sources: &[Arc<dyn SymbolSource>]) -> Result<Option<ParsedFile>> {
+    let started = Instant::now();
+    let mut open_time = Duration::ZERO;
+    let read = sources.iter().find_map(|s| s.read(filename));
+    let mut warnings = vec![];
+    let symbols = match (read, cache) {
+        (Some(symbols), _) => symbols?.0,
+        (None, Some(cache)) if !filters.needed_only => {
+            let (mut symbols, warning) = cache::load_or_read(cache, filename, mmap)?;
+            warnings.extend(warning);
+            // the cached entries have no digest, the file is read again
+            if filters.sha256 {
+                symbols.attributes.insert(String::from("sha256"), sha256(&map(filename, mmap)?));
+            }
+            symbols
+        }
+        (None, _) => {
+            let memory = map(filename, mmap)?;
+            open_time = started.elapsed();
+            let mut symbols = extract(filename, &memory, filters.needed_only)?;
+            if filters.sha256 {
+                symbols.attributes.insert(String::from("sha256"), sha256(&memory));
+            }
+            symbols
+        }
+    };
+    Ok(apply_filters(filename, symbols, filters).map(|mut parsed| {
+        parsed.warnings.extend(warnings);
+        ParsedFile {
+            open_time,
+            parse_time: started.elapsed() - open_time,
+            ..parsed
+        }
+    }))
+}
+
+// parse the files on jobs threads, the results are in the files order
+//
+// the saved graphs are loaded instead, with all their nodes
+pub(crate) fn parse_all<F>(files: &[String], jobs: usize, filters: &Filters, cache: Option<&Path>, mmap: bool, sources: &[Arc<dyn SymbolSource>], on_file: F)
+    -> Vec<Result<Vec<ParsedFile>>>
+    where F: Fn(&str) + Sync
+{
+    let jobs = jobs.max(1).min(files.len().max(1));
+    let on_file = &on_file;
+    let next = AtomicUsize::new(0);
+
+    let mut results: Vec<(usize, Result<Vec<ParsedFile>>)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs)
+            .map(|_| scope.spawn(|| {
+                let mut results = vec![];
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let filename = match files.get(index) {
+                        Some(filename) => filename,
+                        None => break,
+                    };
+                    on_file(filename);
+                    let result = if saved::is_saved(filename) {
+                        saved::read(filename)
+                    } else {
+                        parse(filename, filters, cache, mmap, sources).map(|p| p.into_iter().collect())
+                    };
+                    results.push((index, result));
+                }
+                results
+            }))
+            .collect();
+
+        workers.into_iter()
+            .flat_map(|w| w.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+            .collect()
+    });
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+// the contents of a file, mapped in memory or read into a buffer
+pub(crate) enum Contents {
+    Mapped(memmap::Mmap),
+    Read(Vec<u8>),
+}
+
+impl Deref for Contents {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Contents::Mapped(memory) => memory,
+            Contents::Read(buffer) => buffer,
+        }
+    }
+}
+
+// map the file, or read it when mmap is off or fails, eg. on some network
+// filesystems or for empty files
+pub(crate) fn map(filename: &str, mmap: bool) -> Result<Contents> {
+    let mut file = match fs::File::open(filename) {
+        Ok(file) => file,
+        Err(error) => return Err(Error::Io { path: filename.to_string(), error }),
+    };
+
+    if mmap {
+        match unsafe { memmap::Mmap::map(&file) } {
+            Ok(memory) => return Ok(Contents::Mapped(memory)),
+            Err(error) => debug!("Unable to mmap {} : {}, reading it instead", filename, error),
+        }
+    }
+    let mut buffer = vec![];
+    match file.read_to_end(&mut buffer) {
+        Ok(_) => Ok(Contents::Read(buffer)),
+        Err(error) => Err(Error::Io { path: filename.to_string(), error }),
+    }
+}
+
+// the exported and imported symbols of the mapped file, none but the
+// attributes when needed_only
+pub(crate) fn extract(filename: &str, memory: &[u8], needed_only: bool) -> Result<FileSymbols> {
+    let object_file = match object::File::parse(memory) {
+        Ok(object_file) => object_file,
+        Err(error) => return coff_archive(memory).ok_or(Error::Parse { path: filename.to_string(), error }),
+    };
+    let coff = object_file.format() == object::BinaryFormat::Coff;
+
+    let soname = dynamic_strings(&object_file, object::elf::DT_SONAME).pop();
+    let mut attributes = BTreeMap::new();
+    attributes.insert(String::from("size"), memory.len().to_string());
+    attributes.insert(String::from("arch"), format!("{:?}", object_file.architecture()).to_lowercase());
+    attributes.insert(String::from("endianness"), String::from(if object_file.is_little_endian() { "little" } else { "big" }));
+    attributes.insert(String::from("kind"), format!("{:?}", object_file.kind()).to_lowercase());
+    if let Some(soname) = &soname {
+        attributes.insert(String::from("soname"), soname.clone());
+    }
+    let needed = needed_libraries(&object_file);
+    if !needed.is_empty() {
+        attributes.insert(String::from("needed"), needed.join(" "));
+    }
+    if let Some(interpreter) = interpreter(&object_file) {
+        attributes.insert(String::from("interpreter"), interpreter);
+    }
+    identify(&object_file, &mut attributes);
+    if needed_only {
+        return Ok(FileSymbols {
+            pe: object_file.format() == object::BinaryFormat::Pe,
+            coff,
+            soname,
+            attributes,
+            exports: vec![],
+            imports: vec![],
+            ordinals: vec![],
+            forwards: vec![],
+        });
+    }
+    let issues = hardening_issues(&object_file);
+    if !issues.is_empty() {
+        attributes.insert(String::from("hardening"), issues.join(","));
+    }
+
+    let infos = symbol_infos(&object_file);
+    let raw = |name: &[u8]| RawSymbol { name: name.to_vec(), info: infos.get(name).cloned() };
+
+    let tables = match &object_file {
+        object::File::Pe32(pe) => pe_tables(pe, filename),
+        object::File::Pe64(pe) => pe_tables(pe, filename),
+        _ => PeTables::default(),
+    };
+    if coff {
+        let (exports, imports) = global_symbols(&object_file);
+        return Ok(FileSymbols {
+            pe: false,
+            coff,
+            soname,
+            attributes,
+            exports: exports.into_iter().map(raw).collect(),
+            imports: imports.into_iter().map(raw).collect(),
+            ordinals: vec![],
+            forwards: vec![],
+        });
+    }
+    let mut symbols = FileSymbols {
+        pe: object_file.format() == object::BinaryFormat::Pe,
+        coff,
+        soname,
+        attributes,
+        exports: match object_file.exports() {
+            Ok(symbols) => symbols.iter()
+                .map(|s| raw(s.name()))
+                .chain(special_exports(&object_file).into_iter().map(raw))
+                .chain(tables.unnamed.iter().map(|name| raw(name)))
+                .collect(),
+            Err(_) => vec![],
+        },
+        // in case of plain object files
+        imports: match object_file.imports() {
+            Ok(symbols) => symbols.iter()
+                .map(|s| raw(s.name()))
+                .chain(tables.imports.iter().map(|name| raw(name)))
+                .collect(),
+            Err(_) => vec![],
+        },
+        ordinals: tables.named,
+        forwards: tables.forwards,
+    };
+
+    // without dynamic symbols, eg. stripped static binaries, the symbol table
+    // is used if any, else only the DT_NEEDED libraries link the file
+    if symbols.exports.is_empty() && symbols.imports.is_empty() {
+        let (exports, imports) = global_symbols(&object_file);
+        let degraded = match (exports.is_empty() && imports.is_empty(), symbols.attributes.contains_key("needed")) {
+            (false, _) => Degraded::Symtab,
+            (true, true) => Degraded::Needed,
+            (true, false) => Degraded::Empty,
+        };
+        // the object files have nothing else
+        if object_file.kind() != object::ObjectKind::Relocatable {
+            symbols.attributes.insert(String::from("degraded"), String::from(degraded.name()));
+        }
+        symbols.exports = exports.into_iter().map(raw).collect();
+        symbols.imports = imports.into_iter().map(raw).collect();
+    }
+    Ok(symbols)
+}
+
+// the global definitions and references of the symbol table, left out by
+// object for the COFF and the relocatable objects
+fn global_symbols<'data>(object_file: &object::File<'data>) -> (Vec<&'data [u8]>, Vec<&'data [u8]>) {
+    let (mut exports, mut imports) = (vec![], vec![]);
+    for symbol in object_file.symbols().filter(|s| s.is_global()) {
+        match symbol.name_bytes() {
+            // the import descriptors of the import libraries
+            Ok(name) if name.starts_with(b"\x7f") => (),
+            Ok(name) if symbol.is_undefined() => imports.push(name),
+            Ok(name) if symbol.is_definition() => exports.push(name),
+            _ => (),
+        }
+    }
+    (exports, imports)
+}
+
+// an import library, or a static library of COFF objects, as a single file
+// exporting the symbols of its members
+fn coff_archive(memory: &[u8]) -> Option<FileSymbols> {
+    let archive = object::read::archive::ArchiveFile::parse(memory).ok()?;
+    let mut symbols = FileSymbols {
+        pe: false,
+        coff: true,
+        soname: None,
+        attributes: BTreeMap::new(),
+        exports: vec![],
+        imports: vec![],
+        ordinals: vec![],
+        forwards: vec![],
+    };
+    let mut architecture = None;
+    let mut libraries = vec![];
+    for member in archive.members() {
+        let data = match member.and_then(|m| m.data(memory)) {
+            Ok(data) => data,
+            Err(_) => continue,
+        };
+        if let Ok(import) = object::read::coff::ImportFile::parse(data) {
+            architecture = Some(import.architecture());
+            let library = String::from_utf8_lossy(import.dll()).to_lowercase();
+            if !libraries.contains(&library) {
+                libraries.push(library);
+            }
+            symbols.exports.push(RawSymbol { name: import.symbol().to_vec(), info: None });
+        } else if let Some(object_file) = object::File::parse(data).ok().filter(|o| o.format() == object::BinaryFormat::Coff) {
+            architecture = Some(object_file.architecture());
+            let (exports, imports) = global_symbols(&object_file);
+            symbols.exports.extend(exports.into_iter().map(|name| RawSymbol { name: name.to_vec(), info: None }));
+            symbols.imports.extend(imports.into_iter().map(|name| RawSymbol { name: name.to_vec(), info: None }));
+        }
+    }
+    // the other archives are not supported
+    let architecture = architecture?;
+    symbols.attributes.insert(String::from("size"), memory.len().to_string());
+    symbols.attributes.insert(String::from("arch"), format!("{:?}", architecture).to_lowercase());
+    symbols.attributes.insert(String::from("endianness"), String::from("little"));
+    symbols.attributes.insert(String::from("kind"), String::from(if libraries.is_empty() { "archive" } else { "import" }));
+    if !libraries.is_empty() {
+        symbols.attributes.insert(String::from("needed"), libraries.join(" "));
+    }
+    Some(symbols)
+}
+
+// the PE exports and imports by ordinal and the forwarded exports, left out
+// by object
+#[derive(Default)]
+struct PeTables {
+    named: Vec<(u32, Vec<u8>)>,
+    // the exports without a name and the imports by ordinal, named
+    // dll#ordinal after the exporting DLL
+    unnamed: Vec<Vec<u8>>,
+    imports: Vec<Vec<u8>>,
+    forwards: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+fn pe_tables<Pe: ImageNtHeaders>(pe: &PeFile<Pe>, filename: &str) -> PeTables {
+    let mut tables = PeTables::default();
+    if let Ok(Some(table)) = pe.export_table() {
+        for export in table.exports().unwrap_or_default() {
+            let name = match export.name {
+                Some(name) => name.to_vec(),
+                None => ordinal_name(basename(filename).as_bytes(), export.ordinal),
+            };
+            // the forwarded DLLs are named without their extension
+            let library = |library: &[u8]| match library.contains(&b'.') {
+                true => library.to_vec(),
+                false => [library, b".dll"].concat(),
+            };
+            match export.target {
+                ExportTarget::ForwardByName(_, symbol) => tables.forwards.push((name, symbol.to_vec())),
+                ExportTarget::ForwardByOrdinal(target, ordinal) => tables.forwards.push((name, ordinal_name(&library(target), ordinal))),
+                ExportTarget::Address(_) if export.name.is_some() => tables.named.push((export.ordinal, name)),
+                ExportTarget::Address(_) => tables.unnamed.push(name),
+            }
+        }
+    }
+    if let Ok(Some(table)) = pe.import_table() {
+        let mut descriptors = match table.descriptors() {
+            Ok(descriptors) => descriptors,
+            Err(_) => return tables,
+        };
+        while let Ok(Some(descriptor)) = descriptors.next() {
+            let library = match table.name(descriptor.name.get(object::LittleEndian)) {
+                Ok(library) => library,
+                Err(_) => continue,
+            };
+            let mut first_thunk = descriptor.original_first_thunk.get(object::LittleEndian);
+            if first_thunk == 0 {
+                first_thunk = descriptor.first_thunk.get(object::LittleEndian);
+            }
+            let mut thunks = match table.thunks(first_thunk) {
+                Ok(thunks) => thunks,
+                Err(_) => continue,
+            };
+            while let Ok(Some(thunk)) = thunks.next::<Pe>() {
+                if let Ok(Import::Ordinal(ordinal)) = table.import::<Pe>(thunk) {
+                    tables.imports.push(ordinal_name(library, u32::from(ordinal)));
+                }
+            }
+        }
+    }
+    tables
+}
+
+// the placeholder of an export by ordinal, the DLL names are case insensitive
+fn ordinal_name(library: &[u8], ordinal: u32) -> Vec<u8> {
+    format!("{}#{}", String::from_utf8_lossy(library).to_lowercase(), ordinal).into_bytes()
+}
+
+// the names of the PE exports by ordinal and the followed forwarders, by the
+// placeholder prefix of their DLL, for the later importers
+#[derive(Clone, Default)]
+pub(crate) struct PeNames {
+    ordinals: HashMap<String, HashMap<u32, String>>,
+    forwards: HashMap<String, Vec<(String, String)>>,
+}
+
+impl PeNames {
+    // the ordinals of the DLL at path, sorted
+    pub(crate) fn ordinals_of(&self, path: &str, filters: &Filters) -> Vec<(u32, String)> {
+        let mut ordinals: Vec<(u32, String)> = pe_library(path, filters)
+            .and_then(|library| self.ordinals.get(&library))
+            .map(|ordinals| ordinals.iter().map(|(o, n)| (*o, n.clone())).collect())
+            .unwrap_or_default();
+        ordinals.sort();
+        ordinals
+    }
+
+    // the followed forwarders of the DLL at path, as parsed
+    pub(crate) fn forwards_of(&self, path: &str, filters: &Filters) -> Vec<(String, String)> {
+        pe_library(path, filters).and_then(|library| self.forwards.get(&library)).cloned().unwrap_or_default()
+    }
+}
+
+// the prefix of the placeholders of the exports of a DLL
+fn pe_library(path: &str, filters: &Filters) -> Option<String> {
+    filters.mangle_as_valid_dot_name(&basename(path).to_lowercase())
+}
+
+// name the imports by ordinal after the exports of their DLL and the imports
+// of the followed forwarders after their target, when the DLL is one of these
+// files or was one of the previous ones, kept in `known`
+pub(crate) fn name_pe_imports(files: &mut [ParsedFile], filters: &Filters, known: &mut PeNames) {
+    for file in files.iter().filter(|f| !f.ordinals.is_empty() || !f.forwards.is_empty()) {
+        if let Some(library) = pe_library(&file.path, filters) {
+            known.ordinals.insert(library.clone(), file.ordinals.iter().cloned().collect());
+            known.forwards.insert(library, file.forwards.clone());
+        }
+    }
+    if known.ordinals.is_empty() && known.forwards.is_empty() {
+        return;
+    }
+    let forwards: HashMap<&str, HashMap<&str, &str>> = known.forwards.iter()
+        .map(|(library, forwards)| (library.as_str(), forwards.iter().map(|(e, t)| (e.as_str(), t.as_str())).collect()))
+        .collect();
+    // the targets forwarded by name are of unknown DLL
+    let any: HashMap<&str, &str> = forwards.values().flatten().map(|(e, t)| (*e, *t)).collect();
+    for file in files.iter_mut() {
+        let needed: Vec<String> = file.attributes.get("needed").into_iter()
+            .flat_map(|n| n.split(' '))
+            .filter_map(|l| pe_library(l, filters))
+            .collect();
+        for import in file.imports.iter_mut() {
+            // in the forwarders of the DLLs the file imports from, the first
+            // one wins, then of the DLL of an ordinal, else of every DLL
+            let (mut direct, mut exporter): (bool, Option<String>) = (true, None);
+            // a forwarder may be forwarded again, the loops are left as they are
+            for _ in 0..8 {
+                let ordinal = import.rsplit_once('#')
+                    .and_then(|(library, ordinal)| Some((library, known.ordinals.get(library)?, ordinal.parse().ok()?)))
+                    .and_then(|(library, ordinals, ordinal)| Some((library, ordinals.get(&ordinal)?)));
+                let forwarded = if direct {
+                    needed.iter().find_map(|l| forwards.get(l.as_str())?.get(import.as_str()))
+                } else {
+                    match &exporter {
+                        Some(library) => forwards.get(library.as_str()).and_then(|f| f.get(import.as_str())),
+                        None => any.get(import.as_str()),
+                    }
+                };
+                let (library, name) = match (ordinal, forwarded) {
+                    (Some((library, name)), _) => (Some(library.to_string()), name.as_str()),
+                    (None, Some(name)) => (None, *name),
+                    (None, None) => break,
+                };
+                if name == import.as_str() {
+                    break;
+                }
+                *import = name.to_string();
+                direct = false;
+                exporter = library;
+            }
+        }
+    }
+}
+
+// the DT_NEEDED libraries in the load order, or the imported PE modules
+fn needed_libraries(object_file: &object::File) -> Vec<String> {
+    let mut needed = dynamic_strings(object_file, object::elf::DT_NEEDED);
+    if object_file.format() == object::BinaryFormat::Pe {
+        for import in object_file.imports().unwrap_or_default() {
+            let library = String::from_utf8_lossy(import.library()).to_lowercase();
+            if !needed.contains(&library) {
+                needed.push(library);
+            }
+        }
+    }
+    needed
+}
+
+// the global common and absolute dynamic definitions, left out of the
+// exports by object
+fn special_exports<'data>(object_file: &object::File<'data>) -> Vec<&'data [u8]> {
+    object_file.dynamic_symbols()
+        .filter(|s| s.is_global() && (s.is_common() || s.section() == object::SymbolSection::Absolute))
+        .filter_map(|s| s.name_bytes().ok())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+fn sha256(memory: &[u8]) -> String {
+    Sha256::digest(memory).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// the attributes telling the exact binary, to correlate graphs made elsewhere
+//
+// the GNU build-id note or the Mach-O UUID, and for PE the link timestamp and
+// the PDB key of the symbol servers
+fn identify(object_file: &object::File, attributes: &mut BTreeMap<String, String>) {
+    if let Ok(Some(build_id)) = object_file.build_id() {
+        attributes.insert(String::from("build_id"), build_id.iter().map(|b| format!("{:02x}", b)).collect());
+    }
+    let timestamp = match object_file {
+        object::File::Pe32(file) => Some(file.nt_headers().file_header.time_date_stamp.get(object::LittleEndian)),
+        object::File::Pe64(file) => Some(file.nt_headers().file_header.time_date_stamp.get(object::LittleEndian)),
+        _ => None,
+    };
+    if let Some(timestamp) = timestamp {
+        attributes.insert(String::from("timestamp"), timestamp.to_string());
+    }
+    if let Ok(Some(codeview)) = object_file.pdb_info() {
+        let guid = codeview.guid();
+        let data1 = u32::from_le_bytes([guid[0], guid[1], guid[2], guid[3]]);
+        let data2 = u16::from_le_bytes([guid[4], guid[5]]);
+        let data3 = u16::from_le_bytes([guid[6], guid[7]]);
+        let data4: String = guid[8..].iter().map(|b| format!("{:02X}", b)).collect();
+        attributes.insert(String::from("pdb_id"), format!("{:08X}{:04X}{:04X}{}{:x}", data1, data2, data3, data4, codeview.age()));
+    }
+    if let Some(version) = go_version(object_file) {
+        attributes.insert(String::from("go"), version);
+    }
+}
+
+// the toolchain version of a Go binary from its build info, unknown for the
+// older layout only pointing to it
+fn go_version(object_file: &object::File) -> Option<String> {
+    let section = object_file.section_by_name(".go.buildinfo").or_else(|| object_file.section_by_name("__go_buildinfo"))?;
+    let data = section.data().ok()?;
+    if !data.starts_with(b"\xff Go buildinf:") || data.len() < 32 {
+        return None;
+    }
+    if data[15] & 2 == 0 {
+        return Some(String::from("unknown"));
+    }
+    // a varint length of at most 10 bytes then the string, none if malformed
+    let (mut length, mut offset) = (0usize, 32);
+    for shift in (0..=63).step_by(7) {
+        let byte = *data.get(offset)?;
+        length |= usize::from(byte & 0x7f).checked_shl(shift)?;
+        offset += 1;
+        if byte & 0x80 == 0 {
+            let version = data.get(offset..offset.checked_add(length)?)?;
+            return Some(String::from_utf8_lossy(version).into_owned());
+        }
+    }
+    None
+}
+
+fn apply_filters(filename: &str, symbols: FileSymbols, filters: &Filters) -> Option<ParsedFile> {
+    // skip the libraries linked by nearly everything
+    if filters.ignore_system {
+        let soname = match &symbols.soname {
+            Some(soname) => soname,
+            None => basename(filename),
+        };
+        if filters.is_system(filename, soname) {
+            return None;
+        }
+    }
+
+    let label = filters.node_label(filename)?;
+
+    // windows resolves modules and imports regardless of the case
+    let windows = symbols.pe || symbols.coff;
+    let go = symbols.attributes.contains_key("go");
+    let fold = filters.ignore_case || windows;
+    let path = match (fold, symbols.pe) {
+        (true, true) => basename(filename).to_lowercase(),
+        (true, false) => filename.to_lowercase(),
+        _ => filename.to_string(),
+    };
+
+    let mut parsed = ParsedFile {
+        path,
+        label,
+        exports: Vec::with_capacity(symbols.exports.len()),
+        export_infos: Vec::with_capacity(symbols.exports.len()),
+        export_names: Vec::with_capacity(symbols.exports.len()),
+        imports: Vec::with_capacity(symbols.imports.len()),
+        import_weak: Vec::with_capacity(symbols.imports.len()),
+        ordinals: vec![],
+        forwards: vec![],
+        attributes: symbols.attributes,
+        warnings: vec![],
+        dropped: vec![],
+        open_time: Duration::ZERO,
+        parse_time: Duration::ZERO,
+    };
+
+    // the symbol names are expected to be UTF-8, the others are skipped
+    let symbol_name = |symbol: &RawSymbol, parsed: &mut ParsedFile| {
+        if !filters.accept(symbol.info.as_ref()) {
+            return None;
+        }
+        // the MSVC dllimport references go through the __imp_ pointer of the
+        // symbol, matched to the symbol itself
+        let name = match symbol.name.strip_prefix(b"__imp_") {
+            Some(name) if windows => name,
+            _ => &symbol.name,
+        };
+        let symbol_name = match str::from_utf8(name) {
+            Ok(symbol_name) => symbol_name,
+            Err(_) => {
+                parsed.warnings.push(Error::NonUtf8Symbol { path: filename.to_string(), symbol: symbol.name.clone() });
+                return None;
+            }
+        };
+        let reason = match go {
+            true => filters.go_dropped_reason(symbol_name),
+            false => filters.dropped_reason(symbol_name),
+        };
+        if let Some(reason) = reason {
+            parsed.dropped.push(DroppedSymbol { path: filename.to_string(), symbol: symbol_name.to_string(), reason });
+            return None;
+        }
+        if go {
+            Some(filters.go_name(symbol_name))
+        } else if fold {
+            filters.mangle_as_valid_dot_name(&symbol_name.to_lowercase())
+        } else {
+            filters.mangle_as_valid_dot_name(symbol_name)
+        }
+    };
+
+    for symbol in &symbols.exports {
+        if let Some(reason) = filters.special_reason(symbol.info.as_ref()) {
+            parsed.dropped.push(DroppedSymbol { path: filename.to_string(), symbol: String::from_utf8_lossy(&symbol.name).into_owned(), reason });
+            continue;
+        }
+        if let Some(name) = symbol_name(symbol, &mut parsed) {
+            parsed.export_names.push(raw_name(&symbol.name, &name));
+            parsed.exports.push(name);
+            parsed.export_infos.push(symbol.info.clone());
+        }
+    }
+    // a package is exported once, without the size of one of its symbols
+    if go && filters.go_packages {
+        let mut seen = HashSet::new();
+        parsed.exports.retain(|name| seen.insert(name.clone()));
+        parsed.export_infos = vec![None; parsed.exports.len()];
+        parsed.export_names = vec![None; parsed.exports.len()];
+    }
+    // the libraries defining versions are expected to version every export
+    let versioned: Vec<&str> = parsed.exports.iter().zip(&parsed.export_infos)
+        .filter(|(_, info)| info.as_ref().is_some_and(|i| i.version.is_some()))
+        .map(|(s, _)| s.as_str())
+        .collect();
+    let unversioned: Vec<&str> = parsed.exports.iter().zip(&parsed.export_infos)
+        .filter(|(_, info)| info.as_ref().is_some_and(|i| i.version.is_none()))
+        .map(|(s, _)| s.as_str())
+        .collect();
+    if !versioned.is_empty() && !unversioned.is_empty() {
+        let (symbols, versioned) = if unversioned.len() <= versioned.len() { (unversioned, false) } else { (versioned, true) };
+        let symbols = symbols.into_iter().map(String::from).collect();
+        parsed.warnings.push(Error::MixedVersioning { path: filename.to_string(), symbols, versioned });
+    }
+
+    if let Some(degraded) = parsed.attributes.get("degraded").and_then(|d| Degraded::from_name(d)) {
+        parsed.warnings.push(Error::Degraded { path: filename.to_string(), degraded });
+    }
+
+    // named as the exports, which were already checked
+    for (ordinal, name) in &symbols.ordinals {
+        let name = str::from_utf8(name).ok().and_then(|n| filters.mangle_as_valid_dot_name(&n.to_lowercase()));
+        if let Some(name) = name {
+            parsed.ordinals.push((*ordinal, name));
+        }
+    }
+
+    for symbol in &symbols.imports {
+        if let Some(name) = symbol_name(symbol, &mut parsed) {
+            parsed.imports.push(name);
+            parsed.import_weak.push(symbol.info.as_ref().is_some_and(|i| i.weak));
+        }
+    }
+    // exported by the forwarding DLL which imports the target, or resolved
+    // to the target by its importers
+    for (export, target) in &symbols.forwards {
+        let names = (
+            symbol_name(&RawSymbol { name: export.clone(), info: None }, &mut parsed),
+            symbol_name(&RawSymbol { name: target.clone(), info: None }, &mut parsed),
+        );
+        match names {
+            (Some(export), Some(target)) if filters.follow_forwarders => parsed.forwards.push((export, target)),
+            (Some(name), Some(target)) => {
+                parsed.export_names.push(raw_name(export, &name));
+                parsed.exports.push(name);
+                parsed.export_infos.push(None);
+                parsed.imports.push(target);
+                parsed.import_weak.push(false);
+            }
+            _ => (),
+        }
+    }
+
+    Some(parsed)
+}
+
+// the symbol table name of a symbol, if not the one of the graph
+fn raw_name(symbol: &[u8], name: &str) -> Option<String> {
+    str::from_utf8(symbol).ok().filter(|raw| *raw != name).map(String::from)
+}
@@ -0,0 +1,97 @@
+use std::fmt;
+use std::fmt::Display;
+
+use analyzer::{Finding, Severity};
+use graph::Graph;
+use json::quote;
+
+/// Renders the findings of the [`Analyzers`](crate::Analyzers) as a SARIF
+/// 2.1.0 log, for the code scanning dashboards.
+///
+/// Each analyzer is a rule, its id the name with dashes, each finding a result
+/// located at the files of its nodes:
+///
+/// ```no_run
+/// # use symbols_graph::{Analyzers, Graph, SarifWriter};
+/// # fn main() -> symbols_graph::Result<()> {
+/// let mut graph = Graph::new("deps");
+/// graph.parse_file("libfoo.so")?;
+/// let analyzers = Analyzers::default();
+/// let findings = analyzers.run(&graph);
+/// print!("{}", SarifWriter::new(&graph, &findings));
+/// # Ok(())
+/// # }
+/// ```
+pub struct SarifWriter<'a> {
+    graph: &'a Graph,
+    findings: &'a [(&'a str, Vec<Finding>)],
+}
+
+impl<'a> SarifWriter<'a> {
+    pub fn new(graph: &'a Graph, findings: &'a [(&'a str, Vec<Finding>)]) -> Self {
+        Self { graph, findings }
+    }
+}
+
+impl<'a> Display for SarifWriter<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rules: Vec<String> = self.findings.iter()
+            .map(|(name, _)| format!("{{\"id\": {}, \"name\": {}, \"shortDescription\": {{\"text\": {}}}}}",
+                                     quote(&rule_id(name)), quote(name), quote(name)))
+            .collect();
+        let mut results = vec![];
+        for (index, (name, findings)) in self.findings.iter().enumerate() {
+            for finding in findings {
+                let mut result = format!("{{\"ruleId\": {}, \"ruleIndex\": {}, \"level\": {}, \"message\": {{\"text\": {}}}",
+                                         quote(&rule_id(name)), index, quote(level(finding.severity)), quote(&finding.message));
+                let locations: Vec<String> = finding.nodes.iter()
+                    .filter_map(|n| self.graph.path(*n))
+                    .map(location)
+                    .collect();
+                if !locations.is_empty() {
+                    result += &format!(", \"locations\": [{}]", locations.join(", "));
+                }
+                results.push(result + "}");
+            }
+        }
+
+        writeln!(f, "{{")?;
+        writeln!(f, "  \"$schema\": \"https://json.schemastore.org/sarif-2.1.0.json\",")?;
+        writeln!(f, "  \"version\": \"2.1.0\",")?;
+        writeln!(f, "  \"runs\": [{{")?;
+        writeln!(f, "    \"tool\": {{\"driver\": {{\"name\": \"symbols-graph\", \"version\": {}, \"rules\": [", quote(env!("CARGO_PKG_VERSION")))?;
+        if !rules.is_empty() {
+            writeln!(f, "      {}", rules.join(",\n      "))?;
+        }
+        writeln!(f, "    ]}}}},")?;
+        writeln!(f, "    \"results\": [")?;
+        if !results.is_empty() {
+            writeln!(f, "      {}", results.join(",\n      "))?;
+        }
+        writeln!(f, "    ]")?;
+        writeln!(f, "  }}]")?;
+        writeln!(f, "}}")
+    }
+}
+
+// the name of an analyzer as a rule id, eg. dependency-cycles
+fn rule_id(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join("-")
+}
+
+fn level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Info => "note",
+    }
+}
+
+// the file of a node, relative to the source root unless absolute
+fn location(path: &str) -> String {
+    let artifact = match path.strip_prefix('/') {
+        Some(_) => format!("{{\"uri\": {}}}", quote(&format!("file://{}", path))),
+        None => format!("{{\"uri\": {}, \"uriBaseId\": \"%SRCROOT%\"}}", quote(path.trim_start_matches("./"))),
+    };
+    format!("{{\"physicalLocation\": {{\"artifactLocation\": {}}}}}", artifact)
+}
@@ -0,0 +1,164 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::io::{Read, Write};
+use std::time::Duration;
+
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+
+use cache::{hex, kind_from_name, kind_name, unhex};
+use error::{Error, Result};
+use filters::{SymbolInfo, Visibility};
+use parse::ParsedFile;
+
+// bumped when the layout changes, the older graphs are then rejected
+const HEADER: &str = "symbols-graph saved graph 4";
+
+// whether an input is a graph saved by --save rather than a binary
+pub(crate) fn is_saved(path: &str) -> bool {
+    path.ends_with(".sgz")
+}
+
+// the layout is a header then one record per line, each node followed by its
+// attributes and symbols:
+//
+//   node <hex path> <hex label>, attribute <hex name> <hex value>,
+//   E <kind> <size> <hex section>|- <hex address> <hex version>|- <hex name>,
+//   or E - <hex name> without symbol table information, followed by N <hex
+//   name> when the symbol table names it otherwise, I w|- <hex name>,
+//   O <ordinal> <hex name>, F <hex export> <hex target>
+//
+// the files are already filtered, they are loaded as saved
+pub(crate) fn write<W: Write>(files: &[ParsedFile], writer: W) -> io::Result<()> {
+    let mut writer = GzEncoder::new(writer, Compression::default());
+    writeln!(writer, "{}", HEADER)?;
+    for file in files {
+        writeln!(writer, "node {} {}", hex(file.path.as_bytes()), hex(file.label.as_bytes()))?;
+        for (name, value) in &file.attributes {
+            writeln!(writer, "attribute {} {}", hex(name.as_bytes()), hex(value.as_bytes()))?;
+        }
+        for ((symbol, info), raw) in file.exports.iter().zip(&file.export_infos).zip(&file.export_names) {
+            match info {
+                Some(info) => {
+                    let section = info.section.as_ref().map_or(String::from("-"), |s| hex(s.as_bytes()));
+                    let version = info.version.as_ref().map_or(String::from("-"), |v| hex(v.as_bytes()));
+                    writeln!(writer, "E {} {} {} {:x} {} {}", kind_name(info.kind),
+                             info.size, section, info.address, version, hex(symbol.as_bytes()))?;
+                }
+                None => writeln!(writer, "E - {}", hex(symbol.as_bytes()))?,
+            }
+            if let Some(raw) = raw {
+                writeln!(writer, "N {}", hex(raw.as_bytes()))?;
+            }
+        }
+        for (symbol, weak) in file.imports.iter().zip(&file.import_weak) {
+            writeln!(writer, "I {} {}", if *weak { "w" } else { "-" }, hex(symbol.as_bytes()))?;
+        }
+        for (ordinal, name) in &file.ordinals {
+            writeln!(writer, "O {} {}", ordinal, hex(name.as_bytes()))?;
+        }
+        for (export, target) in &file.forwards {
+            writeln!(writer, "F {} {}", hex(export.as_bytes()), hex(target.as_bytes()))?;
+        }
+    }
+    writer.finish()?.flush()
+}
+
+// the nodes of a saved graph, in the saved order
+pub(crate) fn read(path: &str) -> Result<Vec<ParsedFile>> {
+    let mut content = String::new();
+    let read = fs::File::open(path).and_then(|file| GzDecoder::new(file).read_to_string(&mut content));
+    if let Err(error) = read {
+        return Err(Error::Io { path: path.to_string(), error });
+    }
+
+    let mut lines = content.lines();
+    if lines.next() != Some(HEADER) {
+        return Err(Error::Saved { path: path.to_string(), line: 1 });
+    }
+    let mut files: Vec<ParsedFile> = vec![];
+    for (number, line) in lines.enumerate() {
+        let malformed = || Error::Saved { path: path.to_string(), line: number + 2 };
+        let mut fields = line.split(' ');
+        let tag = fields.next().ok_or_else(malformed)?;
+        if tag == "node" {
+            let mut string = || fields.next().and_then(unhex_string).ok_or_else(malformed);
+            let (node_path, label) = (string()?, string()?);
+            files.push(ParsedFile {
+                path: node_path,
+                label,
+                exports: vec![],
+                export_infos: vec![],
+                export_names: vec![],
+                imports: vec![],
+                import_weak: vec![],
+                ordinals: vec![],
+                forwards: vec![],
+                attributes: BTreeMap::new(),
+                warnings: vec![],
+                dropped: vec![],
+                open_time: Duration::ZERO,
+                parse_time: Duration::ZERO,
+            });
+            continue;
+        }
+
+        let file = files.last_mut().ok_or_else(malformed)?;
+        let fields: Vec<&str> = fields.collect();
+        match (tag, fields.as_slice()) {
+            ("attribute", [name, value]) => {
+                let (name, value) = (unhex_string(name).ok_or_else(malformed)?, unhex_string(value).ok_or_else(malformed)?);
+                file.attributes.insert(name, value);
+            }
+            ("E", ["-", name]) => {
+                file.exports.push(unhex_string(name).ok_or_else(malformed)?);
+                file.export_infos.push(None);
+                file.export_names.push(None);
+            }
+            ("E", [kind, size, section, address, version, name]) => {
+                let optional = |value: &str| match value {
+                    "-" => Some(None),
+                    value => unhex_string(value).map(Some),
+                };
+                let info = SymbolInfo {
+                    kind: kind_from_name(kind).ok_or_else(malformed)?,
+                    visibility: Visibility::Default,
+                    weak: false,
+                    size: size.parse().map_err(|_| malformed())?,
+                    section: optional(section).ok_or_else(malformed)?,
+                    address: u64::from_str_radix(address, 16).map_err(|_| malformed())?,
+                    version: optional(version).ok_or_else(malformed)?,
+                    special: None,
+                };
+                file.exports.push(unhex_string(name).ok_or_else(malformed)?);
+                file.export_infos.push(Some(info));
+                file.export_names.push(None);
+            }
+            ("N", [name]) => {
+                let raw = file.export_names.last_mut().ok_or_else(malformed)?;
+                *raw = Some(unhex_string(name).ok_or_else(malformed)?);
+            }
+            ("I", [weak, name]) => {
+                file.imports.push(unhex_string(name).ok_or_else(malformed)?);
+                file.import_weak.push(*weak == "w");
+            }
+            ("O", [ordinal, name]) => {
+                let ordinal = ordinal.parse().map_err(|_| malformed())?;
+                file.ordinals.push((ordinal, unhex_string(name).ok_or_else(malformed)?));
+            }
+            ("F", [export, target]) => {
+                let (export, target) = (unhex_string(export).ok_or_else(malformed)?, unhex_string(target).ok_or_else(malformed)?);
+                file.forwards.push((export, target));
+            }
+            _ => return Err(malformed()),
+        }
+    }
+    Ok(files)
+}
+
+// the names are valid UTF-8 once parsed
+fn unhex_string(hex: &str) -> Option<String> {
+    String::from_utf8(unhex(hex)?).ok()
+}
@@ -0,0 +1,365 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Duration;
+
+use serde::de::{self, Deserialize, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, SerializeStruct, Serializer};
+
+use cache::{kind_from_name, kind_name};
+use filters::{SymbolInfo, Visibility};
+use graph::Graph;
+use parse::ParsedFile;
+
+// the model is the one of Graph::save: the nodes as parsed, their imports
+// resolved, then the edges; the edges are made again from the nodes on
+// deserialization, with the resolution of the graph
+const GRAPH: &[&str] = &["name", "nodes", "edges"];
+const NODE: &[&str] = &["path", "label", "attributes", "exports", "imports", "ordinals", "forwards"];
+const EXPORT: &[&str] = &["name", "kind", "size", "section", "address", "version", "raw_name"];
+const IMPORT: &[&str] = &["name", "weak"];
+const EDGE: &[&str] = &["importer", "exporter", "symbols"];
+const KINDS: &[&str] = &["text", "data", "section", "file", "label", "tls", "unknown"];
+
+struct Node(ParsedFile);
+
+// a symbol, its definition and its symbol table name if mangled, the kind is
+// None without symbol tables
+struct Export(String, Option<SymbolInfo>, Option<String>);
+
+struct Import(String, bool);
+
+struct Edge {
+    importer: String,
+    exporter: String,
+    symbols: Vec<String>,
+}
+
+impl Serialize for Graph {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let resolve = |id: u32| self.strings.resolve(id).unwrap_or_default().to_string();
+        let nodes: Vec<Node> = self.parsed_files().into_iter().map(Node).collect();
+        let mut edges: Vec<Edge> = self.edges.iter()
+            .map(|((importer, exporter), properties)| Edge {
+                importer: resolve(*importer),
+                exporter: resolve(*exporter),
+                symbols: properties.symbols.keys().map(|s| resolve(*s)).collect(),
+            })
+            .collect();
+        edges.sort_by(|a, b| (&a.importer, &a.exporter).cmp(&(&b.importer, &b.exporter)));
+
+        let mut state = serializer.serialize_struct("Graph", GRAPH.len())?;
+        state.serialize_field("name", &self.name)?;
+        state.serialize_field("nodes", &nodes)?;
+        state.serialize_field("edges", &edges)?;
+        state.end()
+    }
+}
+
+impl Serialize for Node {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let file = &self.0;
+        let exports: Vec<Export> = file.exports.iter().zip(&file.export_infos).zip(&file.export_names)
+            .map(|((e, i), r)| Export(e.clone(), i.clone(), r.clone()))
+            .collect();
+        let imports: Vec<Import> = file.imports.iter().zip(&file.import_weak).map(|(i, w)| Import(i.clone(), *w)).collect();
+        let mut state = serializer.serialize_struct("Node", NODE.len())?;
+        state.serialize_field("path", &file.path)?;
+        state.serialize_field("label", &file.label)?;
+        state.serialize_field("attributes", &file.attributes)?;
+        state.serialize_field("exports", &exports)?;
+        state.serialize_field("imports", &imports)?;
+        state.serialize_field("ordinals", &file.ordinals)?;
+        state.serialize_field("forwards", &file.forwards)?;
+        state.end()
+    }
+}
+
+impl Serialize for Export {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let info = self.1.as_ref();
+        let mut state = serializer.serialize_struct("Export", EXPORT.len())?;
+        state.serialize_field("name", &self.0)?;
+        state.serialize_field("kind", &info.map(|i| kind_name(i.kind)))?;
+        state.serialize_field("size", &info.map_or(0, |i| i.size))?;
+        state.serialize_field("section", &info.and_then(|i| i.section.as_ref()))?;
+        state.serialize_field("address", &info.map_or(0, |i| i.address))?;
+        state.serialize_field("version", &info.and_then(|i| i.version.as_ref()))?;
+        state.serialize_field("raw_name", &self.2)?;
+        state.end()
+    }
+}
+
+impl Serialize for Import {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Import", IMPORT.len())?;
+        state.serialize_field("name", &self.0)?;
+        state.serialize_field("weak", &self.1)?;
+        state.end()
+    }
+}
+
+impl Serialize for Edge {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Edge", EDGE.len())?;
+        state.serialize_field("importer", &self.importer)?;
+        state.serialize_field("exporter", &self.exporter)?;
+        state.serialize_field("symbols", &self.symbols)?;
+        state.end()
+    }
+}
+
+// the next field of a struct read as a sequence, in the declared order
+fn element<'de, T: Deserialize<'de>, A: SeqAccess<'de>>(seq: &mut A, index: usize, fields: &[&str]) -> Result<T, A::Error> {
+    seq.next_element()?.ok_or_else(|| de::Error::invalid_length(index, &format!("{} fields", fields.len()).as_str()))
+}
+
+fn required<T, E: de::Error>(value: Option<T>, field: &'static str) -> Result<T, E> {
+    value.ok_or_else(|| de::Error::missing_field(field))
+}
+
+struct GraphVisitor;
+
+impl<'de> Visitor<'de> for GraphVisitor {
+    type Value = Graph;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a symbols graph")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Graph, A::Error> {
+        let name: String = element(&mut seq, 0, GRAPH)?;
+        let nodes: Vec<Node> = element(&mut seq, 1, GRAPH)?;
+        let _: Vec<Edge> = element(&mut seq, 2, GRAPH)?;
+        Ok(graph(&name, nodes))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Graph, A::Error> {
+        let (mut name, mut nodes): (Option<String>, Option<Vec<Node>>) = (None, None);
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "name" => name = Some(map.next_value()?),
+                "nodes" => nodes = Some(map.next_value()?),
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(graph(&required(name, "name")?, required(nodes, "nodes")?))
+    }
+}
+
+// the graph of the nodes, resolved in order as when loading a saved graph
+fn graph(name: &str, nodes: Vec<Node>) -> Graph {
+    let mut graph = Graph::new(name);
+    graph.add_parsed(nodes.into_iter().map(|n| n.0).collect());
+    graph
+}
+
+impl<'de> Deserialize<'de> for Graph {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_struct("Graph", GRAPH, GraphVisitor)
+    }
+}
+
+struct NodeVisitor;
+
+impl<'de> Visitor<'de> for NodeVisitor {
+    type Value = Node;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a node")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Node, A::Error> {
+        let mut file = parsed_file(element(&mut seq, 0, NODE)?, element(&mut seq, 1, NODE)?);
+        file.attributes = element(&mut seq, 2, NODE)?;
+        add_symbols(&mut file, element(&mut seq, 3, NODE)?, element(&mut seq, 4, NODE)?);
+        file.ordinals = element(&mut seq, 5, NODE)?;
+        file.forwards = element(&mut seq, 6, NODE)?;
+        Ok(Node(file))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Node, A::Error> {
+        let (mut path, mut label) = (None, None);
+        let mut file = parsed_file(String::new(), String::new());
+        let (mut exports, mut imports) = (vec![], vec![]);
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "path" => path = Some(map.next_value()?),
+                "label" => label = Some(map.next_value()?),
+                "attributes" => file.attributes = map.next_value()?,
+                "exports" => exports = map.next_value()?,
+                "imports" => imports = map.next_value()?,
+                "ordinals" => file.ordinals = map.next_value()?,
+                "forwards" => file.forwards = map.next_value()?,
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        file.path = required(path, "path")?;
+        file.label = required(label, "label")?;
+        add_symbols(&mut file, exports, imports);
+        Ok(Node(file))
+    }
+}
+
+fn parsed_file(path: String, label: String) -> ParsedFile {
+    ParsedFile {
+        path,
+        label,
+        exports: vec![],
+        export_infos: vec![],
+        export_names: vec![],
+        imports: vec![],
+        import_weak: vec![],
+        ordinals: vec![],
+        forwards: vec![],
+        attributes: BTreeMap::new(),
+        warnings: vec![],
+        dropped: vec![],
+        open_time: Duration::ZERO,
+        parse_time: Duration::ZERO,
+    }
+}
+
+fn add_symbols(file: &mut ParsedFile, exports: Vec<Export>, imports: Vec<Import>) {
+    for Export(name, info, raw) in exports {
+        file.exports.push(name);
+        file.export_infos.push(info);
+        file.export_names.push(raw);
+    }
+    for Import(name, weak) in imports {
+        file.imports.push(name);
+        file.import_weak.push(weak);
+    }
+}
+
+impl<'de> Deserialize<'de> for Node {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_struct("Node", NODE, NodeVisitor)
+    }
+}
+
+struct ExportVisitor;
+
+impl<'de> Visitor<'de> for ExportVisitor {
+    type Value = Export;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an exported symbol")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Export, A::Error> {
+        let name = element(&mut seq, 0, EXPORT)?;
+        let kind: Option<String> = element(&mut seq, 1, EXPORT)?;
+        let (size, section, address, version) = (element(&mut seq, 2, EXPORT)?, element(&mut seq, 3, EXPORT)?,
+                                                 element(&mut seq, 4, EXPORT)?, element(&mut seq, 5, EXPORT)?);
+        export(name, kind, size, section, address, version, element(&mut seq, 6, EXPORT)?)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Export, A::Error> {
+        let (mut name, mut kind, mut size, mut section, mut address, mut version, mut raw) = (None, None, 0, None, 0, None, None);
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "name" => name = Some(map.next_value()?),
+                "kind" => kind = map.next_value()?,
+                "size" => size = map.next_value()?,
+                "section" => section = map.next_value()?,
+                "address" => address = map.next_value()?,
+                "version" => version = map.next_value()?,
+                "raw_name" => raw = map.next_value()?,
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        export(required(name, "name")?, kind, size, section, address, version, raw)
+    }
+}
+
+fn export<E: de::Error>(name: String, kind: Option<String>, size: u64, section: Option<String>, address: u64, version: Option<String>, raw: Option<String>)
+    -> Result<Export, E>
+{
+    let kind = match kind {
+        Some(kind) => kind_from_name(&kind).ok_or_else(|| de::Error::unknown_variant(&kind, KINDS))?,
+        None => return Ok(Export(name, None, raw)),
+    };
+    let info = SymbolInfo { kind, visibility: Visibility::Default, weak: false, size, section, address, version, special: None };
+    Ok(Export(name, Some(info), raw))
+}
+
+impl<'de> Deserialize<'de> for Export {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_struct("Export", EXPORT, ExportVisitor)
+    }
+}
+
+struct ImportVisitor;
+
+impl<'de> Visitor<'de> for ImportVisitor {
+    type Value = Import;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an imported symbol")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Import, A::Error> {
+        Ok(Import(element(&mut seq, 0, IMPORT)?, element(&mut seq, 1, IMPORT)?))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Import, A::Error> {
+        let (mut name, mut weak) = (None, false);
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "name" => name = Some(map.next_value()?),
+                "weak" => weak = map.next_value()?,
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(Import(required(name, "name")?, weak))
+    }
+}
+
+impl<'de> Deserialize<'de> for Import {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_struct("Import", IMPORT, ImportVisitor)
+    }
+}
+
+struct EdgeVisitor;
+
+impl<'de> Visitor<'de> for EdgeVisitor {
+    type Value = Edge;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an edge")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Edge, A::Error> {
+        Ok(Edge { importer: element(&mut seq, 0, EDGE)?, exporter: element(&mut seq, 1, EDGE)?, symbols: element(&mut seq, 2, EDGE)? })
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Edge, A::Error> {
+        let mut edge = Edge { importer: String::new(), exporter: String::new(), symbols: vec![] };
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "importer" => edge.importer = map.next_value()?,
+                "exporter" => edge.exporter = map.next_value()?,
+                "symbols" => edge.symbols = map.next_value()?,
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(edge)
+    }
+}
+
+impl<'de> Deserialize<'de> for Edge {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_struct("Edge", EDGE, EdgeVisitor)
+    }
+}
@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+use std::io;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::str;
+use std::thread;
+
+use symbols_graph::Graph;
+
+use json_string;
+
+// the web UI served at /, backed by the endpoints below
+const INDEX: &str = include_str!("../ui/index.html");
+
+// answer the queries on the parsed graph until interrupted, one thread per connection
+pub(crate) fn serve(graph: &Graph, address: &str) -> io::Result<()> {
+    let listener = TcpListener::bind(address)?;
+    eprintln!("Listening on http://{}", listener.local_addr()?);
+
+    let ids = graph.node_ids();
+    let ids = &ids;
+    thread::scope(|scope| {
+        for stream in listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    scope.spawn(move || {
+                        if let Err(error) = handle(graph, ids, stream) {
+                            warn!("Unable to answer a request : {}", error);
+                        }
+                    });
+                }
+                Err(error) => warn!("Unable to accept a connection : {}", error),
+            }
+        }
+    });
+    Ok(())
+}
+
+fn handle(graph: &Graph, ids: &HashMap<u32, u64>, mut stream: TcpStream) -> io::Result<()> {
+    let mut reader = BufReader::new(&stream);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+
+    // the headers are not used
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let mut fields = request.split_whitespace();
+    let (status, content_type, body) = match (fields.next(), fields.next()) {
+        (Some("GET"), Some(target)) => {
+            debug!("GET {}", target);
+            let (path, query) = match target.find('?') {
+                Some(index) => (&target[..index], query_parameters(&target[index+1..])),
+                None => (target, HashMap::new()),
+            };
+            let segments: Vec<String> = path.split('/').filter(|s| !s.is_empty()).map(percent_decode).collect();
+            let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+            match segments.as_slice() {
+                [] | ["index.html"] => ("200 OK", "text/html; charset=utf-8", INDEX.to_string()),
+                segments => {
+                    let (status, body) = route(graph, ids, segments, &query);
+                    (status, "application/json", body)
+                }
+            }
+        }
+        (Some(_), Some(_)) => ("405 Method Not Allowed", "application/json", error_body("only GET is supported")),
+        _ => ("400 Bad Request", "application/json", error_body("malformed request line")),
+    };
+
+    write!(stream, "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+           status, content_type, body.len(), body)?;
+    stream.flush()
+}
+
+// the status and the JSON body of an endpoint
+//
+//   GET /nodes                          all the nodes
+//   GET /nodes/NAME                     a node and its direct neighbors
+//   GET /providers?symbol=SYMBOL        the nodes exporting SYMBOL
+//   GET /path?from=NAME&to=NAME         the shortest dependency chain
+//   GET /subgraph?root=NAME&depth=N     the dependencies of NAME up to N edges away
+//   GET /clusters                       the clusters and the labels of their nodes
+fn route(graph: &Graph, ids: &HashMap<u32, u64>, segments: &[&str], query: &HashMap<String, String>) -> (&'static str, String) {
+    let find = |name: Option<&String>| match name {
+        Some(name) => graph.find_node(name).ok_or_else(|| format!("unknown node {}", name)),
+        None => Err(String::from("missing node name")),
+    };
+    let result = match segments {
+        ["nodes"] => {
+            let mut nodes: Vec<u32> = graph.nodes().map(|(n, _)| n).collect();
+            nodes.sort_by_key(|n| ids[n]);
+            Ok(format!("[{}]", nodes.iter().map(|n| node_json(graph, ids, *n)).collect::<Vec<_>>().join(",")))
+        }
+        ["nodes", name] => find(Some(&name.to_string())).map(|n| {
+            format!("{{\"node\":{},\"dependencies\":{},\"dependents\":{}}}",
+                    node_json(graph, ids, n), labels_json(graph, &graph.dependencies_of(n)), labels_json(graph, &graph.dependents_of(n)))
+        }),
+        ["providers"] => match query.get("symbol") {
+            Some(symbol) => Ok(labels_json(graph, &graph.providers_of_symbol(symbol))),
+            None => Err(String::from("missing symbol")),
+        },
+        ["path"] => find(query.get("from")).and_then(|from| {
+            let to = find(query.get("to"))?;
+            match graph.shortest_path(from, to) {
+                Some(path) => Ok(format!("{{\"path\":{}}}", labels_json(graph, &path))),
+                None => Err(String::from("no dependency path")),
+            }
+        }),
+        ["subgraph"] => find(query.get("root")).and_then(|root| {
+            let depth = match query.get("depth") {
+                Some(depth) => depth.parse().map_err(|_| format!("invalid depth {}", depth))?,
+                None => 1,
+            };
+            Ok(subgraph_json(graph, ids, root, depth))
+        }),
+        ["clusters"] => {
+            let clusters: Vec<String> = graph.clusters()
+                .map(|(name, nodes)| {
+                    let nodes: Vec<u32> = nodes.into_iter().collect();
+                    format!("{{\"name\":{},\"nodes\":{}}}", json_string(name), labels_json(graph, &nodes))
+                })
+                .collect();
+            Ok(format!("[{}]", clusters.join(",")))
+        }
+        _ => return ("404 Not Found", error_body("unknown endpoint")),
+    };
+
+    match result {
+        Ok(body) => ("200 OK", body),
+        Err(message) if message.starts_with("missing") || message.starts_with("invalid") => ("400 Bad Request", error_body(&message)),
+        Err(message) => ("404 Not Found", error_body(&message)),
+    }
+}
+
+fn node_json(graph: &Graph, ids: &HashMap<u32, u64>, node: u32) -> String {
+    let exports = graph.nodes().find(|(n, _)| *n == node).map(|(_, p)| p.symbols().len()).unwrap_or(0);
+    format!("{{\"id\":{},\"label\":{},\"exports\":{}}}", ids[&node], json_string(graph.label(node).unwrap_or_default()), exports)
+}
+
+fn labels_json(graph: &Graph, nodes: &[u32]) -> String {
+    let labels: Vec<String> = nodes.iter().filter_map(|n| graph.label(*n)).map(json_string).collect();
+    format!("[{}]", labels.join(","))
+}
+
+fn subgraph_json(graph: &Graph, ids: &HashMap<u32, u64>, root: u32, depth: usize) -> String {
+    let nodes = graph.reachable(root, depth);
+    let mut edges: Vec<String> = graph.edges()
+        .filter(|((n1, n2), _)| nodes.contains(n1) && nodes.contains(n2))
+        .map(|((n1, n2), p)| {
+            let (from, to) = graph.edge_direction.orient(ids[&n1], ids[&n2]);
+            let symbols: Vec<String> = p.imports().map(json_string).collect();
+            format!("{{\"from\":{},\"to\":{},\"symbols\":[{}]}}", from, to, symbols.join(","))
+        })
+        .collect();
+    edges.sort();
+    let nodes: Vec<String> = nodes.iter().map(|n| node_json(graph, ids, *n)).collect();
+    format!("{{\"nodes\":[{}],\"edges\":[{}]}}", nodes.join(","), edges.join(","))
+}
+
+fn error_body(message: &str) -> String {
+    format!("{{\"error\":{}}}", json_string(message))
+}
+
+fn query_parameters(query: &str) -> HashMap<String, String> {
+    query.split('&')
+        .filter_map(|p| {
+            let index = p.find('=')?;
+            Some((percent_decode(&p[..index]), percent_decode(&p[index+1..])))
+        })
+        .collect()
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                let hex = str::from_utf8(&bytes[i+1..i+3]).ok().and_then(|h| u8::from_str_radix(h, 16).ok());
+                match hex {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 3;
+                        continue;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            b'+' => decoded.push(b' '),
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
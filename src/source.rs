@@ -0,0 +1,109 @@
+use std::collections::BTreeMap;
+
+use error::{Error, Result};
+use parse::{self, FileSymbols, RawSymbol};
+
+/// A reader of the inputs in a format of its own, eg. linker map files or
+/// firmware images, yielding their exports, imports and attributes.
+///
+/// The [`Graph::sources`](crate::Graph::sources) are asked in order before
+/// the file is parsed as an object file, the first one reading it provides
+/// its symbols; the [`Filters`](crate::Filters) apply to them as to the object
+/// files:
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use symbols_graph::{Error, Graph, Result, SymbolSource, Symbols};
+/// // lines "E symbol" or "I symbol"
+/// struct Listing;
+///
+/// impl SymbolSource for Listing {
+///     fn read(&self, path: &str) -> Option<Result<Symbols>> {
+///         if !path.ends_with(".syms") {
+///             return None;
+///         }
+///         let content = match std::fs::read_to_string(path) {
+///             Ok(content) => content,
+///             Err(error) => return Some(Err(Error::Source { path: path.to_string(), message: error.to_string() })),
+///         };
+///         Some(Ok(content.lines().fold(Symbols::new(), |symbols, line| match line.split_once(' ') {
+///             Some(("E", name)) => symbols.export(name),
+///             Some(("I", name)) => symbols.import(name),
+///             _ => symbols,
+///         })))
+///     }
+/// }
+///
+/// let mut graph = Graph::new("deps");
+/// graph.sources.push(Arc::new(Listing));
+/// ```
+pub trait SymbolSource: Send + Sync {
+    /// The symbols of the file at `path`, None when it is not in the format
+    /// of the source.
+    fn read(&self, path: &str) -> Option<Result<Symbols>>;
+}
+
+/// The exports, imports and attributes of a file read by a [`SymbolSource`].
+pub struct Symbols(pub(crate) FileSymbols);
+
+impl Symbols {
+    /// No symbols nor attributes, added by the other methods.
+    pub fn new() -> Self {
+        Symbols(FileSymbols {
+            pe: false,
+            coff: false,
+            soname: None,
+            attributes: BTreeMap::new(),
+            exports: vec![],
+            imports: vec![],
+            ordinals: vec![],
+            forwards: vec![],
+        })
+    }
+
+    /// Add a symbol defined by the file.
+    pub fn export(mut self, name: &str) -> Self {
+        self.0.exports.push(RawSymbol { name: name.as_bytes().to_vec(), info: None });
+        self
+    }
+
+    /// Add a symbol the file needs from another.
+    pub fn import(mut self, name: &str) -> Self {
+        self.0.imports.push(RawSymbol { name: name.as_bytes().to_vec(), info: None });
+        self
+    }
+
+    /// Set an attribute of the node, eg. `arch`, the `soname` filtered as the
+    /// ELF one or the space separated `needed` libraries.
+    pub fn attribute(mut self, name: &str, value: &str) -> Self {
+        if name == "soname" {
+            self.0.soname = Some(value.to_string());
+        }
+        self.0.attributes.insert(name.to_string(), value.to_string());
+        self
+    }
+}
+
+impl Default for Symbols {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The ELF, Mach-O, PE and COFF files read with the object crate, as the
+/// files no source reads are; it reads nothing else.
+pub struct ObjectSource;
+
+impl SymbolSource for ObjectSource {
+    fn read(&self, path: &str) -> Option<Result<Symbols>> {
+        let memory = match parse::map(path, true) {
+            Ok(memory) => memory,
+            Err(error) => return Some(Err(error)),
+        };
+        match parse::extract(path, &memory, false) {
+            Ok(symbols) => Some(Ok(Symbols(symbols))),
+            Err(Error::Parse { .. }) => None,
+            Err(error) => Some(Err(error)),
+        }
+    }
+}
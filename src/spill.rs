@@ -0,0 +1,119 @@
+use std::collections::HashMap;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use cache::fnv1a;
+
+// small enough to resolve one partition of a distribution in memory
+const PARTITIONS: usize = 64;
+
+// the spills created so far, naming the next directory
+static SPILLS: AtomicUsize = AtomicUsize::new(0);
+
+// the symbols of the streamed files kept on disk, split by symbol into
+// partitions resolved one at a time
+pub(crate) struct Spill {
+    dir: PathBuf,
+    partitions: Vec<BufWriter<File>>,
+}
+
+impl Spill {
+    // a fresh directory inside `parent`, removed once resolved; one per spill
+    // of the process, never one left by another
+    pub(crate) fn create(parent: &Path) -> io::Result<Self> {
+        let nth = SPILLS.fetch_add(1, Ordering::Relaxed);
+        let dir = parent.join(format!("symbols-graph-spill-{}-{}", process::id(), nth));
+        fs::create_dir_all(parent)?;
+        fs::create_dir(&dir)?;
+        let partitions = (0..PARTITIONS)
+            .map(|i| File::create(dir.join(format!("{:02}", i))).map(BufWriter::new))
+            .collect::<io::Result<_>>()?;
+        Ok(Self { dir, partitions })
+    }
+
+    // the files are expected in order, their exports with their size before
+    // their imports
+    pub(crate) fn add_export(&mut self, node: u32, size: u64, symbol: &str) -> io::Result<()> {
+        let partition = partition(symbol);
+        writeln!(self.partitions[partition], "E {} {} {}", node, size, symbol)
+    }
+
+    // the weak imports are marked w, the others -
+    pub(crate) fn add_import(&mut self, node: u32, weak: bool, symbol: &str) -> io::Result<()> {
+        let partition = partition(symbol);
+        writeln!(self.partitions[partition], "I {} {} {}", node, if weak { "w" } else { "-" }, symbol)
+    }
+
+    // call on_edge with each resolved (importer, exporter, symbol, size, weak),
+    // to every exporter when all is set else to the first one, returns the
+    // number of unresolved symbols
+    pub(crate) fn resolve<F>(mut self, all: bool, mut on_edge: F) -> io::Result<usize>
+        where F: FnMut(u32, u32, &str, u64, bool) -> io::Result<()>
+    {
+        for partition in &mut self.partitions {
+            partition.flush()?;
+        }
+
+        let mut unresolved = 0;
+        for i in 0..PARTITIONS {
+            // defined symbol -> libs and sizes, undefined and resolved symbol -> libs, as in the stream
+            let mut defined: HashMap<String, Vec<(u32, u64)>> = HashMap::new();
+            let mut undefined: HashMap<String, Vec<(u32, bool)>> = HashMap::new();
+            let mut imported: HashMap<String, Vec<(u32, bool)>> = HashMap::new();
+
+            let reader = BufReader::new(File::open(self.dir.join(format!("{:02}", i)))?);
+            for line in reader.lines() {
+                let line = line?;
+                let mut fields = line.splitn(4, ' ');
+                let (kind, node, value, symbol) = match (fields.next(), fields.next().and_then(|n| n.parse().ok()), fields.next(), fields.next()) {
+                    (Some(kind), Some(node), Some(value), Some(symbol)) => (kind, node, value, symbol),
+                    _ => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("malformed spill line {}", line))),
+                };
+
+                if kind == "E" {
+                    let size = value.parse().unwrap_or(0);
+                    defined.entry(symbol.to_string()).or_default().push((node, size));
+                    let libs = match undefined.remove(symbol) {
+                        Some(libs) => libs,
+                        None if all => imported.get(symbol).cloned().unwrap_or_default(),
+                        None => vec![],
+                    };
+                    for (lib, weak) in &libs {
+                        on_edge(*lib, node, symbol, size, *weak)?;
+                    }
+                    if all && !libs.is_empty() && !imported.contains_key(symbol) {
+                        imported.insert(symbol.to_string(), libs);
+                    }
+                } else if let Some(libs) = defined.get(symbol) {
+                    let weak = value == "w";
+                    let libs = if all { &libs[..] } else { &libs[..1] };
+                    for (lib, size) in libs {
+                        on_edge(node, *lib, symbol, *size, weak)?;
+                    }
+                    if all {
+                        imported.entry(symbol.to_string()).or_default().push((node, weak));
+                    }
+                } else {
+                    undefined.entry(symbol.to_string()).or_default().push((node, value == "w"));
+                }
+            }
+            unresolved += undefined.len();
+        }
+        Ok(unresolved)
+    }
+}
+
+fn partition(symbol: &str) -> usize {
+    (fnv1a(symbol.as_bytes()) % PARTITIONS as u64) as usize
+}
+
+impl Drop for Spill {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
@@ -0,0 +1,416 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use dot::{dashed, escape, fill_attributes, graph_id, highlight_attributes, layout_attributes, title_attributes, tooltip, Highlight};
+use error::Error;
+use filters::Filters;
+use graph::{node_id, EdgeDirection, Resolution};
+use parse;
+use parse::{ParsedFile, PeNames};
+use source::SymbolSource;
+use spill::Spill;
+use strings::Strings;
+
+/// Writes the DOT graph while a [`ParseStream`] parses the files, for inputs
+/// too large to build a [`Graph`](::Graph) in memory.
+///
+/// Each node is written once parsed and each edge as soon as both of its ends
+/// are known. The statements are the ones of [`DotWriter`](::DotWriter)
+/// without options, in another order.
+///
+/// ```no_run
+/// # use symbols_graph::{DotStream, ParseStream};
+/// # fn main() -> std::io::Result<()> {
+/// let mut stream = ParseStream::new(DotStream::new(std::io::stdout(), "deps")?);
+/// let failed = stream.parse_files(&["libfoo.so".to_string()], 4, |_| ())?;
+/// stream.finish()?.finish()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct DotStream<'a, W: Write> {
+    writer: W,
+    /// Which way the edges point ([`EdgeDirection::Uses`] by default).
+    pub edge_direction: EdgeDirection,
+    highlights: &'a [Highlight],
+
+    // the DOT ids of the written nodes by path and their labels, as in
+    // Graph::node_ids
+    ids: HashMap<String, u64>,
+    labels: HashSet<String>,
+}
+
+/// The callbacks of a [`ParseStream`], each one doing nothing by default.
+///
+/// The nodes are visited in the order of the files, each with its symbols
+/// before its edges; the edges are named by the paths of their ends.
+pub trait ParseVisitor {
+    /// A parsed file, before its symbols.
+    fn visit_node(&mut self, path: &str, label: &str, attributes: &BTreeMap<String, String>) -> io::Result<()> {
+        let _ = (path, label, attributes);
+        Ok(())
+    }
+
+    /// A symbol the file at `path` defines.
+    fn visit_export(&mut self, path: &str, symbol: &str) -> io::Result<()> {
+        let _ = (path, symbol);
+        Ok(())
+    }
+
+    /// A symbol the file at `path` needs, once each.
+    fn visit_import(&mut self, path: &str, symbol: &str, weak: bool) -> io::Result<()> {
+        let _ = (path, symbol, weak);
+        Ok(())
+    }
+
+    /// An import of `importer` resolved to `exporter`, with the size of the
+    /// symbol, as soon as both are parsed.
+    fn visit_edge(&mut self, importer: &str, exporter: &str, symbol: &str, size: u64, weak: bool) -> io::Result<()> {
+        let _ = (importer, exporter, symbol, size, weak);
+        Ok(())
+    }
+}
+
+/// Parses the files and resolves their imports while calling the visitor,
+/// eg. a [`DotStream`] or one storing the symbols elsewhere, only the symbol
+/// tables needed to resolve the imports are kept.
+///
+/// ```no_run
+/// # use std::io;
+/// # use symbols_graph::{ParseStream, ParseVisitor};
+/// # fn main() -> io::Result<()> {
+/// // the library pairs
+/// #[derive(Default)]
+/// struct Pairs(Vec<(String, String)>);
+///
+/// impl ParseVisitor for Pairs {
+///     fn visit_edge(&mut self, importer: &str, exporter: &str, _: &str, _: u64, _: bool) -> io::Result<()> {
+///         self.0.push((importer.to_string(), exporter.to_string()));
+///         Ok(())
+///     }
+/// }
+///
+/// let mut stream = ParseStream::new(Pairs::default());
+/// let failed = stream.parse_files(&["libfoo.so".to_string()], 4, |_| ())?;
+/// let pairs = stream.finish()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ParseStream<V: ParseVisitor> {
+    visitor: V,
+    /// Options applied while parsing.
+    pub filters: Filters,
+    /// Directory keeping the symbols of the parsed files.
+    pub cache: Option<PathBuf>,
+    /// Map the files in memory rather than reading them (true by default).
+    pub mmap: bool,
+    /// Readers of the inputs other than object files.
+    pub sources: Vec<Arc<dyn SymbolSource>>,
+    /// Which of the libraries exporting a symbol its importers depend on,
+    /// [`Resolution::ElfOrder`] needs the whole graph and is resolved as
+    /// [`Resolution::First`].
+    pub resolution: Resolution,
+
+    // the paths of the visited nodes, naming the edges
+    paths: HashMap<u32, String>,
+    linker: Linker,
+}
+
+// the symbol tables of the streamed files, resolving each import once a file
+// exporting it is parsed
+struct Linker {
+    strings: Strings,
+    warnings: Vec<Error>,
+    nodes: usize,
+    // the resolved library pairs, for the counts
+    edges: HashSet<(u32, u32)>,
+
+    // defined symbol -> libs and sizes
+    defined: HashMap<u32, Vec<(u32, u64)>>,
+    // undefined symbol -> libs and weak references, until a later lib defines it
+    undefined: HashMap<u32, Vec<(u32, bool)>>,
+    // resolved symbol -> libs and weak references, for the later providers
+    // with Resolution::All
+    imported: HashMap<u32, Vec<(u32, bool)>>,
+    // the PE exports by ordinal and forwarded, for the later importers
+    pe_names: PeNames,
+
+    // the symbols kept on disk instead, resolved once all the files are parsed
+    spill: Option<Spill>,
+    spilled_unresolved: usize,
+}
+
+impl<'a, W: Write> DotStream<'a, W> {
+    /// Start the graph, writing its header.
+    pub fn new(mut writer: W, name: &str) -> io::Result<Self> {
+        writeln!(writer, "digraph {} {{", graph_id(name))?;
+        Ok(Self {
+            writer,
+            edge_direction: EdgeDirection::Uses,
+            highlights: &[],
+
+            ids: HashMap::new(),
+            labels: HashSet::new(),
+        })
+    }
+
+    /// Label the graph with `title`, under the diagram.
+    pub fn title(mut self, title: &str) -> io::Result<Self> {
+        write!(self.writer, "{}", title_attributes(title))?;
+        Ok(self)
+    }
+
+    /// Set these attributes of the graph and the font size of the node
+    /// labels, as [`DotWriter::graph_attributes`](::DotWriter::graph_attributes)
+    /// and [`DotWriter::font_size`](::DotWriter::font_size).
+    pub fn layout(mut self, attributes: &[(String, String)], font_size: Option<f64>) -> io::Result<Self> {
+        write!(self.writer, "{}", layout_attributes(attributes, font_size))?;
+        Ok(self)
+    }
+
+    /// Color the nodes and the symbol edges matching the highlights.
+    pub fn highlights(mut self, highlights: &'a [Highlight]) -> Self {
+        self.highlights = highlights;
+        self
+    }
+
+    /// End the graph, once [`ParseStream::finish`] visited the last edges,
+    /// returning the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        writeln!(self.writer, "}}")?;
+        Ok(self.writer)
+    }
+}
+
+impl<'a, W: Write> ParseVisitor for DotStream<'a, W> {
+    fn visit_node(&mut self, path: &str, label: &str, attributes: &BTreeMap<String, String>) -> io::Result<()> {
+        let id = node_id(&mut self.labels, label, path);
+        self.ids.insert(path.to_string(), id);
+        let highlight = highlight_attributes(self.highlights, label);
+        writeln!(self.writer, "    n{} [label=\"{}\"{}{}]", id, escape(label), fill_attributes(attributes), highlight)
+    }
+
+    fn visit_edge(&mut self, importer: &str, exporter: &str, symbol: &str, size: u64, weak: bool) -> io::Result<()> {
+        let (from, to) = self.edge_direction.orient(self.ids[importer], self.ids[exporter]);
+        writeln!(self.writer, "    n{} -> n{} [label=\"{}\"{}{}{}]", from, to, escape(symbol), tooltip(size), highlight_attributes(self.highlights, symbol), dashed(weak))
+    }
+}
+
+impl<V: ParseVisitor> ParseStream<V> {
+    pub fn new(visitor: V) -> Self {
+        Self {
+            visitor,
+            filters: Filters::default(),
+            cache: None,
+            mmap: true,
+            sources: Vec::new(),
+            resolution: Resolution::First,
+
+            paths: HashMap::new(),
+            linker: Linker::new(),
+        }
+    }
+
+    /// Keep the symbol tables in temporary files inside `dir` rather than in
+    /// memory, the edges are visited after all the nodes.
+    pub fn spill(mut self, dir: &Path) -> io::Result<Self> {
+        self.linker.spill = Some(Spill::create(dir)?);
+        Ok(self)
+    }
+
+    /// Parse the files on `jobs` threads, visiting them in order.
+    ///
+    /// At most a few files per thread are held in memory at once. Returns the
+    /// errors of the files left out.
+    pub fn parse_files<F>(&mut self, files: &[String], jobs: usize, on_file: F) -> io::Result<Vec<Error>>
+        where F: Fn(&str) + Sync
+    {
+        let mut errors = vec![];
+        for chunk in files.chunks(jobs.max(1) * 4) {
+            let results = parse::parse_all(chunk, jobs, &self.filters, self.cache.as_deref(), self.mmap, &self.sources, &on_file);
+            for parsed in self.linker.collect(results, &self.filters, &mut errors) {
+                self.visit_parsed(parsed)?;
+            }
+        }
+        Ok(errors)
+    }
+
+    fn visit_parsed(&mut self, parsed: ParsedFile) -> io::Result<()> {
+        let node = self.linker.node(&parsed.path);
+        self.visitor.visit_node(&parsed.path, &parsed.label, &parsed.attributes)?;
+        let mut seen = HashSet::with_capacity(parsed.exports.len().max(parsed.imports.len()));
+        for symbol in parsed.exports.iter().filter(|s| seen.insert(s.as_str())) {
+            self.visitor.visit_export(&parsed.path, symbol)?;
+        }
+        seen.clear();
+        for (symbol, weak) in parsed.imports.iter().zip(&parsed.import_weak).filter(|(s, _)| seen.insert(s.as_str())) {
+            self.visitor.visit_import(&parsed.path, symbol, *weak)?;
+        }
+        self.paths.insert(node, parsed.path.clone());
+
+        let all = self.resolution == Resolution::All;
+        let (visitor, paths) = (&mut self.visitor, &self.paths);
+        self.linker.add(node, parsed, all, |n1, n2, symbol, size, weak| visitor.visit_edge(&paths[&n1], &paths[&n2], symbol, size, weak))
+    }
+
+    /// The recoverable errors met while parsing, eg. skipped symbols.
+    pub fn warnings(&self) -> &[Error] {
+        &self.linker.warnings
+    }
+
+    /// The number of nodes and of library pairs visited so far.
+    pub fn counts(&self) -> (usize, usize) {
+        (self.linker.nodes, self.linker.edges.len())
+    }
+
+    /// The number of imported symbols no parsed file exports yet.
+    pub fn unresolved_count(&self) -> usize {
+        self.linker.unresolved_count()
+    }
+
+    /// Visit the edges of the spilled symbols, once all the files are parsed.
+    ///
+    /// Called by [`ParseStream::finish`], call it before to count them.
+    pub fn resolve_spilled(&mut self) -> io::Result<()> {
+        let all = self.resolution == Resolution::All;
+        let (visitor, paths) = (&mut self.visitor, &self.paths);
+        self.linker.resolve_spilled(all, |n1, n2, symbol, size, weak| visitor.visit_edge(&paths[&n1], &paths[&n2], symbol, size, weak))
+    }
+
+    /// Visit the last edges, returning the visitor.
+    pub fn finish(mut self) -> io::Result<V> {
+        self.resolve_spilled()?;
+        Ok(self.visitor)
+    }
+}
+
+impl Linker {
+    fn new() -> Self {
+        Self {
+            strings: Strings::new(),
+            warnings: Vec::new(),
+            nodes: 0,
+            edges: HashSet::new(),
+
+            defined: HashMap::new(),
+            undefined: HashMap::new(),
+            imported: HashMap::new(),
+            pe_names: PeNames::default(),
+
+            spill: None,
+            spilled_unresolved: 0,
+        }
+    }
+
+    // the parsed files of a chunk, their PE imports named after the exports
+    // of the previous chunks
+    fn collect(&mut self, results: Vec<Result<Vec<ParsedFile>, Error>>, filters: &Filters, errors: &mut Vec<Error>) -> Vec<ParsedFile> {
+        let mut parsed_files = vec![];
+        for result in results {
+            match result {
+                Ok(parsed) => parsed_files.extend(parsed),
+                Err(error) => errors.push(error),
+            }
+        }
+        parse::name_pe_imports(&mut parsed_files, filters, &mut self.pe_names);
+        parsed_files
+    }
+
+    fn node(&mut self, path: &str) -> u32 {
+        self.nodes += 1;
+        self.strings.get_or_intern(path)
+    }
+
+    // resolve the symbols of the file, calling on_edge with the importer, the
+    // exporter, the symbol, its size and whether the import is weak
+    fn add<F>(&mut self, node: u32, parsed: ParsedFile, all: bool, mut on_edge: F) -> io::Result<()>
+        where F: FnMut(u32, u32, &str, u64, bool) -> io::Result<()>
+    {
+        self.warnings.extend(parsed.warnings);
+        let edges = &mut self.edges;
+        let mut on_edge = |n1, n2, symbol: &str, size, weak| {
+            edges.insert((n1, n2));
+            on_edge(n1, n2, symbol, size, weak)
+        };
+
+        if let Some(spill) = &mut self.spill {
+            let mut seen = HashSet::new();
+            for (symbol_name, info) in parsed.exports.iter().zip(&parsed.export_infos).filter(|(s, _)| seen.insert(s.as_str())) {
+                spill.add_export(node, info.as_ref().map_or(0, |i| i.size), symbol_name)?;
+            }
+            seen.clear();
+            for (symbol_name, weak) in parsed.imports.iter().zip(&parsed.import_weak).filter(|(s, _)| seen.insert(s.as_str())) {
+                spill.add_import(node, *weak, symbol_name)?;
+            }
+            return Ok(());
+        }
+
+        // each symbol once, as in the graph edges
+        self.defined.reserve(parsed.exports.len());
+        let mut seen = HashSet::with_capacity(parsed.exports.len().max(parsed.imports.len()));
+        for (symbol_name, info) in parsed.exports.iter().zip(&parsed.export_infos) {
+            let symbol = self.strings.get_or_intern(symbol_name.as_str());
+            if !seen.insert(symbol) {
+                continue;
+            }
+            let size = info.as_ref().map_or(0, |i| i.size);
+            self.defined.entry(symbol).or_default().push((node, size));
+
+            // the previous libs waiting for it, or already resolved
+            let libs = match self.undefined.remove(&symbol) {
+                Some(libs) if all => {
+                    self.imported.entry(symbol).or_default().extend(libs.iter().cloned());
+                    libs
+                }
+                Some(libs) => libs,
+                None if all => self.imported.get(&symbol).cloned().unwrap_or_default(),
+                None => vec![],
+            };
+            for (lib, weak) in libs {
+                on_edge(lib, node, symbol_name, size, weak)?;
+            }
+        }
+
+        seen.clear();
+        for (symbol_name, weak) in parsed.imports.iter().zip(&parsed.import_weak) {
+            let symbol = self.strings.get_or_intern(symbol_name.as_str());
+            if !seen.insert(symbol) {
+                continue;
+            }
+            let providers = match self.defined.get(&symbol) {
+                Some(libs) if all => {
+                    self.imported.entry(symbol).or_default().push((node, *weak));
+                    libs.clone()
+                }
+                Some(libs) => libs[..1].to_vec(),
+                None => {
+                    self.undefined.entry(symbol).or_default().push((node, *weak));
+                    continue;
+                }
+            };
+            for (lib, size) in providers {
+                on_edge(node, lib, symbol_name, size, *weak)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn unresolved_count(&self) -> usize {
+        self.undefined.len() + self.spilled_unresolved
+    }
+
+    fn resolve_spilled<F>(&mut self, all: bool, mut on_edge: F) -> io::Result<()>
+        where F: FnMut(u32, u32, &str, u64, bool) -> io::Result<()>
+    {
+        if let Some(spill) = self.spill.take() {
+            let edges = &mut self.edges;
+            self.spilled_unresolved = spill.resolve(all, |n1, n2, symbol, size, weak| {
+                edges.insert((n1, n2));
+                on_edge(n1, n2, symbol, size, weak)
+            })?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,35 @@
+use string_interner;
+use string_interner::{Sym, Symbol};
+
+// the interned paths and symbol names, with 32 bits ids to halve the keys
+// of the graph maps on 64 bits hosts
+#[derive(Debug, Clone)]
+pub(crate) struct Strings {
+    interner: string_interner::StringInterner<Sym>,
+}
+
+impl Strings {
+    pub(crate) fn new() -> Self {
+        Self { interner: string_interner::StringInterner::new() }
+    }
+
+    // room for `additional` more strings without rehashing
+    pub(crate) fn reserve(&mut self, additional: usize) {
+        self.interner.reserve(additional);
+    }
+
+    pub(crate) fn get_or_intern<T: Into<String> + AsRef<str>>(&mut self, value: T) -> u32 {
+        self.interner.get_or_intern(value).to_usize() as u32
+    }
+
+    pub(crate) fn get(&self, value: &str) -> Option<u32> {
+        self.interner.get(value).map(|s| s.to_usize() as u32)
+    }
+
+    pub(crate) fn resolve(&self, id: u32) -> Option<&str> {
+        match id {
+            u32::MAX => None,
+            id => self.interner.resolve(Sym::from_usize(id as usize)),
+        }
+    }
+}
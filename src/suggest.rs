@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use graph::Graph;
+
+/// The exports of a library no other node imports, the candidates to hide.
+#[derive(Debug, Clone)]
+pub struct HiddenExports<'a> {
+    pub node: u32,
+    pub label: &'a str,
+    /// The exports not imported, sorted.
+    pub unused: Vec<&'a str>,
+    /// The exports to keep public, as named in the symbol tables for the
+    /// version scripts and the `.def` files, sorted.
+    pub used: Vec<&'a str>,
+    /// Whether the library is a PE one, exported by `.def` files rather than
+    /// version scripts.
+    pub pe: bool,
+}
+
+impl<'a> HiddenExports<'a> {
+    /// The libraries with exports to hide, sorted by label.
+    pub fn all(graph: &'a Graph) -> Vec<Self> {
+        let mut nodes: Vec<(&str, u32)> = graph.nodes()
+            .filter(|(_, p)| p.symbols().len() > 0)
+            .filter_map(|(n, _)| graph.label(n).map(|l| (l, n)))
+            .collect();
+        nodes.sort();
+
+        let mut hidden = vec![];
+        for (label, node) in nodes {
+            let unused = graph.unused_exports(node);
+            if unused.is_empty() {
+                continue;
+            }
+            // as named in the symbol tables, not mangled for the graph
+            let mut used: Vec<&str> = graph.nodes()
+                .filter(|(n, _)| *n == node)
+                .flat_map(|(_, p)| p.symbols().filter_map(move |s| Some((graph.symbol(*s)?, p.raw_name(*s)?))))
+                .filter(|(s, _)| unused.binary_search(s).is_err())
+                .map(|(_, raw)| raw)
+                .collect();
+            used.sort();
+            let pe = graph.path(node).is_some_and(|p| p.ends_with(".dll") || p.ends_with(".exe"));
+            hidden.push(HiddenExports { node, label, unused, used, pe });
+        }
+        hidden
+    }
+}
+
+/// The link line changes of a build target fixing its under- and
+/// overlinking.
+#[derive(Debug, Clone)]
+pub struct LinkChanges<'a> {
+    pub node: u32,
+    pub label: &'a str,
+    /// The target of the node.
+    pub target: &'a str,
+    /// The libraries to link, as targets or paths, with the number of
+    /// symbols imported from them and the first one.
+    pub added: Vec<(&'a str, usize, &'a str)>,
+    /// The libraries linked but not imported from, as targets or paths.
+    pub removed: Vec<&'a str>,
+}
+
+impl<'a> LinkChanges<'a> {
+    /// The changes of the nodes with a target, sorted by label. The target of
+    /// a node is looked up in `targets` by label, soname or file name.
+    pub fn all(graph: &'a Graph, targets: &'a HashMap<String, String>) -> Vec<Self> {
+        let target = |node: u32| -> Option<&'a str> {
+            let properties = graph.nodes().find(|(n, _)| *n == node).map(|(_, p)| p)?;
+            let path = graph.path(node)?;
+            let file_name = Path::new(path).file_name().and_then(|f| f.to_str());
+            [graph.label(node), properties.attributes().get("soname").map(String::as_str), file_name].iter()
+                .flatten()
+                .find_map(|name| targets.get(*name))
+                .map(String::as_str)
+        };
+        let library = |node: u32| target(node).or_else(|| graph.path(node)).unwrap_or_default();
+
+        let mut nodes: Vec<(&str, u32)> = graph.nodes().filter_map(|(n, _)| Some((graph.label(n)?, n))).collect();
+        nodes.sort();
+        let mut changes = vec![];
+        for (label, node) in nodes {
+            let importer = match target(node) {
+                Some(target) => target,
+                None => continue,
+            };
+            let (missing, unused) = (graph.underlinked(node), graph.overlinked(node));
+            if missing.is_empty() && unused.is_empty() {
+                continue;
+            }
+            let added = missing.into_iter()
+                .map(|exporter| {
+                    let symbols = graph.edges().find(|(e, _)| *e == (node, exporter)).map(|(_, p)| p);
+                    let first = symbols.and_then(|p| p.symbols().next()).and_then(|s| graph.symbol(*s)).unwrap_or_default();
+                    (library(exporter), symbols.map_or(0, |p| p.symbols_count()), first)
+                })
+                .collect();
+            let removed = unused.into_iter().map(library).collect();
+            changes.push(LinkChanges { node, label, target: importer, added, removed });
+        }
+        changes
+    }
+}
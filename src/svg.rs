@@ -0,0 +1,255 @@
+use std::fmt;
+use std::fmt::Display;
+
+use dot::DotWriter;
+
+/// Renders a [`Graph`](crate::Graph) as SVG, laid out without Graphviz.
+///
+/// The drawing is the one of [`DotWriter::xdot`], with all the rendering
+/// options of the `DotWriter`:
+///
+/// ```no_run
+/// # use symbols_graph::{DotWriter, Graph, SvgWriter};
+/// # fn main() -> symbols_graph::Result<()> {
+/// let mut graph = Graph::new("deps");
+/// graph.parse_file("libfoo.so")?;
+/// print!("{}", SvgWriter::new(DotWriter::new(&graph).detailed_labels(true)));
+/// # Ok(())
+/// # }
+/// ```
+pub struct SvgWriter<'a> {
+    dot: DotWriter<'a>,
+}
+
+// the empty border around the drawing, in points
+const MARGIN: f64 = 4.0;
+
+impl<'a> SvgWriter<'a> {
+    pub fn new(dot: DotWriter<'a>) -> Self {
+        SvgWriter { dot: dot.xdot(true) }
+    }
+}
+
+impl<'a> Display for SvgWriter<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let xdot = self.dot.to_string();
+        let (mut width, mut height, mut sized) = (0.0, 0.0, false);
+        let mut comments = vec![];
+        let mut body = String::new();
+        for line in xdot.lines() {
+            if let Some(comment) = line.strip_prefix("// ") {
+                comments.push(comment);
+                continue;
+            }
+            let attributes = attributes(line);
+            let value = |name: &str| attributes.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str());
+            // the first bounding box is the graph one, the clusters follow
+            if let Some(bb) = value("bb").filter(|_| !sized) {
+                let corners: Vec<f64> = bb.split(',').filter_map(|v| v.parse().ok()).collect();
+                if let [_, _, w, h] = corners.as_slice() {
+                    width = *w;
+                    height = *h;
+                    sized = true;
+                }
+            }
+            let drawing: String = ["_draw_", "_hdraw_", "_ldraw_"].iter()
+                .filter_map(|name| value(name))
+                .map(|operations| elements(operations, height))
+                .collect();
+            if drawing.is_empty() {
+                continue;
+            }
+            body += "<g>\n";
+            if let Some(tooltip) = value("tooltip") {
+                body += &format!("<title>{}</title>\n", xml_escape(tooltip));
+            }
+            body += &drawing;
+            body += "</g>\n";
+        }
+
+        writeln!(f, "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>")?;
+        let (width, height) = (width + 2.0 * MARGIN, height + 2.0 * MARGIN);
+        writeln!(f, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0:.2}pt\" height=\"{1:.2}pt\" viewBox=\"{2:.2} {2:.2} {0:.2} {1:.2}\">",
+                 width, height, -MARGIN)?;
+        // the metadata comments of the DOT graph
+        if !comments.is_empty() {
+            writeln!(f, "<desc>{}</desc>", xml_escape(&comments.join("\n")))?;
+        }
+        writeln!(f, "<rect x=\"{0:.2}\" y=\"{0:.2}\" width=\"{1:.2}\" height=\"{2:.2}\" fill=\"white\"/>", -MARGIN, width, height)?;
+        write!(f, "{}", body)?;
+        writeln!(f, "</svg>")
+    }
+}
+
+// the name="value" attributes of a DOT statement, unescaped; the HTML-like
+// labels are skipped
+fn attributes(statement: &str) -> Vec<(String, String)> {
+    let mut attributes = vec![];
+    let mut chars = statement.chars();
+    let mut name = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                let mut value = String::new();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => match chars.next() {
+                            Some('n') => value.push('\n'),
+                            Some(c) => value.push(c),
+                            None => break,
+                        },
+                        c => value.push(c),
+                    }
+                }
+                if let Some(name) = name.strip_suffix('=') {
+                    attributes.push((name.to_string(), value));
+                }
+                name.clear();
+            }
+            '<' => {
+                let mut depth = 1;
+                while depth > 0 {
+                    match chars.next() {
+                        Some('<') => depth += 1,
+                        Some('>') => depth -= 1,
+                        Some(_) => {}
+                        None => break,
+                    }
+                }
+                name.clear();
+            }
+            '=' if !name.is_empty() => name.push('='),
+            c if c.is_alphanumeric() || c == '_' => {
+                if name.ends_with('=') {
+                    name.clear();
+                }
+                name.push(c);
+            }
+            _ => name.clear(),
+        }
+    }
+    attributes
+}
+
+// the pen of the xdot operations, as set by the previous ones
+struct Pen<'s> {
+    color: &'s str,
+    fill: &'s str,
+    style: &'s str,
+    width: f64,
+    font: &'s str,
+    font_size: f64,
+}
+
+impl<'s> Pen<'s> {
+    fn stroke(&self) -> String {
+        let mut attributes = format!(" stroke=\"{}\"", xml_escape(self.color));
+        match self.style {
+            "dashed" => attributes += " stroke-dasharray=\"5,2\"",
+            "dotted" => attributes += " stroke-dasharray=\"1,5\"",
+            _ => {}
+        }
+        if self.width != 1.0 {
+            attributes += &format!(" stroke-width=\"{:.2}\"", self.width);
+        }
+        attributes
+    }
+
+    fn fill(&self, filled: bool) -> String {
+        format!(" fill=\"{}\"", if filled { xml_escape(self.fill) } else { String::from("none") })
+    }
+}
+
+// the SVG elements of xdot drawing operations, y going down from height
+fn elements(operations: &str, height: f64) -> String {
+    let mut pen = Pen { color: "black", fill: "black", style: "solid", width: 1.0, font: "Times-Roman", font_size: 14.0 };
+    let mut rest = operations;
+    let mut svg = String::new();
+    while let Some(operation) = token(&mut rest) {
+        let drawn = match operation {
+            "E" | "e" => {
+                let values = numbers(&mut rest, 4);
+                values.map(|v| format!("<ellipse cx=\"{:.2}\" cy=\"{:.2}\" rx=\"{:.2}\" ry=\"{:.2}\"{}{}/>\n",
+                                       v[0], height - v[1], v[2], v[3], pen.fill(operation == "E"), pen.stroke()))
+            }
+            "P" | "p" | "L" => points(&mut rest, height).map(|points| match operation {
+                "L" => format!("<polyline points=\"{}\" fill=\"none\"{}/>\n", points.join(" "), pen.stroke()),
+                _ => format!("<polygon points=\"{}\"{}{}/>\n", points.join(" "), pen.fill(operation == "P"), pen.stroke()),
+            }),
+            "B" | "b" => points(&mut rest, height).filter(|p| !p.is_empty()).map(|points| {
+                format!("<path d=\"M{}C{}\"{}{}/>\n", points[0], points[1..].join(" "), pen.fill(operation == "b"), pen.stroke())
+            }),
+            "T" => numbers(&mut rest, 4).and_then(|v| Some((v, text(&mut rest)?))).map(|(v, text)| {
+                let anchor = match v[2] as i64 {
+                    -1 => "start",
+                    1 => "end",
+                    _ => "middle",
+                };
+                format!("<text x=\"{:.2}\" y=\"{:.2}\" text-anchor=\"{}\" font-family=\"{}\" font-size=\"{:.2}\" fill=\"{}\">{}</text>\n",
+                        v[0], height - v[1], anchor, xml_escape(pen.font), pen.font_size, xml_escape(pen.color), xml_escape(text))
+            }),
+            "c" => text(&mut rest).map(|color| {
+                pen.color = color;
+                String::new()
+            }),
+            "C" => text(&mut rest).map(|color| {
+                pen.fill = color;
+                String::new()
+            }),
+            "S" => text(&mut rest).map(|style| {
+                match style.strip_prefix("setlinewidth(").and_then(|w| w.strip_suffix(')')) {
+                    Some(width) => pen.width = width.parse().unwrap_or(1.0),
+                    None => pen.style = style,
+                }
+                String::new()
+            }),
+            "F" => numbers(&mut rest, 1).and_then(|v| Some((v, text(&mut rest)?))).map(|(v, font)| {
+                pen.font_size = v[0];
+                pen.font = font;
+                String::new()
+            }),
+            // the unknown operations end the drawing as their operands are unknown
+            _ => None,
+        };
+        match drawn {
+            Some(drawn) => svg += &drawn,
+            None => break,
+        }
+    }
+    svg
+}
+
+// the next space separated token of the operations
+fn token<'s>(rest: &mut &'s str) -> Option<&'s str> {
+    let trimmed = rest.trim_start();
+    let end = trimmed.find(' ').unwrap_or(trimmed.len());
+    *rest = &trimmed[end..];
+    Some(&trimmed[..end]).filter(|t| !t.is_empty())
+}
+
+fn numbers(rest: &mut &str, count: usize) -> Option<Vec<f64>> {
+    (0..count).map(|_| token(rest)?.parse().ok()).collect()
+}
+
+// the points of a polygon, a polyline or a spline, as SVG coordinates
+fn points(rest: &mut &str, height: f64) -> Option<Vec<String>> {
+    let count: usize = token(rest)?.parse().ok()?;
+    (0..count).map(|_| numbers(rest, 2).map(|p| format!("{:.2},{:.2}", p[0], height - p[1]))).collect()
+}
+
+// a string operand: its length in bytes, a dash then the bytes
+fn text<'s>(rest: &mut &'s str) -> Option<&'s str> {
+    let length: usize = token(rest)?.parse().ok()?;
+    let value = rest.trim_start().strip_prefix('-')?;
+    let text = value.get(..length)?;
+    *rest = &value[length..];
+    Some(text)
+}
+
+fn xml_escape(value: &str) -> String {
+    value.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,239 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+
+use ratatui;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+
+use symbols_graph::{DotWriter, Graph};
+
+const HELP: &str = "type to search, Up/Down move, Tab switch list, Enter follow edge, Ctrl-E export DOT, Esc quit";
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Pane {
+    Nodes,
+    Dependencies,
+    Dependents,
+    Exports,
+}
+
+impl Pane {
+    fn next(self) -> Self {
+        match self {
+            Pane::Nodes => Pane::Dependencies,
+            Pane::Dependencies => Pane::Dependents,
+            Pane::Dependents => Pane::Exports,
+            Pane::Exports => Pane::Nodes,
+        }
+    }
+}
+
+struct App<'a> {
+    graph: &'a Graph,
+    // every node, sorted by label
+    nodes: Vec<u32>,
+    query: String,
+    // the nodes matching the query
+    matches: Vec<u32>,
+    pane: Pane,
+    states: [ListState; 4],
+    message: String,
+}
+
+// browse the graph until Esc is pressed
+pub(crate) fn explore(graph: &Graph) -> io::Result<()> {
+    let mut terminal = ratatui::try_init()?;
+    let result = App::new(graph).run(&mut terminal);
+    ratatui::try_restore()?;
+    result
+}
+
+impl<'a> App<'a> {
+    fn new(graph: &'a Graph) -> Self {
+        let mut nodes: Vec<u32> = graph.nodes().map(|(n, _)| n).collect();
+        nodes.sort_by_key(|n| graph.label(*n));
+        let mut app = App {
+            graph,
+            matches: nodes.clone(),
+            nodes,
+            query: String::new(),
+            pane: Pane::Nodes,
+            states: Default::default(),
+            message: String::from(HELP),
+        };
+        app.states[0].select(Some(0));
+        app
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> io::Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let key = match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => key,
+                _ => continue,
+            };
+            let control = key.modifiers.contains(KeyModifiers::CONTROL);
+            match key.code {
+                KeyCode::Esc => return Ok(()),
+                KeyCode::Char('c') if control => return Ok(()),
+                KeyCode::Char('e') if control => self.export(),
+                KeyCode::Tab => {
+                    self.pane = self.pane.next();
+                    let len = self.items(self.pane).len();
+                    let state = &mut self.states[self.pane as usize];
+                    if state.selected().is_none() && len > 0 {
+                        state.select(Some(0));
+                    }
+                }
+                KeyCode::Up => self.move_selection(-1),
+                KeyCode::Down => self.move_selection(1),
+                KeyCode::Enter => self.follow(),
+                KeyCode::Backspace => {
+                    self.query.pop();
+                    self.search();
+                }
+                KeyCode::Char(c) if !control => {
+                    self.query.push(c);
+                    self.search();
+                }
+                _ => (),
+            }
+        }
+    }
+
+    // the node shown on the right side
+    fn focused(&self) -> Option<u32> {
+        self.states[Pane::Nodes as usize].selected().and_then(|i| self.matches.get(i).cloned())
+    }
+
+    // the nodes or the symbols listed by a pane, sorted by label
+    fn items(&self, pane: Pane) -> Vec<(Option<u32>, String)> {
+        let graph = self.graph;
+        let focused = match (pane, self.focused()) {
+            (Pane::Nodes, _) => return self.matches.iter().map(|n| (Some(*n), graph.label(*n).unwrap_or_default().to_string())).collect(),
+            (_, Some(focused)) => focused,
+            (_, None) => return vec![],
+        };
+
+        let mut items: Vec<(Option<u32>, String)> = match pane {
+            Pane::Dependencies | Pane::Dependents => graph.edges()
+                .filter_map(|((n1, n2), p)| match pane {
+                    Pane::Dependencies if n1 == focused => Some((n2, p.symbols_count())),
+                    Pane::Dependents if n2 == focused => Some((n1, p.symbols_count())),
+                    _ => None,
+                })
+                .map(|(n, count)| (Some(n), format!("{} ({} symbols)", graph.label(n).unwrap_or_default(), count)))
+                .collect(),
+            _ => graph.nodes()
+                .filter(|(n, _)| *n == focused)
+                .flat_map(|(_, p)| p.exports())
+                .map(|s| (None, s.to_string()))
+                .collect(),
+        };
+        items.sort_by(|a, b| a.1.cmp(&b.1));
+        items
+    }
+
+    // keep the nodes containing the query letters in order, regardless of the case
+    fn search(&mut self) {
+        let query: Vec<char> = self.query.to_lowercase().chars().collect();
+        let graph = self.graph;
+        self.matches = self.nodes.iter()
+            .cloned()
+            .filter(|n| {
+                let mut query = query.iter().peekable();
+                for c in graph.label(*n).unwrap_or_default().to_lowercase().chars() {
+                    if query.peek() == Some(&&c) {
+                        query.next();
+                    }
+                }
+                query.peek().is_none()
+            })
+            .collect();
+        self.pane = Pane::Nodes;
+        self.reset_selection(if self.matches.is_empty() { None } else { Some(0) });
+    }
+
+    fn reset_selection(&mut self, node_index: Option<usize>) {
+        for state in self.states.iter_mut() {
+            state.select(None);
+        }
+        self.states[0].select(node_index);
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        let len = self.items(self.pane).len();
+        if len == 0 {
+            return;
+        }
+        let state = &mut self.states[self.pane as usize];
+        let current = state.selected().unwrap_or(0) as isize;
+        state.select(Some((current + delta).clamp(0, len as isize - 1) as usize));
+        if self.pane == Pane::Nodes {
+            for state in self.states[1..].iter_mut() {
+                state.select(None);
+            }
+        }
+    }
+
+    // focus the node on the other side of the selected edge
+    fn follow(&mut self) {
+        let selected = self.states[self.pane as usize].selected();
+        let target = match selected.and_then(|i| self.items(self.pane).get(i).cloned()) {
+            Some((Some(node), _)) if self.pane != Pane::Nodes => node,
+            _ => return,
+        };
+        self.query.clear();
+        self.matches = self.nodes.clone();
+        self.pane = Pane::Nodes;
+        let index = self.matches.iter().position(|n| *n == target);
+        self.reset_selection(index);
+    }
+
+    // write the focused node with its direct neighbors to LABEL.dot
+    fn export(&mut self) {
+        let focused = match self.focused() {
+            Some(focused) => focused,
+            None => return,
+        };
+        let mut nodes = BTreeSet::new();
+        nodes.insert(focused);
+        nodes.extend(self.graph.dependencies_of(focused));
+        nodes.extend(self.graph.dependents_of(focused));
+
+        let path = format!("{}.dot", self.graph.label(focused).unwrap_or("subgraph").replace('/', "_"));
+        let dot = DotWriter::new(&self.graph.subgraph(&nodes)).to_string();
+        self.message = match fs::write(&path, dot) {
+            Ok(()) => format!("Exported {} nodes to {}", nodes.len(), path),
+            Err(error) => format!("Unable to write {} : {}", path, error),
+        };
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, status] = Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        let [left, right] = Layout::horizontal([Constraint::Percentage(40), Constraint::Percentage(60)]).areas(main);
+        let [search, nodes] = Layout::vertical([Constraint::Length(3), Constraint::Min(1)]).areas(left);
+        let [dependencies, dependents, exports] = Layout::vertical([Constraint::Ratio(1, 3); 3]).areas(right);
+
+        frame.render_widget(Paragraph::new(self.query.as_str()).block(Block::default().borders(Borders::ALL).title("Search")), search);
+        let title = format!("Nodes {}/{}", self.matches.len(), self.nodes.len());
+        self.draw_list(frame, Pane::Nodes, &title, nodes);
+        self.draw_list(frame, Pane::Dependencies, "Dependencies", dependencies);
+        self.draw_list(frame, Pane::Dependents, "Dependents", dependents);
+        self.draw_list(frame, Pane::Exports, "Exports", exports);
+        frame.render_widget(Paragraph::new(self.message.as_str()), status);
+    }
+
+    fn draw_list(&mut self, frame: &mut Frame, pane: Pane, title: &str, area: Rect) {
+        let items: Vec<ListItem> = self.items(pane).into_iter().map(|(_, label)| ListItem::new(label)).collect();
+        let border = if pane == self.pane { Style::default().fg(Color::Yellow) } else { Style::default() };
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).border_style(border).title(title.to_string()))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, area, &mut self.states[pane as usize]);
+    }
+}
@@ -0,0 +1,52 @@
+use std::fmt;
+
+use graph::{Edge, Graph, Node};
+
+/// An output format rendering a [`Graph`] a piece at a time, driven by
+/// [`Graph::write`]; [`DotWriter`](crate::DotWriter) is one.
+///
+/// ```
+/// # use std::fmt;
+/// # use symbols_graph::{Edge, Graph, GraphBuilder, GraphWriter, Node};
+/// // a Mermaid flowchart
+/// struct Mermaid;
+///
+/// impl GraphWriter for Mermaid {
+///     fn write_header(&mut self, _: &Graph, out: &mut dyn fmt::Write) -> fmt::Result {
+///         writeln!(out, "flowchart LR")
+///     }
+///
+///     fn write_node(&mut self, node: Node, _: Option<&str>, out: &mut dyn fmt::Write) -> fmt::Result {
+///         writeln!(out, "    n{}[\"{}\"]", node.id(), node.label())
+///     }
+///
+///     fn write_edge(&mut self, edge: Edge, out: &mut dyn fmt::Write) -> fmt::Result {
+///         let (importer, exporter) = edge.id();
+///         writeln!(out, "    n{} --> n{}", importer, exporter)
+///     }
+/// }
+///
+/// let mut builder = GraphBuilder::new("deps");
+/// builder.add_symbols("app", Vec::<String>::new(), ["foo"]).add_symbols("libfoo.so", ["foo"], Vec::<String>::new());
+/// let graph = builder.finish();
+/// let mut out = String::new();
+/// graph.write(&mut Mermaid, &mut out).unwrap();
+/// assert_eq!(out.lines().count(), 4);
+/// ```
+pub trait GraphWriter {
+    /// Start the output, before the first node.
+    fn write_header(&mut self, graph: &Graph, out: &mut dyn fmt::Write) -> fmt::Result;
+
+    /// Write a node, with the name of its cluster if grouped; the nodes of a
+    /// cluster come one after the other.
+    fn write_node(&mut self, node: Node, cluster: Option<&str>, out: &mut dyn fmt::Write) -> fmt::Result;
+
+    /// Write an edge, once every node is written.
+    fn write_edge(&mut self, edge: Edge, out: &mut dyn fmt::Write) -> fmt::Result;
+
+    /// End the output, after the last edge.
+    fn finish(&mut self, graph: &Graph, out: &mut dyn fmt::Write) -> fmt::Result {
+        let _ = (graph, out);
+        Ok(())
+    }
+}